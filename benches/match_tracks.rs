@@ -0,0 +1,96 @@
+//! Times the matching hot path, for comparing changes to it:
+//!
+//! ```sh
+//! cargo bench --bench match_tracks
+//! ```
+//!
+//! Every other track only matches by name, so both the ISRC index and the title comparisons are
+//! exercised.
+
+use std::{hint::black_box, time::Instant};
+
+use apple_music_album_library_migrator::{
+    apple_music::custom_types::{Album, TrackNoLibrary, TrackWithLibrary},
+    matching::{MatchOptions, match_tracks},
+};
+
+fn tracks(album: u32, count: u32) -> Vec<TrackNoLibrary> {
+    (0..count)
+        .map(|i| TrackNoLibrary {
+            catalog_id: format!("{album}{i:04}").parse().unwrap(),
+            name: format!("Song {:08x} (feat. Someone)", i.wrapping_mul(2_654_435_761)),
+            artist_name: "Artist & Band".to_owned(),
+            genre_names: vec![],
+            disc_number: 1,
+            track_number: (i % 250) as u8 + 1,
+            content_rating: None,
+            isrc: (i % 2 == 0).then(|| format!("ISRC{i}")),
+            release_date: None,
+            duration: None,
+            playable: true,
+        })
+        .collect()
+}
+
+fn album<Track>(catalog_id: &str, tracks: Vec<Track>) -> Album<Track> {
+    Album {
+        catalog_id: catalog_id.parse().unwrap(),
+        name: "Album".to_owned(),
+        artist_name: "Artist & Band".to_owned(),
+        genre_names: vec![],
+        release_date: None,
+        upc: None,
+        audio_variants: vec![],
+        apple_digital_master: false,
+        tracks,
+    }
+}
+
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    // Warm up
+    for _ in 0..iterations / 10 {
+        f();
+    }
+    let started = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    println!(
+        "{name}: {:.1} µs",
+        started.elapsed().as_secs_f64() * 1e6 / f64::from(iterations),
+    );
+}
+
+fn main() {
+    let source = album(
+        "1",
+        tracks(1, 20)
+            .into_iter()
+            .map(|track| TrackWithLibrary {
+                catalog_id: track.catalog_id,
+                name: track.name,
+                artist_name: track.artist_name,
+                genre_names: track.genre_names,
+                disc_number: track.disc_number,
+                track_number: track.track_number,
+                content_rating: track.content_rating,
+                isrc: track.isrc,
+                release_date: track.release_date,
+                duration: track.duration,
+                library_id: None,
+                date_added: None,
+            })
+            .collect(),
+    );
+    let options = MatchOptions::default();
+
+    let destination = album("2", tracks(2, 20));
+    bench("20 tracks against 20", 20_000, || {
+        black_box(match_tracks(black_box(&source), black_box(&destination), &options).unwrap());
+    });
+
+    let destination = album("2", tracks(2, 200));
+    bench("20 tracks against 200", 200, || {
+        black_box(match_tracks(black_box(&source), black_box(&destination), &options).unwrap());
+    });
+}
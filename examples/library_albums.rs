@@ -0,0 +1,35 @@
+//! Lists the albums in a library using the library target, with the bundled examples unless
+//! `DEVELOPER_TOKEN` and `USER_TOKEN` are set (and `STOREFRONT` if the account isn't in the US):
+//!
+//! ```sh
+//! DEVELOPER_TOKEN=… USER_TOKEN=… cargo run --example library_albums
+//! ```
+
+use anyhow::Result;
+use apple_music_album_library_migrator::apple_music::{Client, ParseMode};
+use futures_util::TryStreamExt;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    let client = match (
+        std::env::var("DEVELOPER_TOKEN"),
+        std::env::var("USER_TOKEN"),
+    ) {
+        (Ok(developer_token), Ok(user_token)) => {
+            let storefront = std::env::var("STOREFRONT").unwrap_or_else(|_| "us".to_owned());
+            Client::builder(developer_token, storefront)
+                .user_token(user_token)
+                .build()?
+        }
+        _ => Client::offline(ParseMode::Strict),
+    };
+
+    let mut albums = std::pin::pin!(client.library_albums());
+    while let Some(album) = albums.try_next().await? {
+        println!(
+            "{} - {} ({})",
+            album.artist_name, album.name, album.library_id
+        );
+    }
+    Ok(())
+}
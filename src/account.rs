@@ -0,0 +1,32 @@
+use anyhow::Result;
+
+use crate::apple_music;
+
+/// Print what the API reveals about the account the user token belongs to, to check it's the
+/// intended one before making changes
+pub async fn whoami(client: &apple_music::Client) -> Result<()> {
+    let response = client.get_account_storefront().await?;
+    let storefront = response.storefront()?;
+    println!("Storefront: {} ({})", storefront.name(), storefront.id());
+    println!("Language: {}", storefront.default_language_tag());
+    if let Some(policy) = storefront.explicit_content_policy() {
+        println!("Explicit content: {policy}");
+    }
+
+    let library = client.get_library_albums_summary().await?;
+    match library.total() {
+        Some(total) => println!("Library: {total} albums"),
+        None => println!("Library: readable"),
+    }
+
+    if storefront.id() != client.storefront() {
+        println!();
+        println!(
+            "Warning: the account's storefront is `{}` but `--storefront` is `{}`, catalog IDs \
+             may not match the library",
+            storefront.id(),
+            client.storefront(),
+        );
+    }
+    Ok(())
+}
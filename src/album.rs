@@ -0,0 +1,152 @@
+use anyhow::{Result, ensure};
+
+use crate::{
+    apple_music::{self, custom_types},
+    discovery, migrate, output,
+};
+
+/// The album to list the editions of
+pub enum Editions {
+    Of(apple_music::CatalogAlbumId),
+    Search { artist_name: String, name: String },
+}
+
+pub async fn info(
+    client: &apple_music::Client,
+    catalog_id: &apple_music::CatalogAlbumId,
+) -> Result<()> {
+    let album: custom_types::Album<custom_types::TrackNoLibrary> =
+        client.get_catalog_album(catalog_id).await?.try_into()?;
+    ensure!(album.catalog_id == *catalog_id);
+
+    println!("\"{}\" by {}", album.name, album.artist_name);
+    println!("Catalog ID: {}", album.catalog_id);
+    println!(
+        "Released: {}",
+        output::format_release_date(album.release_date.as_deref()),
+    );
+    if let Some(upc) = &album.upc {
+        println!("UPC: {upc}");
+    }
+    if let Some(genres) = output::format_genres(&album.genre_names) {
+        println!("Genres: {genres}");
+    }
+    if let Some(badges) =
+        output::format_audio_badges(&album.audio_variants, album.apple_digital_master)
+    {
+        println!("Audio: {badges}");
+    }
+    println!();
+
+    println!("Tracks:");
+    let multi_disc = album.tracks.iter().any(|t| t.disc_number > 1);
+    let width = output::terminal_width();
+    for track in &album.tracks {
+        let content_rating = output::format_content_rating(track.content_rating.as_ref());
+        let position = output::format_position(track.disc_number, track.track_number, multi_disc);
+        let other = format!("  #{position} {content_rating}");
+        if track.artist_name != album.artist_name {
+            let [name, artist] = output::fit_names(
+                [&track.name, &track.artist_name],
+                other.chars().count() + " by ".len(),
+                width,
+            );
+            println!("  #{position} {name} by {artist}{content_rating}");
+        } else {
+            let [name] = output::fit_names([&track.name], other.chars().count(), width);
+            println!("  #{position} {name}{content_rating}");
+        }
+    }
+    Ok(())
+}
+
+/// `[E]` if any track is explicit, otherwise `[C]` if any is a clean version
+fn album_content_rating(album: &custom_types::Album<custom_types::TrackNoLibrary>) -> &'static str {
+    let has = |rating: &custom_types::ContentRating| {
+        album
+            .tracks
+            .iter()
+            .any(|t| t.content_rating.as_ref() == Some(rating))
+    };
+    output::format_content_rating(
+        [
+            custom_types::ContentRating::Explicit,
+            custom_types::ContentRating::Clean,
+        ]
+        .iter()
+        .find(|rating| has(rating)),
+    )
+}
+
+/// Print every edition of an album with its catalog ID, release date and track count, oldest
+/// first
+pub async fn editions(client: &apple_music::Client, query: Editions) -> Result<()> {
+    let catalog_ids = match &query {
+        Editions::Of(catalog_id) => {
+            let album = migrate::load_destination(client, catalog_id).await?;
+            let mut catalog_ids = discovery::other_editions(
+                client,
+                &album.catalog_id,
+                &album.artist_name,
+                &album.name,
+            )
+            .await?;
+            catalog_ids.insert(0, catalog_id.clone());
+            catalog_ids
+        }
+        Editions::Search { artist_name, name } => {
+            discovery::search_editions(client, artist_name, name).await?
+        }
+    };
+
+    let mut albums = Vec::new();
+    for catalog_id in catalog_ids {
+        if albums
+            .iter()
+            .any(|album: &custom_types::Album<_>| album.catalog_id == catalog_id)
+        {
+            continue;
+        }
+        match migrate::load_destination(client, &catalog_id).await {
+            Ok(album) => albums.push(album),
+            Err(error) => eprintln!("Skipping {catalog_id}: {error:#}"),
+        }
+    }
+    // Searching also finds the artist's other albums
+    if let Editions::Search { name, .. } = &query {
+        albums.retain(|album| {
+            discovery::base_title(&album.name).eq_ignore_ascii_case(discovery::base_title(name))
+        });
+    }
+    ensure!(!albums.is_empty(), "no editions found");
+    // Albums without a release date yet are the newest
+    albums.sort_by_key(|album| {
+        (
+            album.release_date.is_none(),
+            album.release_date.clone(),
+            album.tracks.len(),
+        )
+    });
+
+    let width = output::terminal_width();
+    for album in &albums {
+        let content_rating = album_content_rating(album);
+        let badges = output::format_audio_badges(&album.audio_variants, album.apple_digital_master)
+            .map(|badges| format!(" ({badges})"))
+            .unwrap_or_default();
+        let other = format!(
+            "{}  {}  {:>3} tracks  {content_rating}{badges}",
+            album.catalog_id,
+            output::format_release_date(album.release_date.as_deref()),
+            album.tracks.len(),
+        );
+        let [name] = output::fit_names([&album.name], other.chars().count(), width);
+        println!(
+            "{}  {}  {:>3} tracks  {name}{content_rating}{badges}",
+            album.catalog_id,
+            output::format_release_date(album.release_date.as_deref()),
+            album.tracks.len(),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+//! Response from `/v1/catalog/{storefront}/albums/{id}/view/{view}`, e.g. the other versions of
+//! an album
+
+use serde::Deserialize;
+
+use super::search::Album;
+
+#[derive(Deserialize)]
+pub struct Root {
+    /// Empty if the album has no such albums
+    pub(in crate::apple_music) data: Vec<Album>,
+}
+
+impl Root {
+    pub fn albums(&self) -> &[Album] {
+        &self.data
+    }
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
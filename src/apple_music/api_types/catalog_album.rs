@@ -17,10 +17,19 @@ pub struct Album {
 pub struct AlbumAttributes {
     /// All of the album's artists
     pub(in crate::apple_music) artist_name: String,
+    /// Only given with `extend=audioVariants`, e.g. `lossless` or `dolby-atmos`
+    #[serde(default)]
+    pub(in crate::apple_music) audio_variants: Vec<String>,
+    #[serde(default)]
+    pub(in crate::apple_music) genre_names: Vec<String>,
+    /// Apple Digital Master
+    #[serde(default)]
+    pub(in crate::apple_music) is_mastered_for_itunes: bool,
     pub(in crate::apple_music) name: String,
-    /// YYYY-MM-DD
-    pub(in crate::apple_music) release_date: String,
+    /// YYYY-MM-DD, missing for some pre-release albums
+    pub(in crate::apple_music) release_date: Option<String>,
     pub(in crate::apple_music) track_count: u8,
+    pub(in crate::apple_music) upc: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -37,6 +46,17 @@ pub struct AlbumRelationshipsTracks {
 pub struct Song {
     pub(in crate::apple_music) attributes: SongAttributes,
     pub(in crate::apple_music) id: String,
+    #[serde(rename = "type")]
+    pub(in crate::apple_music) kind: SongType,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SongType {
+    Songs,
+    MusicVideos,
+    #[serde(untagged)]
+    Other(String),
 }
 
 #[derive(Deserialize)]
@@ -46,15 +66,46 @@ pub struct SongAttributes {
     pub(in crate::apple_music) artist_name: String,
     pub(in crate::apple_music) content_rating: Option<ContentRating>,
     pub(in crate::apple_music) disc_number: u8,
-    pub(in crate::apple_music) isrc: String,
+    pub(in crate::apple_music) duration_in_millis: Option<u64>,
+    #[serde(default)]
+    pub(in crate::apple_music) genre_names: Vec<String>,
+    /// Missing for some songs and most music videos
+    pub(in crate::apple_music) isrc: Option<String>,
     pub(in crate::apple_music) name: String,
-    /// YYYY-MM-DD
-    pub(in crate::apple_music) release_date: String,
+    /// Missing for songs that can't be played in the storefront, e.g. region-restricted ones
+    pub(in crate::apple_music) play_params: Option<serde::de::IgnoredAny>,
+    /// YYYY-MM-DD, missing for some tracks of pre-release albums
+    pub(in crate::apple_music) release_date: Option<String>,
     pub(in crate::apple_music) track_number: u8,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ContentRating {
+    Clean,
     Explicit,
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        self.data
+            .iter()
+            .flat_map(|album| &album.relationships.tracks.data)
+            .flat_map(|song| {
+                let content_rating = match &song.attributes.content_rating {
+                    Some(ContentRating::Other(value)) => {
+                        Some(format!("content rating `{value}` on song {}", song.id))
+                    }
+                    _ => None,
+                };
+                let kind = match &song.kind {
+                    SongType::Other(value) => Some(format!("track type `{value}` of {}", song.id)),
+                    SongType::Songs | SongType::MusicVideos => None,
+                };
+                content_rating.into_iter().chain(kind)
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,36 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Root {
+    pub(in crate::apple_music) data: Vec<Song>,
+}
+
+#[derive(Deserialize)]
+pub struct Song {
+    pub(in crate::apple_music) id: String,
+    pub(in crate::apple_music) attributes: SongAttributes,
+}
+
+#[derive(Deserialize)]
+pub struct SongAttributes {
+    pub(in crate::apple_music) isrc: Option<String>,
+}
+
+impl Root {
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.data.iter().map(|song| song.id.as_str())
+    }
+
+    /// `(isrc, catalog_id)` of every song that has an ISRC
+    pub fn ids_by_isrc(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.data
+            .iter()
+            .filter_map(|song| Some((song.attributes.isrc.as_deref()?, song.id.as_str())))
+    }
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
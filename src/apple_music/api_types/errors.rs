@@ -0,0 +1,47 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Root {
+    pub(in crate::apple_music) errors: Vec<Error>,
+}
+
+#[derive(Deserialize)]
+pub struct Error {
+    pub(in crate::apple_music) code: Option<String>,
+    pub(in crate::apple_music) title: Option<String>,
+    pub(in crate::apple_music) detail: Option<String>,
+}
+
+impl Error {
+    /// The most descriptive message available, e.g. `Resource Not Found: No album with ID 123`
+    pub fn message(&self) -> String {
+        match (self.title.as_deref(), self.detail.as_deref()) {
+            (Some(title), Some(detail)) if title != detail => format!("{title}: {detail}"),
+            (Some(message), _) | (None, Some(message)) => message.to_owned(),
+            (None, None) => match &self.code {
+                Some(code) => format!("error code {code}"),
+                None => "unknown error".to_owned(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_messages() {
+        let body = r#"{"errors":[{"id":"ABC","title":"Resource Not Found","detail":"Resource with requested id was not found","status":"404","code":"40400"},{"title":"Forbidden","detail":"Forbidden"},{"code":"50000"}]}"#;
+        let root: Root = serde_json::from_str(body).unwrap();
+        let messages: Vec<String> = root.errors.iter().map(Error::message).collect();
+        assert_eq!(
+            messages,
+            [
+                "Resource Not Found: Resource with requested id was not found",
+                "Forbidden",
+                "error code 50000",
+            ],
+        );
+    }
+}
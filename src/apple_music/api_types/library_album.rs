@@ -1,3 +1,5 @@
+//! Responses from `/v1/me/library/albums/{id}` and `/v1/me/library/albums/{id}/tracks`
+
 use anyhow::{Result, ensure};
 use serde::Deserialize;
 
@@ -9,9 +11,18 @@ pub struct Root {
 #[derive(Deserialize)]
 pub struct LibraryAlbum {
     pub(in crate::apple_music) id: String,
+    pub(in crate::apple_music) attributes: LibraryAlbumAttributes,
     pub(in crate::apple_music) relationships: LibraryAlbumRelationshipsWithTracksCatalog,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryAlbumAttributes {
+    pub(in crate::apple_music) artist_name: String,
+    pub(in crate::apple_music) date_added: Option<String>,
+    pub(in crate::apple_music) name: String,
+}
+
 #[derive(Deserialize)]
 pub struct LibraryAlbumRelationshipsWithTracksCatalog {
     pub(in crate::apple_music) catalog: LibraryAlbumRelationshipsCatalog,
@@ -31,6 +42,15 @@ pub struct LibraryAlbumCatalog {
 #[derive(Deserialize)]
 pub struct LibraryAlbumRelationshipsTracks {
     pub(in crate::apple_music) data: Vec<LibrarySong>,
+    /// Path of the next page if the included tracks were cut off, as for large box sets
+    pub(in crate::apple_music) next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TracksRoot {
+    pub(in crate::apple_music) data: Vec<LibrarySong>,
+    /// Path of the next page, if there is one
+    pub(in crate::apple_music) next: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -42,13 +62,23 @@ pub struct LibrarySong {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LibrarySongAttributes {
+    pub(in crate::apple_music) date_added: Option<String>,
+    pub(in crate::apple_music) name: String,
+    #[serde(default)]
     pub(in crate::apple_music) play_params: LibrarySongPlayParams,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct LibrarySongPlayParams {
-    pub(in crate::apple_music) catalog_id: String,
+    /// Missing for uploaded songs and songs no longer in the catalog
+    pub(in crate::apple_music) catalog_id: Option<String>,
+}
+
+impl LibrarySong {
+    pub(in crate::apple_music) fn catalog_id(&self) -> Option<&str> {
+        self.attributes.play_params.catalog_id.as_deref()
+    }
 }
 
 impl Root {
@@ -65,3 +95,84 @@ impl Root {
         Ok(&album.id)
     }
 }
+
+impl super::Page for TracksRoot {
+    type Item = LibrarySong;
+
+    fn has_next(&self) -> bool {
+        self.next.is_some()
+    }
+
+    fn into_items(self) -> Vec<LibrarySong> {
+        self.data
+    }
+}
+
+fn no_catalog_id_warnings<'a>(songs: impl IntoIterator<Item = &'a LibrarySong>) -> Vec<String> {
+    songs
+        .into_iter()
+        .filter(|song| song.catalog_id().is_none())
+        .map(|song| {
+            format!(
+                "library song {} (\"{}\") has no catalog ID, as for uploaded songs and songs no \
+                 longer in the catalog, it will be ignored",
+                song.id, song.attributes.name,
+            )
+        })
+        .collect()
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        // Cut off tracks are requested again page by page, which warns about them instead
+        no_catalog_id_warnings(
+            self.data
+                .iter()
+                .filter(|album| album.relationships.tracks.next.is_none())
+                .flat_map(|album| &album.relationships.tracks.data),
+        )
+    }
+}
+
+impl super::UnknownValues for TracksRoot {
+    fn unknown_values(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        no_catalog_id_warnings(&self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::UnknownValues;
+    use super::*;
+
+    #[test]
+    fn test_warnings_skip_cut_off_tracks() {
+        let response = |next: &str| {
+            serde_json::from_str::<Root>(&format!(
+                r#"{{"data": [{{
+                    "id": "l.1",
+                    "attributes": {{"artistName": "Artist", "name": "Album"}},
+                    "relationships": {{
+                        "catalog": {{"data": [{{"id": "1"}}]}},
+                        "tracks": {{"data": [{{"id": "i.1", "attributes": {{"name": "Song"}}}}]{next}}}
+                    }}
+                }}]}}"#,
+            ))
+            .unwrap()
+        };
+        assert_eq!(response("").warnings().len(), 1);
+        assert!(
+            response(r#", "next": "/v1/me/library/albums/l.1/tracks?offset=100""#)
+                .warnings()
+                .is_empty()
+        );
+    }
+}
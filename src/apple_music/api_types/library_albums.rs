@@ -0,0 +1,57 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Root {
+    pub(in crate::apple_music) data: Vec<LibraryAlbum>,
+    /// Path of the next page, if there is one
+    pub(in crate::apple_music) next: Option<String>,
+    pub(in crate::apple_music) meta: Option<Meta>,
+}
+
+#[derive(Deserialize)]
+pub struct Meta {
+    pub(in crate::apple_music) total: usize,
+}
+
+#[derive(Deserialize)]
+pub struct LibraryAlbum {
+    pub(in crate::apple_music) id: String,
+    pub(in crate::apple_music) attributes: LibraryAlbumAttributes,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryAlbumAttributes {
+    pub(in crate::apple_music) name: String,
+    /// All of the album's artists
+    pub(in crate::apple_music) artist_name: String,
+}
+
+impl Root {
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.data.iter().map(|album| album.id.as_str())
+    }
+
+    /// Number of albums in the library across all pages
+    pub fn total(&self) -> Option<usize> {
+        self.meta.as_ref().map(|meta| meta.total)
+    }
+}
+
+impl super::Page for Root {
+    type Item = LibraryAlbum;
+
+    fn has_next(&self) -> bool {
+        self.next.is_some()
+    }
+
+    fn into_items(self) -> Vec<LibraryAlbum> {
+        self.data
+    }
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
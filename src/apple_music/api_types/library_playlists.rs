@@ -0,0 +1,110 @@
+//! Responses from `/v1/me/library/playlists` and `/v1/me/library/playlists/{id}/tracks`
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Root {
+    pub(in crate::apple_music) data: Vec<Playlist>,
+    /// Path of the next page, if there is one
+    pub(in crate::apple_music) next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Playlist {
+    pub(in crate::apple_music) id: String,
+    pub(in crate::apple_music) attributes: PlaylistAttributes,
+}
+
+#[derive(Deserialize)]
+pub struct PlaylistAttributes {
+    pub(in crate::apple_music) name: String,
+}
+
+#[derive(Deserialize)]
+pub struct TracksRoot {
+    pub(in crate::apple_music) data: Vec<Track>,
+    /// Path of the next page, if there is one
+    pub(in crate::apple_music) next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Track {
+    pub(in crate::apple_music) attributes: TrackAttributes,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackAttributes {
+    pub(in crate::apple_music) name: String,
+    pub(in crate::apple_music) artist_name: String,
+    /// Missing for uploaded songs and music videos
+    pub(in crate::apple_music) play_params: Option<PlayParams>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayParams {
+    /// Missing for songs that aren't from the catalog, e.g. uploaded ones
+    pub(in crate::apple_music) catalog_id: Option<String>,
+}
+
+impl Playlist {
+    /// Starts with `p.`
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.attributes.name
+    }
+}
+
+impl Track {
+    pub fn name(&self) -> &str {
+        &self.attributes.name
+    }
+
+    pub fn artist_name(&self) -> &str {
+        &self.attributes.artist_name
+    }
+
+    pub fn catalog_id(&self) -> Option<&str> {
+        self.attributes.play_params.as_ref()?.catalog_id.as_deref()
+    }
+}
+
+impl super::Page for Root {
+    type Item = Playlist;
+
+    fn has_next(&self) -> bool {
+        self.next.is_some()
+    }
+
+    fn into_items(self) -> Vec<Playlist> {
+        self.data
+    }
+}
+
+impl super::Page for TracksRoot {
+    type Item = Track;
+
+    fn has_next(&self) -> bool {
+        self.next.is_some()
+    }
+
+    fn into_items(self) -> Vec<Track> {
+        self.data
+    }
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl super::UnknownValues for TracksRoot {
+    fn unknown_values(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
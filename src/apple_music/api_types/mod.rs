@@ -1,2 +1,31 @@
+pub mod album_views;
 pub mod catalog_album;
+pub mod catalog_songs;
+pub mod errors;
 pub mod library_album;
+pub mod library_albums;
+pub mod library_playlists;
+pub mod ratings;
+pub mod recent;
+pub mod search;
+pub mod storefront;
+
+/// Implemented by response types to report values that were accepted but aren't known to this tool
+pub trait UnknownValues {
+    /// Human-readable descriptions of each unknown value
+    fn unknown_values(&self) -> Vec<String>;
+
+    /// Expected oddities that are handled, such as uploaded songs, printed whatever the parse mode
+    fn warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Implemented by list responses that the client pages through by offset
+pub trait Page: serde::de::DeserializeOwned + UnknownValues {
+    type Item;
+
+    fn has_next(&self) -> bool;
+
+    fn into_items(self) -> Vec<Self::Item>;
+}
@@ -0,0 +1,33 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Root {
+    pub(in crate::apple_music) data: Vec<Rating>,
+}
+
+#[derive(Deserialize)]
+pub struct Rating {
+    pub(in crate::apple_music) attributes: RatingAttributes,
+}
+
+#[derive(Deserialize)]
+pub struct RatingAttributes {
+    /// 1 for a favorite and -1 for suggesting less like it
+    pub(in crate::apple_music) value: i8,
+}
+
+impl Root {
+    pub fn is_favorite(&self) -> bool {
+        self.data.iter().any(|rating| rating.attributes.value == 1)
+    }
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        self.data
+            .iter()
+            .filter(|rating| !matches!(rating.attributes.value, 1 | -1))
+            .map(|rating| format!("rating value {}", rating.attributes.value))
+            .collect()
+    }
+}
@@ -0,0 +1,33 @@
+//! Responses from `/v1/me/recent/played` and `/v1/me/history/heavy-rotation`, which mix albums,
+//! playlists and stations
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Root {
+    pub(in crate::apple_music) data: Vec<Resource>,
+}
+
+#[derive(Deserialize)]
+pub struct Resource {
+    pub(in crate::apple_music) id: String,
+    #[serde(rename = "type")]
+    pub(in crate::apple_music) kind: String,
+}
+
+impl Root {
+    /// IDs of the catalog (`albums`) and library (`library-albums`) albums, in the order given
+    pub fn album_ids(&self) -> impl Iterator<Item = &str> {
+        self.data
+            .iter()
+            .filter(|resource| matches!(resource.kind.as_str(), "albums" | "library-albums"))
+            .map(|resource| resource.id.as_str())
+    }
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        // Other kinds of resources are expected and skipped
+        Vec::new()
+    }
+}
@@ -0,0 +1,67 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Root {
+    pub(in crate::apple_music) results: Results,
+}
+
+#[derive(Deserialize)]
+pub struct Results {
+    /// Missing if nothing was found
+    pub(in crate::apple_music) albums: Option<Albums>,
+}
+
+#[derive(Deserialize)]
+pub struct Albums {
+    pub(in crate::apple_music) data: Vec<Album>,
+    /// Path of the next page, if there is one
+    pub(in crate::apple_music) next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Album {
+    pub(in crate::apple_music) id: String,
+    pub(in crate::apple_music) attributes: AlbumAttributes,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumAttributes {
+    /// All of the album's artists
+    pub(in crate::apple_music) artist_name: String,
+}
+
+impl super::Page for Root {
+    type Item = Album;
+
+    fn has_next(&self) -> bool {
+        self.results
+            .albums
+            .as_ref()
+            .is_some_and(|albums| albums.next.is_some())
+    }
+
+    fn into_items(self) -> Vec<Album> {
+        self.results
+            .albums
+            .map(|albums| albums.data)
+            .unwrap_or_default()
+    }
+}
+
+impl Album {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// All of the album's artists
+    pub fn artist_name(&self) -> &str {
+        &self.attributes.artist_name
+    }
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::{Result, ensure};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Root {
+    pub(in crate::apple_music) data: Vec<Storefront>,
+}
+
+#[derive(Deserialize)]
+pub struct Storefront {
+    pub(in crate::apple_music) id: String,
+    pub(in crate::apple_music) attributes: StorefrontAttributes,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorefrontAttributes {
+    pub(in crate::apple_music) name: String,
+    pub(in crate::apple_music) default_language_tag: String,
+    /// `allowed`, `opt-in` or `prohibited`
+    pub(in crate::apple_music) explicit_content_policy: Option<String>,
+}
+
+impl Root {
+    pub fn storefront(&self) -> Result<&Storefront> {
+        ensure!(self.data.len() == 1);
+        Ok(&self.data[0])
+    }
+}
+
+impl Storefront {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.attributes.name
+    }
+
+    pub fn default_language_tag(&self) -> &str {
+        &self.attributes.default_language_tag
+    }
+
+    pub fn explicit_content_policy(&self) -> Option<&str> {
+        self.attributes.explicit_content_policy.as_deref()
+    }
+}
+
+impl super::UnknownValues for Root {
+    fn unknown_values(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
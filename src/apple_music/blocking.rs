@@ -0,0 +1,86 @@
+//! A synchronous [`Client`](super::Client) for scripts that don't otherwise use async, each call
+//! runs the request on a runtime owned by the client. Calling it from within an async runtime
+//! panics.
+
+use anyhow::{Context, Result};
+use futures_util::TryStreamExt;
+
+use crate::apple_music::{
+    self, CatalogAlbumId, CatalogSongId, LibraryAlbumId,
+    custom_types::{Album, LibraryAlbum, LibraryAlbumSummary, TrackNoLibrary},
+};
+
+pub struct Client {
+    client: apple_music::Client,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Client {
+    pub fn new(client: apple_music::Client) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("failed to start the runtime")?;
+        Ok(Self { client, runtime })
+    }
+
+    /// The async client, e.g. for its storefront or rate limits
+    pub fn inner(&self) -> &apple_music::Client {
+        &self.client
+    }
+
+    pub fn get_catalog_album(&self, catalog_id: &CatalogAlbumId) -> Result<Album<TrackNoLibrary>> {
+        self.runtime
+            .block_on(self.client.get_catalog_album(catalog_id))?
+            .try_into()
+    }
+
+    pub fn get_library_album(&self, library_id: &LibraryAlbumId) -> Result<LibraryAlbum> {
+        self.runtime
+            .block_on(self.client.get_library_album(library_id))?
+            .try_into()
+    }
+
+    /// Every album in the library, fetching all of the pages first
+    pub fn library_albums(&self) -> Result<Vec<LibraryAlbumSummary>> {
+        self.runtime
+            .block_on(self.client.library_albums().try_collect())
+    }
+
+    pub fn add_songs_to_library(&self, catalog_ids: &[&CatalogSongId]) -> Result<()> {
+        self.runtime
+            .block_on(self.client.add_songs_to_library(catalog_ids))
+    }
+
+    pub fn add_album_to_library(&self, catalog_id: &CatalogAlbumId) -> Result<()> {
+        self.runtime
+            .block_on(self.client.add_album_to_library(catalog_id))
+    }
+
+    pub fn remove_album_from_library(&self, library_id: &LibraryAlbumId) -> Result<()> {
+        self.runtime
+            .block_on(self.client.remove_album_from_library(library_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_offline() {
+        let client =
+            Client::new(apple_music::Client::offline(apple_music::ParseMode::Strict)).unwrap();
+        let album = client
+            .get_catalog_album(&"1000000001".parse().unwrap())
+            .unwrap();
+        assert_eq!(album.catalog_id, "1000000001");
+        let albums = client.library_albums().unwrap();
+        assert_eq!(albums[0].library_id, "l.DemoStandard");
+        assert!(
+            client
+                .remove_album_from_library(&albums[0].library_id)
+                .is_err()
+        );
+    }
+}
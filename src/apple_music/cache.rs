@@ -0,0 +1,275 @@
+//! Catalog responses kept on disk between runs, in a SQLite database in the cache directory, so
+//! that albums looked at in an earlier run aren't requested again. Library responses are never
+//! stored since adding and removing albums changes them.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::apple_music::CatalogAlbumId;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS responses (
+    url TEXT PRIMARY KEY,
+    -- Parsed from the URL, for `cache clear`
+    storefront TEXT,
+    album_id TEXT,
+    -- Unix seconds
+    received_at INTEGER NOT NULL,
+    status INTEGER NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS responses_album_id ON responses (album_id);
+";
+
+pub struct ResponseCache {
+    path: PathBuf,
+    connection: Connection,
+}
+
+/// A stored response with when it was received, in Unix seconds
+pub(super) struct Entry {
+    pub received_at: i64,
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Stats {
+    pub entries: u64,
+    /// Of the database file
+    pub bytes: u64,
+    /// Unix seconds, `None` if there are no entries
+    pub oldest: Option<i64>,
+    pub newest: Option<i64>,
+    /// Entries for each storefront, most first
+    pub storefronts: Vec<(String, u64)>,
+}
+
+/// Which responses to remove, all of them if neither is given and the ones matching both if both
+/// are
+#[derive(Default)]
+pub struct Filter {
+    pub storefront: Option<String>,
+    /// The album's responses in every storefront, such as its tracks and other versions
+    pub album_id: Option<CatalogAlbumId>,
+}
+
+/// The storefront and album ID of a catalog path like `/v1/catalog/us/albums/123/view/…`
+fn parse_path(path: &str) -> (Option<&str>, Option<&str>) {
+    let path = path.split('?').next().unwrap_or_default();
+    let Some(rest) = path.strip_prefix("/v1/catalog/") else {
+        return (None, None);
+    };
+    let mut segments = rest.split('/');
+    let storefront = segments.next().filter(|storefront| !storefront.is_empty());
+    let album_id = match (segments.next(), segments.next()) {
+        (Some("albums"), Some(id)) if !id.is_empty() => Some(id),
+        _ => None,
+    };
+    (storefront, album_id)
+}
+
+impl ResponseCache {
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(dirs::cache_dir()
+            .context("could not determine the cache directory")?
+            .join(env!("CARGO_PKG_NAME"))
+            .join("responses.sqlite3"))
+    }
+
+    /// Created if it doesn't exist
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let connection =
+            Connection::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        connection
+            .execute_batch(SCHEMA)
+            .context("failed to create the cache table")?;
+        Ok(Self {
+            path: path.to_owned(),
+            connection,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(super) fn get(&self, url: &str) -> Result<Option<Entry>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT received_at, status, body FROM responses WHERE url = ?1",
+                [url],
+                |row| {
+                    Ok(Entry {
+                        received_at: row.get(0)?,
+                        status: row.get(1)?,
+                        body: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// `path` is relative to the API host
+    pub(super) fn insert(&self, url: &str, path: &str, entry: &Entry) -> Result<()> {
+        let (storefront, album_id) = parse_path(path);
+        self.connection.execute(
+            "INSERT OR REPLACE INTO responses (url, storefront, album_id, received_at, status, \
+             body) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                url,
+                storefront,
+                album_id,
+                entry.received_at,
+                entry.status,
+                entry.body,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Result<Stats> {
+        let (entries, oldest, newest) = self.connection.query_row(
+            "SELECT COUNT(*), MIN(received_at), MAX(received_at) FROM responses",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let storefronts = self
+            .connection
+            .prepare(
+                "SELECT storefront, COUNT(*) FROM responses WHERE storefront IS NOT NULL \
+                 GROUP BY storefront ORDER BY COUNT(*) DESC, storefront",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let bytes = fs::metadata(&self.path)
+            .with_context(|| format!("failed to read the size of {}", self.path.display()))?
+            .len();
+        Ok(Stats {
+            entries,
+            bytes,
+            oldest,
+            newest,
+            storefronts,
+        })
+    }
+
+    /// Returns how many responses were removed
+    pub fn clear(&self, filter: &Filter) -> Result<usize> {
+        let removed = self.connection.execute(
+            "DELETE FROM responses WHERE (?1 IS NULL OR storefront = ?1) \
+             AND (?2 IS NULL OR album_id = ?2)",
+            params![
+                filter.storefront,
+                filter.album_id.as_ref().map(CatalogAlbumId::as_str),
+            ],
+        )?;
+        // So that the file shrinks
+        self.connection.execute_batch("VACUUM")?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(received_at: i64) -> Entry {
+        Entry {
+            received_at,
+            status: 200,
+            body: "{}".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path("/v1/catalog/us/albums/1?extend=audioVariants"),
+            (Some("us"), Some("1")),
+        );
+        assert_eq!(
+            parse_path("/v1/catalog/gb/albums/2/view/other-versions"),
+            (Some("gb"), Some("2")),
+        );
+        assert_eq!(
+            parse_path("/v1/catalog/us/songs?filter[isrc]=X"),
+            (Some("us"), None),
+        );
+        assert_eq!(parse_path("/v1/storefronts"), (None, None));
+    }
+
+    #[test]
+    fn test_cache() {
+        let directory = std::env::temp_dir().join(format!(
+            "{}-test-cache-{}",
+            env!("CARGO_PKG_NAME"),
+            std::process::id(),
+        ));
+        let cache = ResponseCache::open(&directory.join("responses.sqlite3")).unwrap();
+        for (path, received_at) in [
+            ("/v1/catalog/us/albums/1", 10),
+            ("/v1/catalog/us/albums/1/view/other-versions", 20),
+            ("/v1/catalog/gb/albums/1", 30),
+            ("/v1/catalog/gb/albums/2", 40),
+        ] {
+            cache
+                .insert(
+                    &format!("https://api.music.apple.com{path}"),
+                    path,
+                    &entry(received_at),
+                )
+                .unwrap();
+        }
+        let url = "https://api.music.apple.com/v1/catalog/us/albums/1";
+        assert_eq!(cache.get(url).unwrap().unwrap().received_at, 10);
+        cache
+            .insert(url, "/v1/catalog/us/albums/1", &entry(50))
+            .unwrap();
+        assert_eq!(cache.get(url).unwrap().unwrap().received_at, 50);
+        assert!(cache.get(&format!("{url}0")).unwrap().is_none());
+
+        let stats = cache.stats().unwrap();
+        assert_eq!(
+            (stats.entries, stats.oldest, stats.newest),
+            (4, Some(20), Some(50)),
+        );
+        assert_eq!(
+            stats.storefronts,
+            [("gb".to_owned(), 2), ("us".to_owned(), 2)],
+        );
+
+        let album_id: CatalogAlbumId = "1".parse().unwrap();
+        assert_eq!(
+            cache
+                .clear(&Filter {
+                    storefront: Some("us".to_owned()),
+                    album_id: Some(album_id.clone()),
+                })
+                .unwrap(),
+            2,
+        );
+        assert_eq!(
+            cache
+                .clear(&Filter {
+                    album_id: Some(album_id),
+                    ..Filter::default()
+                })
+                .unwrap(),
+            1,
+        );
+        assert_eq!(cache.clear(&Filter::default()).unwrap(), 1);
+        assert_eq!(cache.stats().unwrap().entries, 0);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,97 +1,1088 @@
-use anyhow::Result;
+use std::{borrow::Cow, cell::Cell, path::PathBuf, rc::Rc, time::Duration};
 
-use crate::apple_music::api_types;
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use futures_util::{Stream, TryStreamExt, stream};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::DeserializeOwned;
 
-pub struct Client {
-    client: reqwest::Client,
-    user_token: String,
+use crate::apple_music::{
+    CatalogAlbumId, CatalogSongId, LibraryAlbumId, ParseMode, api_types,
+    api_types::UnknownValues,
+    cache::ResponseCache,
+    custom_types, fixtures,
+    middleware::{self, Stack},
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://amp-api.music.apple.com";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Albums rarely change, but it shouldn't take clearing the cache to see it when they do
+const DEFAULT_DISK_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Far more than the largest page the API returns
+const DEFAULT_MAX_RESPONSE_SIZE: usize = 32 * 1024 * 1024;
+
+/// Longest response body included in error messages
+const BODY_SNIPPET_LEN: usize = 300;
+
+/// A non-2xx response from the API
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: reqwest::StatusCode,
+    /// Messages from the `errors` array in the response body, if it had one
+    pub messages: Vec<String>,
+    /// Truncated response body, only used if there are no messages
+    pub body_snippet: String,
+    /// Suggested next step for common failures
+    pub hint: Option<&'static str>,
+    /// From the `Retry-After` header of a rate-limited response
+    pub retry_after: Option<Duration>,
+}
+
+impl ApiError {
+    fn hint(status: reqwest::StatusCode, library_endpoint: bool) -> Option<&'static str> {
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED => Some(
+                "the developer token is invalid or expired, check it with `token inspect` and get a fresh one",
+            ),
+            reqwest::StatusCode::FORBIDDEN => Some(
+                "the user token is invalid or expired, belongs to a different Apple ID, or the account has no Apple Music subscription, run `auth` to get a new one",
+            ),
+            reqwest::StatusCode::NOT_FOUND if library_endpoint => Some(
+                "the album is not in the library or the ID is from a different storefront, check the library ID and `--storefront`",
+            ),
+            reqwest::StatusCode::NOT_FOUND => Some(
+                "the item is not available in this storefront, check the catalog ID and `--storefront`",
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP {}", self.status)?;
+        if !self.messages.is_empty() {
+            write!(f, ": {}", self.messages.join("; "))?;
+        } else if !self.body_snippet.is_empty() {
+            write!(f, ": {}", self.body_snippet)?;
+        }
+        if let Some(hint) = self.hint {
+            write!(f, " (hint: {hint})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl ApiError {
+    /// Whether the error is (or was caused by) a 404 response
+    pub fn is_not_found(error: &anyhow::Error) -> bool {
+        error
+            .downcast_ref::<ApiError>()
+            .is_some_and(|error| error.status == reqwest::StatusCode::NOT_FOUND)
+    }
+}
+
+/// Replace the values of any token-like query parameters so URLs can be shown in errors
+pub(super) fn redact_url(url: &str) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_owned();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if name.to_ascii_lowercase().contains("token") => {
+                format!("{name}=REDACTED")
+            }
+            _ => pair.to_owned(),
+        })
+        .collect();
+    format!("{base}?{}", query.join("&"))
+}
+
+/// Percent-encode a query parameter value, with `+` for spaces
+fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Quote a value for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// An equivalent `curl` command for a request, with the tokens replaced by shell variables
+pub(super) fn curl_command(
+    method: &reqwest::Method,
+    url: &str,
+    default_headers: &HeaderMap,
+    with_user_token: bool,
+    body: Option<&str>,
+) -> String {
+    let mut command = format!("curl -X {method} {}", shell_quote(url));
+    for (name, value) in default_headers {
+        let header = if name == reqwest::header::AUTHORIZATION {
+            "\"Authorization: Bearer $DEVELOPER_TOKEN\"".to_owned()
+        } else {
+            shell_quote(&format!(
+                "{name}: {}",
+                String::from_utf8_lossy(value.as_bytes()),
+            ))
+        };
+        command += &format!(" -H {header}");
+    }
+    if with_user_token {
+        command += " -H \"Media-User-Token: $MEDIA_USER_TOKEN\"";
+    }
+    if let Some(body) = body {
+        command += &format!(
+            " -H 'Content-Type: application/json' --data {}",
+            shell_quote(body)
+        );
+    }
+    command
+}
+
+fn join_ids(ids: &[&CatalogSongId]) -> String {
+    ids.iter()
+        .map(|id| id.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn snippet(body: &str) -> String {
+    let body = body.trim();
+    match body.char_indices().nth(BODY_SNIPPET_LEN) {
+        Some((end, _)) => format!("{}{}", &body[..end], crate::output::ellipsis()),
+        None => body.to_owned(),
+    }
+}
+
+/// How idempotent requests are retried after transient transport failures. Requests that modify
+/// the library are never retried automatically, except after being rate limited since those
+/// weren't processed.
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    /// Including the first attempt
+    pub max_attempts: u32,
+    /// Doubled after each failed attempt
+    pub initial_backoff: Duration,
+    /// Retries after rate-limited responses, which don't count towards `max_attempts`
+    pub max_rate_limited_retries: u32,
+    /// Used when a rate-limited response has no `Retry-After` header, doubled after each retry
+    pub rate_limited_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_rate_limited_retries: 5,
+            rate_limited_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Rate-limited responses so far, so that slow runs can be explained
+#[derive(Clone, Copy, Default)]
+pub struct RateLimits {
+    pub throttled: u32,
+    pub waited: Duration,
+}
+
+impl std::fmt::Display for RateLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} requests throttled, waited {}s total",
+            self.throttled,
+            self.waited.as_secs(),
+        )
+    }
+}
+
+/// Seconds to wait from a `Retry-After` header, dates aren't supported
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Read the body, stopping as soon as it's larger than `max_size` so a broken response can't use
+/// up all the memory
+async fn read_body(mut response: reqwest::Response, max_size: usize) -> Result<String> {
+    let too_large = || {
+        anyhow!(
+            "response body is larger than the limit of {max_size} bytes, pass \
+             `--max-response-size` to raise it"
+        )
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_size as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_size {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Send a request and read the whole body, failing on non-2xx responses
+pub(super) async fn execute(
+    request: reqwest::RequestBuilder,
+    library_endpoint: bool,
+    max_response_size: usize,
+) -> Result<(reqwest::StatusCode, String)> {
+    let response = request.send().await?;
+    let status = response.status();
+    let retry_after = parse_retry_after(response.headers());
+    let body = read_body(response, max_response_size).await?;
+    if !status.is_success() {
+        let messages = match serde_json::from_str::<api_types::errors::Root>(&body) {
+            Ok(root) => root.errors.iter().map(|error| error.message()).collect(),
+            Err(_) => Vec::new(),
+        };
+        return Err(ApiError {
+            status,
+            messages,
+            body_snippet: snippet(&body),
+            hint: ApiError::hint(status, library_endpoint),
+            retry_after,
+        }
+        .into());
+    }
+    Ok((status, body))
+}
+
+enum Transport {
+    Http(Stack),
+    /// Serves the bundled fixtures and refuses to make changes
+    Offline,
+}
+
+/// Configures a [`Client`] that uses the API, everything but the developer token and storefront
+/// has a default
+pub struct ClientBuilder {
+    developer_token: String,
     storefront: String,
+    user_token: Option<String>,
+    base_url: String,
+    origin: Option<String>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
+    min_request_interval: Option<Duration>,
+    user_agent: Option<String>,
+    proxy: Option<String>,
+    keep_alive: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    max_idle_connections: Option<usize>,
+    max_response_size: usize,
+    language: Option<String>,
+    cache: bool,
+    cache_ttl: Option<Duration>,
+    disk_cache: Option<PathBuf>,
+    parse_mode: ParseMode,
+    dump_curl: bool,
 }
 
-impl Client {
-    pub fn new(
-        developer_token: &str,
-        origin_header: Option<String>,
-        user_token: String,
-        storefront: String,
-    ) -> Result<Self> {
+impl ClientBuilder {
+    /// Needed for library requests
+    pub fn user_token(mut self, user_token: String) -> Self {
+        self.user_token = Some(user_token);
+        self
+    }
+
+    /// Without a trailing slash, defaults to [`DEFAULT_BASE_URL`]
+    pub fn base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    pub fn origin(mut self, origin: String) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Sent with every request, replacing any default with the same name
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// For each attempt of a request, including reading the body
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Wait at least this long between starting requests, including retries
+    pub fn min_request_interval(mut self, interval: Duration) -> Self {
+        self.min_request_interval = Some(interval);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: String) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Used for both HTTP and HTTPS requests
+    pub fn proxy(mut self, proxy: String) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// TCP keep-alive interval for open connections
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// How long an idle connection is kept for reuse
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Idle connections kept for reuse per host, reqwest has no limit on open connections
+    pub fn max_idle_connections(mut self, max: usize) -> Self {
+        self.max_idle_connections = Some(max);
+        self
+    }
+
+    /// Bytes, larger response bodies fail the request, defaults to 32 MiB
+    pub fn max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    /// Language of the names in catalog responses (the `l` parameter), e.g. `en-US` for romanized
+    /// names in the `jp` storefront, defaults to the storefront's
+    pub fn language(mut self, language: String) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    /// Reuse catalog responses for the rest of the run and from the [`Self::disk_cache`], on by
+    /// default. Without it responses are still written to the disk cache, replacing the older
+    /// ones there.
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Request a cached catalog response again once it's this old, whether it's from this run or
+    /// the disk cache
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    pub fn parse_mode(mut self, parse_mode: ParseMode) -> Self {
+        self.parse_mode = parse_mode;
+        self
+    }
+
+    /// Also keep catalog responses between runs in a database at `path`, such as
+    /// [`ResponseCache::default_path`], reusing them for a day unless [`Self::cache_ttl`] is given.
+    /// Responses are requested as usual if it can't be opened.
+    pub fn disk_cache(mut self, path: PathBuf) -> Self {
+        self.disk_cache = Some(path);
+        self
+    }
+
+    /// Print an equivalent `curl` command for every request
+    pub fn dump_curl(mut self, dump_curl: bool) -> Self {
+        self.dump_curl = dump_curl;
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
         let headers = {
-            let mut headers = reqwest::header::HeaderMap::new();
+            let mut headers = HeaderMap::new();
             headers.insert(
                 "Authorization",
-                format!("Bearer {}", developer_token).try_into()?,
+                format!("Bearer {}", self.developer_token).try_into()?,
             );
-            if let Some(origin) = origin_header {
+            if let Some(origin) = self.origin {
                 headers.insert("Origin", origin.try_into()?);
             }
+            for (name, value) in self.headers {
+                headers.insert(name, value);
+            }
             headers
         };
-        let client = reqwest::Client::builder()
+        let curl_headers = self.dump_curl.then(|| headers.clone());
+        let mut client = reqwest::Client::builder()
             .default_headers(headers)
-            .build()?;
-        Ok(Self {
-            client,
-            user_token,
+            .timeout(self.timeout);
+        if let Some(user_agent) = self.user_agent {
+            client = client.user_agent(user_agent);
+        }
+        if let Some(keep_alive) = self.keep_alive {
+            client = client.tcp_keepalive(keep_alive);
+        }
+        if let Some(pool_idle_timeout) = self.pool_idle_timeout {
+            client = client.pool_idle_timeout(pool_idle_timeout);
+        }
+        if let Some(max_idle_connections) = self.max_idle_connections {
+            client = client.pool_max_idle_per_host(max_idle_connections);
+        }
+        if let Some(proxy) = self.proxy {
+            client = client.proxy(
+                reqwest::Proxy::all(&proxy).with_context(|| format!("invalid proxy {proxy}"))?,
+            );
+        }
+        let rate_limits = Rc::default();
+        let mut stack = Stack::new(client.build()?, self.max_response_size);
+        #[cfg(feature = "otel")]
+        {
+            stack = stack.layer(middleware::Trace);
+        }
+        if self.cache {
+            stack = stack.layer(middleware::Cache::new(self.cache_ttl));
+        }
+        if let Some(path) = &self.disk_cache {
+            match ResponseCache::open(path) {
+                Ok(cache) => {
+                    stack = stack.layer(middleware::DiskCache {
+                        cache,
+                        ttl: self.cache_ttl.unwrap_or(DEFAULT_DISK_CACHE_TTL),
+                        read: self.cache,
+                    });
+                }
+                Err(error) => {
+                    eprintln!("Warning: not keeping responses between runs, {error:#}");
+                }
+            }
+        }
+        if let Some(headers) = curl_headers {
+            stack = stack.layer(middleware::Curl { headers });
+        }
+        stack = stack.layer(middleware::Retry {
+            policy: self.retry_policy,
+            rate_limits: Rc::clone(&rate_limits),
+        });
+        if let Some(interval) = self.min_request_interval {
+            stack = stack.layer(middleware::Throttle {
+                interval,
+                last_request: Cell::new(None),
+            });
+        }
+        stack = stack.layer(middleware::UserToken {
+            user_token: self.user_token.clone(),
+        });
+        Ok(Client {
+            transport: Transport::Http(stack),
+            base_url: self.base_url.trim_end_matches('/').to_owned(),
+            user_token: self.user_token,
+            storefront: self.storefront,
+            language: self.language,
+            parse_mode: self.parse_mode,
+            rate_limits,
+        })
+    }
+}
+
+pub struct Client {
+    transport: Transport,
+    base_url: String,
+    user_token: Option<String>,
+    storefront: String,
+    language: Option<String>,
+    parse_mode: ParseMode,
+    /// Shared with the retry layer, which counts them
+    rate_limits: Rc<Cell<RateLimits>>,
+}
+
+impl Client {
+    pub fn builder(developer_token: String, storefront: String) -> ClientBuilder {
+        ClientBuilder {
+            developer_token,
             storefront,
+            user_token: None,
+            base_url: DEFAULT_BASE_URL.to_owned(),
+            origin: None,
+            headers: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+            min_request_interval: None,
+            user_agent: None,
+            proxy: None,
+            keep_alive: None,
+            pool_idle_timeout: None,
+            max_idle_connections: None,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            language: None,
+            cache: true,
+            cache_ttl: None,
+            disk_cache: None,
+            parse_mode: ParseMode::default(),
+            dump_curl: false,
+        }
+    }
+
+    /// A client that serves the bundled example albums without any network access
+    pub fn offline(parse_mode: ParseMode) -> Self {
+        Self {
+            transport: Transport::Offline,
+            base_url: String::new(),
+            user_token: None,
+            storefront: fixtures::STOREFRONT.to_owned(),
+            language: None,
+            parse_mode,
+            rate_limits: Rc::default(),
+        }
+    }
+
+    pub fn storefront(&self) -> &str {
+        &self.storefront
+    }
+
+    /// Whether this is the client from [`Self::offline`]
+    pub fn is_offline(&self) -> bool {
+        matches!(self.transport, Transport::Offline)
+    }
+
+    /// `None` offline or if none was given
+    pub fn user_token(&self) -> Option<&str> {
+        self.user_token.as_deref()
+    }
+
+    /// `None` if no requests were rate limited
+    pub fn rate_limits(&self) -> Option<RateLimits> {
+        let rate_limits = self.rate_limits.get();
+        (rate_limits.throttled > 0).then_some(rate_limits)
+    }
+
+    /// `path` is relative to the API host and may include a query string
+    async fn get<T: DeserializeOwned + UnknownValues>(
+        &self,
+        path: &str,
+        with_user_token: bool,
+    ) -> Result<T> {
+        let path = &self.localized(path);
+        let response: T = match &self.transport {
+            Transport::Http(stack) => {
+                let url = format!("{}{path}", self.base_url);
+                let middleware::Response { status, body } = self
+                    .request(stack, reqwest::Method::GET, path, with_user_token, None)
+                    .await?;
+                serde_json::from_str(&body).with_context(|| {
+                    format!(
+                        "GET {} returned HTTP {status} with an unexpected body: {}",
+                        redact_url(&url),
+                        snippet(&body),
+                    )
+                })?
+            }
+            Transport::Offline => {
+                let body = fixtures::get(path).with_context(|| {
+                    format!(
+                        "no bundled example for {path}, offline mode only has {}",
+                        fixtures::describe(),
+                    )
+                })?;
+                crate::stats::record_request(&reqwest::Method::GET, path, body.len());
+                serde_json::from_str(body)?
+            }
+        };
+
+        for warning in response.warnings() {
+            eprintln!("Warning: {warning}");
+        }
+        let unknown_values = response.unknown_values();
+        match self.parse_mode {
+            ParseMode::Strict => ensure!(
+                unknown_values.is_empty(),
+                "unknown values in response to {path}: {} (use `--parse-mode lenient` to accept them)",
+                unknown_values.join(", "),
+            ),
+            ParseMode::Lenient => {
+                for unknown_value in unknown_values {
+                    eprintln!("Warning: unknown {unknown_value} in response to {path}");
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    /// Every item of a list response, requesting `limit` items at a time for as long as the API
+    /// says there is a next page
+    fn paginate<P: api_types::Page>(
+        &self,
+        path: String,
+        limit: usize,
+        with_user_token: bool,
+    ) -> impl Stream<Item = Result<P::Item>> + '_ {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        stream::try_unfold(Some(0), move |offset| {
+            let path = path.clone();
+            async move {
+                let Some(offset) = offset else {
+                    return Ok(None);
+                };
+                let page: P = self
+                    .get(
+                        &format!("{path}{separator}limit={limit}&offset={offset}"),
+                        with_user_token,
+                    )
+                    .await?;
+                let has_next = page.has_next();
+                let items = page.into_items();
+                ensure!(
+                    !has_next || !items.is_empty(),
+                    "{path} returned an empty page",
+                );
+                let next_offset = has_next.then_some(offset + items.len());
+                Ok(Some((stream::iter(items.into_iter().map(Ok)), next_offset)))
+            }
         })
+        .try_flatten()
+    }
+
+    /// Catalog paths with the `l` parameter if a language was given
+    fn localized<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        match &self.language {
+            Some(language) if path.starts_with("/v1/catalog/") => {
+                let separator = if path.contains('?') { '&' } else { '?' };
+                Cow::Owned(format!(
+                    "{path}{separator}l={}",
+                    encode_query_value(language)
+                ))
+            }
+            _ => Cow::Borrowed(path),
+        }
+    }
+
+    /// Send a request through the middleware stack, `path` is relative to the API host
+    async fn request(
+        &self,
+        stack: &Stack,
+        method: reqwest::Method,
+        path: &str,
+        with_user_token: bool,
+        body: Option<String>,
+    ) -> Result<middleware::Response> {
+        let url = format!("{}{path}", self.base_url);
+        let request = middleware::Request {
+            method: method.clone(),
+            url: url.clone(),
+            path: path.to_owned(),
+            headers: HeaderMap::new(),
+            body,
+            with_user_token,
+            library_endpoint: path.starts_with("/v1/me/library"),
+        };
+        stack
+            .send(request)
+            .await
+            .with_context(|| format!("{method} {} failed", redact_url(&url)))
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<()> {
+        let Transport::Http(stack) = &self.transport else {
+            bail!("offline mode can't make changes to the library");
+        };
+        let body = body.map(|body| body.to_string());
+        self.request(stack, method, path, true, body).await?;
+        Ok(())
     }
 
     pub async fn get_catalog_album(
         &self,
-        catalog_id: &str,
+        catalog_id: &CatalogAlbumId,
     ) -> Result<api_types::catalog_album::Root> {
-        Ok(self
-            .client
-            .get(format!(
-                "https://amp-api.music.apple.com/v1/catalog/{}/albums/{catalog_id}",
+        self.get_catalog_album_in(&self.storefront, catalog_id)
+            .await
+    }
+
+    /// Like [`Self::get_catalog_album`] but in a different storefront than the client's
+    pub async fn get_catalog_album_in(
+        &self,
+        storefront: &str,
+        catalog_id: &CatalogAlbumId,
+    ) -> Result<api_types::catalog_album::Root> {
+        self.get(
+            &format!("/v1/catalog/{storefront}/albums/{catalog_id}?extend=audioVariants"),
+            false,
+        )
+        .await
+    }
+
+    /// Other editions of the album, e.g. deluxe, clean or remastered, as listed on its page
+    pub async fn get_other_versions(
+        &self,
+        catalog_id: &CatalogAlbumId,
+    ) -> Result<api_types::album_views::Root> {
+        self.get(
+            &format!(
+                "/v1/catalog/{}/albums/{catalog_id}/view/other-versions",
                 self.storefront,
-            ))
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?)
+            ),
+            false,
+        )
+        .await
+    }
+
+    pub async fn get_catalog_songs(
+        &self,
+        catalog_ids: &[&CatalogSongId],
+    ) -> Result<api_types::catalog_songs::Root> {
+        let ids = join_ids(catalog_ids);
+        self.get(
+            &format!("/v1/catalog/{}/songs?ids={ids}", self.storefront),
+            false,
+        )
+        .await
+    }
+
+    pub async fn get_catalog_songs_by_isrc(
+        &self,
+        storefront: &str,
+        isrcs: &[&str],
+    ) -> Result<api_types::catalog_songs::Root> {
+        let isrcs = isrcs.join(",");
+        self.get(
+            &format!("/v1/catalog/{storefront}/songs?filter[isrc]={isrcs}"),
+            false,
+        )
+        .await
+    }
+
+    /// Albums matching the search term, most relevant first
+    pub fn search_albums(
+        &self,
+        term: &str,
+    ) -> impl Stream<Item = Result<api_types::search::Album>> + '_ {
+        self.paginate::<api_types::search::Root>(
+            format!(
+                "/v1/catalog/{}/search?term={}&types=albums",
+                self.storefront,
+                encode_query_value(term),
+            ),
+            25,
+            false,
+        )
     }
 
     pub async fn get_library_album(
         &self,
-        library_id: &str,
+        library_id: &LibraryAlbumId,
     ) -> Result<api_types::library_album::Root> {
-        Ok(self
-            .client
-            .get(format!(
-                "https://amp-api.music.apple.com/v1/me/library/albums/{library_id}?include=catalog",
-            ))
-            .header("Media-User-Token", &self.user_token)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?)
-    }
-
-    pub async fn add_songs_to_library(&self, catalog_ids: &[&str]) -> Result<()> {
-        let ids = catalog_ids.join(",");
-        self.client
-            .post(format!(
-                "https://amp-api.music.apple.com/v1/me/library?ids[songs]={ids}",
-            ))
-            .header("Media-User-Token", &self.user_token)
-            .send()
-            .await?
-            .error_for_status()?;
-        Ok(())
+        let mut library_album: api_types::library_album::Root = self
+            .get(
+                &format!("/v1/me/library/albums/{library_id}?include=catalog"),
+                true,
+            )
+            .await?;
+        // Large albums such as box sets only include their first tracks
+        for album in &mut library_album.data {
+            if album.relationships.tracks.next.is_some() {
+                album.relationships.tracks.data =
+                    self.library_album_tracks(library_id).try_collect().await?;
+                album.relationships.tracks.next = None;
+            }
+        }
+        Ok(library_album)
     }
 
-    pub async fn remove_album_from_library(&self, library_id: &str) -> Result<()> {
-        self.client
-            .delete(format!(
-                "https://amp-api.music.apple.com/v1/me/library/albums/{library_id}",
-            ))
-            .header("Media-User-Token", &self.user_token)
-            .send()
-            .await?
-            .error_for_status()?;
-        Ok(())
+    /// The songs of a library album, fetching the next page as the stream is polled
+    pub fn library_album_tracks(
+        &self,
+        library_id: &LibraryAlbumId,
+    ) -> impl Stream<Item = Result<api_types::library_album::LibrarySong>> + '_ {
+        self.paginate::<api_types::library_album::TracksRoot>(
+            format!("/v1/me/library/albums/{library_id}/tracks"),
+            100,
+            true,
+        )
+    }
+
+    /// Every album in the library, fetching the next page as the stream is polled
+    pub fn library_albums(
+        &self,
+    ) -> impl Stream<Item = Result<custom_types::LibraryAlbumSummary>> + '_ {
+        self.paginate::<api_types::library_albums::Root>(
+            "/v1/me/library/albums".to_owned(),
+            100,
+            true,
+        )
+        .and_then(|album| std::future::ready(album.try_into()))
+    }
+
+    /// Every playlist in the library, fetching the next page as the stream is polled
+    pub fn library_playlists(
+        &self,
+    ) -> impl Stream<Item = Result<api_types::library_playlists::Playlist>> + '_ {
+        self.paginate::<api_types::library_playlists::Root>(
+            "/v1/me/library/playlists".to_owned(),
+            100,
+            true,
+        )
+    }
+
+    /// The songs in a library playlist, in playlist order
+    pub fn library_playlist_tracks(
+        &self,
+        playlist_id: &str,
+    ) -> impl Stream<Item = Result<api_types::library_playlists::Track>> + '_ {
+        self.paginate::<api_types::library_playlists::TracksRoot>(
+            format!("/v1/me/library/playlists/{playlist_id}/tracks"),
+            100,
+            true,
+        )
+    }
+
+    /// The storefront of the account the user token belongs to
+    pub async fn get_account_storefront(&self) -> Result<api_types::storefront::Root> {
+        self.get("/v1/me/storefront", true).await
+    }
+
+    /// Albums, playlists and stations the user recently played, most recent first
+    pub async fn get_recently_played(&self) -> Result<api_types::recent::Root> {
+        self.get("/v1/me/recent/played?limit=10", true).await
+    }
+
+    /// Albums and playlists the user has been playing the most lately
+    pub async fn get_heavy_rotation(&self) -> Result<api_types::recent::Root> {
+        self.get("/v1/me/history/heavy-rotation?limit=10", true)
+            .await
+    }
+
+    /// Only the first library album, for its total count
+    pub async fn get_library_albums_summary(&self) -> Result<api_types::library_albums::Root> {
+        self.get("/v1/me/library/albums?limit=1", true).await
+    }
+
+    /// The library album added from a catalog album, `None` if none of its songs are in the
+    /// library
+    pub async fn get_library_album_by_catalog_id(
+        &self,
+        catalog_id: &CatalogAlbumId,
+    ) -> Result<Option<api_types::library_album::Root>> {
+        let candidates: api_types::library_albums::Root = self
+            .get(
+                &format!("/v1/me/library/albums?filter[catalogId]={catalog_id}"),
+                true,
+            )
+            .await?;
+        // Checked against the catalog relationship in case the filter is loosely applied
+        for library_id in candidates.ids() {
+            let library_album = self.get_library_album(&library_id.parse()?).await?;
+            if catalog_id == library_album.catalog_id()? {
+                return Ok(Some(library_album));
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn add_songs_to_library(&self, catalog_ids: &[&CatalogSongId]) -> Result<()> {
+        let ids = join_ids(catalog_ids);
+        self.send(
+            reqwest::Method::POST,
+            &format!("/v1/me/library?ids[songs]={ids}"),
+            None,
+        )
+        .await
+    }
+
+    pub async fn add_album_to_library(&self, catalog_id: &CatalogAlbumId) -> Result<()> {
+        self.send(
+            reqwest::Method::POST,
+            &format!("/v1/me/library?ids[albums]={catalog_id}"),
+            None,
+        )
+        .await
+    }
+
+    pub async fn remove_album_from_library(&self, library_id: &LibraryAlbumId) -> Result<()> {
+        self.send(
+            reqwest::Method::DELETE,
+            &format!("/v1/me/library/albums/{library_id}"),
+            None,
+        )
+        .await
+    }
+
+    /// Whether the library album is marked as a favorite (loved, before iOS 17.1), which is
+    /// separate from the favorites of its songs
+    pub async fn is_library_album_favorite(&self, library_id: &LibraryAlbumId) -> Result<bool> {
+        match self
+            .get::<api_types::ratings::Root>(
+                &format!("/v1/me/ratings/library-albums/{library_id}"),
+                true,
+            )
+            .await
+        {
+            Ok(ratings) => Ok(ratings.is_favorite()),
+            // Albums without a rating have none to get
+            Err(error) if ApiError::is_not_found(&error) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub async fn favorite_album(&self, catalog_id: &CatalogAlbumId) -> Result<()> {
+        self.send(
+            reqwest::Method::PUT,
+            &format!("/v1/me/ratings/albums/{catalog_id}"),
+            Some(serde_json::json!({"type": "rating", "attributes": {"value": 1}})),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_url() {
+        assert_eq!(
+            redact_url("https://example.com/v1/me/library?ids[songs]=1,2"),
+            "https://example.com/v1/me/library?ids[songs]=1,2",
+        );
+        assert_eq!(
+            redact_url("https://example.com/v1?developerToken=abc&l=en-US&user_token=def"),
+            "https://example.com/v1?developerToken=REDACTED&l=en-US&user_token=REDACTED",
+        );
+    }
+
+    #[test]
+    fn test_curl_command() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "Bearer secret".parse().unwrap());
+        headers.insert("Origin", "https://music.apple.com".parse().unwrap());
+        headers.insert("X-Note", "it's".parse().unwrap());
+        assert_eq!(
+            curl_command(
+                &reqwest::Method::DELETE,
+                "https://example.com/v1/me/library/albums/l.A",
+                &headers,
+                true,
+                None,
+            ),
+            "curl -X DELETE 'https://example.com/v1/me/library/albums/l.A' \
+             -H \"Authorization: Bearer $DEVELOPER_TOKEN\" \
+             -H 'origin: https://music.apple.com' -H 'x-note: it'\\''s' \
+             -H \"Media-User-Token: $MEDIA_USER_TOKEN\"",
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+        headers.insert("Retry-After", "30".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(30)));
+        headers.insert(
+            "Retry-After",
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_client_builder() {
+        let client = Client::builder("a.b.c".to_owned(), "us".to_owned())
+            .base_url("http://localhost:8080/".to_owned())
+            .build()
+            .unwrap();
+        assert_eq!(client.base_url, "http://localhost:8080");
+        assert_eq!(client.storefront(), "us");
+        assert_eq!(client.user_token(), None);
+
+        let client = Client::builder("a.b.c".to_owned(), "us".to_owned())
+            .user_token("token".to_owned())
+            .build()
+            .unwrap();
+        assert_eq!(client.base_url, DEFAULT_BASE_URL);
+        assert_eq!(client.user_token(), Some("token"));
+        assert_eq!(
+            client.localized("/v1/catalog/jp/songs?ids=1"),
+            "/v1/catalog/jp/songs?ids=1"
+        );
+
+        let client = Client::builder("a.b.c".to_owned(), "jp".to_owned())
+            .language("en-US".to_owned())
+            .build()
+            .unwrap();
+        assert_eq!(
+            client.localized("/v1/catalog/jp/songs?ids=1"),
+            "/v1/catalog/jp/songs?ids=1&l=en-US",
+        );
+        assert_eq!(
+            client.localized("/v1/catalog/jp/albums/1"),
+            "/v1/catalog/jp/albums/1?l=en-US",
+        );
+        assert_eq!(
+            client.localized("/v1/me/library/albums"),
+            "/v1/me/library/albums"
+        );
+
+        assert!(
+            Client::builder("a.b.c".to_owned(), "us".to_owned())
+                .proxy("not a url".to_owned())
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_api_error_hint() {
+        assert!(ApiError::hint(reqwest::StatusCode::UNAUTHORIZED, false).is_some());
+        assert!(ApiError::hint(reqwest::StatusCode::FORBIDDEN, true).is_some());
+        assert_ne!(
+            ApiError::hint(reqwest::StatusCode::NOT_FOUND, true),
+            ApiError::hint(reqwest::StatusCode::NOT_FOUND, false),
+        );
+        assert!(ApiError::hint(reqwest::StatusCode::INTERNAL_SERVER_ERROR, false).is_none());
+    }
+
+    #[test]
+    fn test_encode_query_value() {
+        assert_eq!(
+            encode_query_value("Demo Artist Caf\u{e9} & Co"),
+            "Demo+Artist+Caf%C3%A9+%26+Co",
+        );
+    }
+
+    #[test]
+    fn test_snippet_truncates() {
+        assert_eq!(snippet("  short  "), "short");
+        let long = "\u{e9}".repeat(BODY_SNIPPET_LEN + 1);
+        assert_eq!(
+            snippet(&long),
+            format!("{}\u{2026}", "\u{e9}".repeat(BODY_SNIPPET_LEN)),
+        );
     }
 }
@@ -1,45 +1,118 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 use anyhow::{Context, Result, ensure};
 
-use crate::apple_music::api_types;
+use crate::apple_music::{CatalogAlbumId, CatalogSongId, LibraryAlbumId, LibrarySongId, api_types};
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Album<Track> {
-    pub catalog_id: String,
+    pub catalog_id: CatalogAlbumId,
     pub name: String,
     /// All of the album's artists
     pub artist_name: String,
-    /// YYYY-MM-DD
-    pub release_date: String,
+    /// May include the generic `Music` genre
+    pub genre_names: Vec<String>,
+    /// YYYY-MM-DD, `None` for some pre-release albums
+    pub release_date: Option<String>,
+    /// Not every album has one
+    pub upc: Option<String>,
+    /// e.g. `lossless`, `hi-res-lossless` or `dolby-atmos`
+    pub audio_variants: Vec<String>,
+    /// Mastered for Apple Music from high-resolution files
+    pub apple_digital_master: bool,
     pub tracks: Vec<Track>,
 }
 
+/// Tracks without a content rating have nothing to be cleaned up
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ContentRating {
+    Clean,
+    Explicit,
+    /// A rating added to the API after this was written, kept so it isn't mistaken for no rating
+    Other(String),
+}
+
+impl ContentRating {
+    /// As the API spells it, e.g. `explicit`
+    pub fn as_str(&self) -> &str {
+        match self {
+            ContentRating::Clean => "clean",
+            ContentRating::Explicit => "explicit",
+            ContentRating::Other(other) => other,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct TrackNoLibrary {
-    pub catalog_id: String,
+    pub catalog_id: CatalogSongId,
     pub name: String,
     /// All of the album's artists
     pub artist_name: String,
-    pub is_explicit: bool,
-    pub isrc: String,
-    /// YYYY-MM-DD
-    pub release_date: String,
+    pub genre_names: Vec<String>,
+    pub disc_number: u8,
+    pub track_number: u8,
+    pub content_rating: Option<ContentRating>,
+    pub isrc: Option<String>,
+    /// YYYY-MM-DD, `None` for some tracks of pre-release albums
+    pub release_date: Option<String>,
+    pub duration: Option<Duration>,
+    /// `false` if it can't be played in the storefront, so adding it to the library may fail
+    pub playable: bool,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct TrackWithLibrary {
-    pub catalog_id: String,
+    pub catalog_id: CatalogSongId,
     pub name: String,
     /// All of the album's artists
     pub artist_name: String,
-    pub is_explicit: bool,
-    pub isrc: String,
-    /// YYYY-MM-DD
-    pub release_date: String,
+    pub genre_names: Vec<String>,
+    pub disc_number: u8,
+    pub track_number: u8,
+    pub content_rating: Option<ContentRating>,
+    pub isrc: Option<String>,
+    /// YYYY-MM-DD, `None` for some tracks of pre-release albums
+    pub release_date: Option<String>,
+    pub duration: Option<Duration>,
+
+    pub library_id: Option<LibrarySongId>,
+    /// ISO 8601, `None` if the track isn't in the library or the date wasn't given
+    pub date_added: Option<String>,
+}
 
-    /// Starts with `i.`
-    pub library_id: Option<String>,
+#[derive(Debug, PartialEq, Eq)]
+pub struct LibraryAlbum {
+    pub library_id: LibraryAlbumId,
+    /// `None` if the album isn't matched to a catalog album (e.g. uploaded music)
+    pub catalog_id: Option<CatalogAlbumId>,
+    pub name: String,
+    /// All of the album's artists
+    pub artist_name: String,
+    /// ISO 8601
+    pub date_added: Option<String>,
+    pub tracks: Vec<LibraryTrack>,
+}
+
+/// A library album from the list of all of them, without its tracks
+#[derive(Debug, PartialEq, Eq)]
+pub struct LibraryAlbumSummary {
+    pub library_id: LibraryAlbumId,
+    pub name: String,
+    /// All of the album's artists
+    pub artist_name: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LibraryTrack {
+    pub library_id: LibrarySongId,
+    pub catalog_id: CatalogSongId,
+    pub name: String,
+    /// ISO 8601
+    pub date_added: Option<String>,
 }
 
 impl TryFrom<api_types::catalog_album::Root> for Album<TrackNoLibrary> {
@@ -49,41 +122,57 @@ impl TryFrom<api_types::catalog_album::Root> for Album<TrackNoLibrary> {
         ensure!(value.data.len() == 1);
         let album = value.data.into_iter().next().unwrap();
 
-        let mut tracks: Vec<(u8, u8, TrackNoLibrary)> = album
+        let mut tracks: Vec<TrackNoLibrary> = album
             .relationships
             .tracks
             .data
             .into_iter()
             .map(|song| {
-                (
-                    song.attributes.disc_number,
-                    song.attributes.track_number,
-                    TrackNoLibrary {
-                        catalog_id: song.id,
-                        name: song.attributes.name,
-                        artist_name: song.attributes.artist_name,
-                        is_explicit: song.attributes.content_rating.is_some(),
-                        isrc: song.attributes.isrc,
-                        release_date: song.attributes.release_date,
+                Ok(TrackNoLibrary {
+                    catalog_id: song.id.try_into()?,
+                    name: song.attributes.name,
+                    artist_name: song.attributes.artist_name,
+                    genre_names: song.attributes.genre_names,
+                    disc_number: song.attributes.disc_number,
+                    track_number: song.attributes.track_number,
+                    content_rating: match song.attributes.content_rating {
+                        Some(api_types::catalog_album::ContentRating::Clean) => {
+                            Some(ContentRating::Clean)
+                        }
+                        Some(api_types::catalog_album::ContentRating::Explicit) => {
+                            Some(ContentRating::Explicit)
+                        }
+                        // Also reported by the client as an unknown value
+                        Some(api_types::catalog_album::ContentRating::Other(other)) => {
+                            Some(ContentRating::Other(other))
+                        }
+                        None => None,
                     },
-                )
+                    isrc: song.attributes.isrc,
+                    release_date: song.attributes.release_date,
+                    duration: song
+                        .attributes
+                        .duration_in_millis
+                        .map(Duration::from_millis),
+                    playable: song.attributes.play_params.is_some(),
+                })
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         ensure!(tracks.len() == album.attributes.track_count as usize);
 
-        tracks.sort_by_key(|(disc, num, _)| (*disc, *num));
+        tracks.sort_by_key(|track| (track.disc_number, track.track_number));
 
         {
             // Check for contiguous 1..N track numbers per disc
             let mut current_disc: Option<u8> = None;
             let mut expected_track_number = 1u8;
-            for (disc, num, _) in &tracks {
-                if Some(*disc) != current_disc {
-                    current_disc = Some(*disc);
+            for track in &tracks {
+                if Some(track.disc_number) != current_disc {
+                    current_disc = Some(track.disc_number);
                     expected_track_number = 1;
                 }
-                ensure!(*num == expected_track_number);
+                ensure!(track.track_number == expected_track_number);
                 expected_track_number = expected_track_number
                     .checked_add(1)
                     .context("Failed to increment expected track number")?;
@@ -91,30 +180,119 @@ impl TryFrom<api_types::catalog_album::Root> for Album<TrackNoLibrary> {
         }
 
         let mut seen_ids = HashSet::new();
-        for (_, _, track) in &tracks {
+        for track in &tracks {
             ensure!(seen_ids.insert(&track.catalog_id));
         }
 
         Ok(Album {
-            catalog_id: album.id,
+            catalog_id: album.id.try_into()?,
             name: album.attributes.name,
             artist_name: album.attributes.artist_name,
+            genre_names: album.attributes.genre_names,
             release_date: album.attributes.release_date,
-            tracks: tracks.into_iter().map(|(_, _, t)| t).collect(),
+            upc: album.attributes.upc,
+            audio_variants: album.attributes.audio_variants,
+            apple_digital_master: album.attributes.is_mastered_for_itunes,
+            tracks,
+        })
+    }
+}
+
+impl TryFrom<api_types::library_album::Root> for LibraryAlbum {
+    type Error = anyhow::Error;
+
+    fn try_from(value: api_types::library_album::Root) -> Result<Self, Self::Error> {
+        ensure!(value.data.len() == 1);
+        let album = value.data.into_iter().next().unwrap();
+
+        ensure!(album.relationships.catalog.data.len() <= 1);
+        let catalog_id = album
+            .relationships
+            .catalog
+            .data
+            .into_iter()
+            .next()
+            .map(|catalog| catalog.id.try_into())
+            .transpose()?;
+
+        // Songs without a catalog ID are reported by the client as warnings
+        let mut seen_ids = HashSet::new();
+        for catalog_id in album
+            .relationships
+            .tracks
+            .data
+            .iter()
+            .filter_map(|song| song.catalog_id())
+        {
+            ensure!(seen_ids.insert(catalog_id));
+        }
+
+        let tracks = album
+            .relationships
+            .tracks
+            .data
+            .into_iter()
+            .filter_map(|song| {
+                let catalog_id = song.attributes.play_params.catalog_id?;
+                Some((
+                    song.id,
+                    catalog_id,
+                    song.attributes.name,
+                    song.attributes.date_added,
+                ))
+            })
+            .map(|(library_id, catalog_id, name, date_added)| {
+                Ok(LibraryTrack {
+                    library_id: library_id.try_into()?,
+                    catalog_id: catalog_id.try_into()?,
+                    name,
+                    date_added,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(LibraryAlbum {
+            library_id: album.id.try_into()?,
+            catalog_id,
+            name: album.attributes.name,
+            artist_name: album.attributes.artist_name,
+            date_added: album.attributes.date_added,
+            tracks,
+        })
+    }
+}
+
+impl TryFrom<api_types::library_albums::LibraryAlbum> for LibraryAlbumSummary {
+    type Error = anyhow::Error;
+
+    fn try_from(album: api_types::library_albums::LibraryAlbum) -> Result<Self, Self::Error> {
+        Ok(LibraryAlbumSummary {
+            library_id: album.id.try_into()?,
+            name: album.attributes.name,
+            artist_name: album.attributes.artist_name,
         })
     }
 }
 
 impl TrackNoLibrary {
-    fn with_library_id(self, library_id: Option<String>) -> TrackWithLibrary {
+    fn with_library_id(
+        self,
+        library_id: Option<LibrarySongId>,
+        date_added: Option<String>,
+    ) -> TrackWithLibrary {
         TrackWithLibrary {
             catalog_id: self.catalog_id,
             name: self.name,
             artist_name: self.artist_name,
-            is_explicit: self.is_explicit,
+            genre_names: self.genre_names,
+            disc_number: self.disc_number,
+            track_number: self.track_number,
+            content_rating: self.content_rating,
             isrc: self.isrc,
             release_date: self.release_date,
+            duration: self.duration,
             library_id,
+            date_added,
         }
     }
 }
@@ -127,14 +305,22 @@ impl Album<TrackNoLibrary> {
         ensure!(library_response.data.len() == 1);
         let library_album = &library_response.data[0];
         ensure!(library_album.relationships.catalog.data.len() == 1);
-        ensure!(library_album.relationships.catalog.data[0].id == self.catalog_id);
+        ensure!(self.catalog_id == library_album.relationships.catalog.data[0].id.as_str());
 
-        let mut catalog_to_library: HashMap<&str, &str> = HashMap::new();
+        let mut catalog_to_library: HashMap<&str, (LibrarySongId, Option<String>)> = HashMap::new();
         for library_song in &library_album.relationships.tracks.data {
-            let catalog_id = &library_song.attributes.play_params.catalog_id;
-            ensure!(!catalog_to_library.contains_key(catalog_id.as_str()));
-            ensure!(self.tracks.iter().any(|t| &t.catalog_id == catalog_id));
-            catalog_to_library.insert(catalog_id, &library_song.id);
+            let Some(catalog_id) = library_song.catalog_id() else {
+                continue;
+            };
+            ensure!(!catalog_to_library.contains_key(catalog_id));
+            ensure!(self.tracks.iter().any(|t| t.catalog_id == catalog_id));
+            catalog_to_library.insert(
+                catalog_id,
+                (
+                    library_song.id.parse()?,
+                    library_song.attributes.date_added.clone(),
+                ),
+            );
         }
 
         ensure!(!catalog_to_library.is_empty());
@@ -143,10 +329,11 @@ impl Album<TrackNoLibrary> {
             .tracks
             .into_iter()
             .map(|track| {
-                let library_id = catalog_to_library
+                let (library_id, date_added) = catalog_to_library
                     .get(track.catalog_id.as_str())
-                    .map(|&id| id.to_owned());
-                track.with_library_id(library_id)
+                    .cloned()
+                    .unzip();
+                track.with_library_id(library_id, date_added.flatten())
             })
             .collect();
 
@@ -154,7 +341,11 @@ impl Album<TrackNoLibrary> {
             catalog_id: self.catalog_id,
             name: self.name,
             artist_name: self.artist_name,
+            genre_names: self.genre_names,
             release_date: self.release_date,
+            upc: self.upc,
+            audio_variants: self.audio_variants,
+            apple_digital_master: self.apple_digital_master,
             tracks,
         })
     }
@@ -172,21 +363,29 @@ mod tests {
                 attributes: api_types::catalog_album::AlbumAttributes {
                     name: "Album".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec!["Pop".to_owned(), "Music".to_owned()],
+                    release_date: Some("2000-01-01".to_owned()),
                     track_count: 1,
+                    upc: Some("00000000000001".to_owned()),
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
                 },
                 relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
                     tracks: api_types::catalog_album::AlbumRelationshipsTracks {
                         data: vec![api_types::catalog_album::Song {
                             id: "1".to_owned(),
+                            kind: api_types::catalog_album::SongType::Songs,
                             attributes: api_types::catalog_album::SongAttributes {
                                 name: "Song 1".to_owned(),
                                 artist_name: "Artist".to_owned(),
+                                genre_names: vec!["Pop".to_owned()],
                                 content_rating: None,
                                 disc_number: 1,
-                                isrc: "ISRC1".to_owned(),
-                                release_date: "2000-01-01".to_owned(),
+                                duration_in_millis: None,
+                                isrc: Some("ISRC1".to_owned()),
+                                release_date: Some("2000-01-01".to_owned()),
                                 track_number: 1,
+                                play_params: Some(serde::de::IgnoredAny),
                             },
                         }],
                     },
@@ -195,18 +394,27 @@ mod tests {
         };
         let album = Album::try_from(response).unwrap();
         let expected = Album {
-            catalog_id: "1".to_owned(),
+            catalog_id: "1".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec!["Pop".to_owned(), "Music".to_owned()],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: Some("00000000000001".to_owned()),
             tracks: vec![TrackNoLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2000-01-01".to_owned(),
+                genre_names: vec!["Pop".to_owned()],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2000-01-01".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         assert_eq!(album, expected);
     }
@@ -219,36 +427,48 @@ mod tests {
                 attributes: api_types::catalog_album::AlbumAttributes {
                     name: "Album".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
                     track_count: 2,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
                 },
                 relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
                     tracks: api_types::catalog_album::AlbumRelationshipsTracks {
                         data: vec![
                             api_types::catalog_album::Song {
                                 id: "2".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 2".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: Some(
                                         api_types::catalog_album::ContentRating::Explicit,
                                     ),
                                     disc_number: 1,
-                                    isrc: "ISRC2".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC2".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 2,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                             api_types::catalog_album::Song {
                                 id: "1".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 1".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC1".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC1".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                         ],
@@ -258,28 +478,42 @@ mod tests {
         };
         let album = Album::try_from(response).unwrap();
         let expected = Album {
-            catalog_id: "1".to_owned(),
+            catalog_id: "1".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: true,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: Some(ContentRating::Explicit),
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         assert_eq!(album, expected);
     }
@@ -292,48 +526,64 @@ mod tests {
                 attributes: api_types::catalog_album::AlbumAttributes {
                     name: "Album".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
                     track_count: 3,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
                 },
                 relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
                     tracks: api_types::catalog_album::AlbumRelationshipsTracks {
                         data: vec![
                             api_types::catalog_album::Song {
                                 id: "3".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 3".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 2,
-                                    isrc: "ISRC3".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC3".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 2,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                             api_types::catalog_album::Song {
                                 id: "2".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 2".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: Some(
                                         api_types::catalog_album::ContentRating::Explicit,
                                     ),
                                     disc_number: 2,
-                                    isrc: "ISRC2".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC2".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                             api_types::catalog_album::Song {
                                 id: "1".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 1".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC1".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC1".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                         ],
@@ -343,40 +593,155 @@ mod tests {
         };
         let album = Album::try_from(response).unwrap();
         let expected = Album {
-            catalog_id: "1".to_owned(),
+            catalog_id: "1".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: true,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 2,
+                    track_number: 1,
+                    content_rating: Some(ContentRating::Explicit),
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "3".to_owned(),
+                    catalog_id: "3".parse().unwrap(),
                     name: "Song 3".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC3".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 2,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC3".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         assert_eq!(album, expected);
     }
 
+    #[test]
+    fn test_catalog_album_into_album_clean_content_rating() {
+        let response = api_types::catalog_album::Root {
+            data: vec![api_types::catalog_album::Album {
+                id: "1".to_owned(),
+                attributes: api_types::catalog_album::AlbumAttributes {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
+                    track_count: 1,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
+                },
+                relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
+                    tracks: api_types::catalog_album::AlbumRelationshipsTracks {
+                        data: vec![api_types::catalog_album::Song {
+                            id: "1".to_owned(),
+                            kind: api_types::catalog_album::SongType::Songs,
+                            attributes: api_types::catalog_album::SongAttributes {
+                                name: "Song 1".to_owned(),
+                                artist_name: "Artist".to_owned(),
+                                genre_names: vec![],
+                                content_rating: Some(
+                                    api_types::catalog_album::ContentRating::Clean,
+                                ),
+                                disc_number: 1,
+                                duration_in_millis: None,
+                                isrc: Some("ISRC1".to_owned()),
+                                release_date: Some("2000-01-01".to_owned()),
+                                track_number: 1,
+                                play_params: Some(serde::de::IgnoredAny),
+                            },
+                        }],
+                    },
+                },
+            }],
+        };
+        assert!(api_types::UnknownValues::unknown_values(&response).is_empty());
+        let album = Album::try_from(response).unwrap();
+        assert_eq!(album.tracks[0].content_rating, Some(ContentRating::Clean));
+    }
+
+    #[test]
+    fn test_catalog_album_into_album_unknown_content_rating() {
+        let response = api_types::catalog_album::Root {
+            data: vec![api_types::catalog_album::Album {
+                id: "1".to_owned(),
+                attributes: api_types::catalog_album::AlbumAttributes {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
+                    track_count: 1,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
+                },
+                relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
+                    tracks: api_types::catalog_album::AlbumRelationshipsTracks {
+                        data: vec![api_types::catalog_album::Song {
+                            id: "1".to_owned(),
+                            kind: api_types::catalog_album::SongType::Songs,
+                            attributes: api_types::catalog_album::SongAttributes {
+                                name: "Song 1".to_owned(),
+                                artist_name: "Artist".to_owned(),
+                                genre_names: vec![],
+                                content_rating: Some(
+                                    api_types::catalog_album::ContentRating::Other(
+                                        "new".to_owned(),
+                                    ),
+                                ),
+                                disc_number: 1,
+                                duration_in_millis: None,
+                                isrc: Some("ISRC1".to_owned()),
+                                release_date: Some("2000-01-01".to_owned()),
+                                track_number: 1,
+                                play_params: Some(serde::de::IgnoredAny),
+                            },
+                        }],
+                    },
+                },
+            }],
+        };
+        assert_eq!(
+            api_types::UnknownValues::unknown_values(&response),
+            ["content rating `new` on song 1"],
+        );
+        let album = Album::try_from(response).unwrap();
+        assert_eq!(
+            album.tracks[0].content_rating,
+            Some(ContentRating::Other("new".to_owned())),
+        );
+    }
+
     #[test]
     fn test_catalog_album_into_album_track_count_mismatch() {
         let response = api_types::catalog_album::Root {
@@ -385,21 +750,29 @@ mod tests {
                 attributes: api_types::catalog_album::AlbumAttributes {
                     name: "Album".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
                     track_count: 2,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
                 },
                 relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
                     tracks: api_types::catalog_album::AlbumRelationshipsTracks {
                         data: vec![api_types::catalog_album::Song {
                             id: "1".to_owned(),
+                            kind: api_types::catalog_album::SongType::Songs,
                             attributes: api_types::catalog_album::SongAttributes {
                                 name: "Song 1".to_owned(),
                                 artist_name: "Artist".to_owned(),
+                                genre_names: vec![],
                                 content_rating: None,
                                 disc_number: 1,
-                                isrc: "ISRC1".to_owned(),
-                                release_date: "2000-01-01".to_owned(),
+                                duration_in_millis: None,
+                                isrc: Some("ISRC1".to_owned()),
+                                release_date: Some("2000-01-01".to_owned()),
                                 track_number: 1,
+                                play_params: Some(serde::de::IgnoredAny),
                             },
                         }],
                     },
@@ -417,34 +790,46 @@ mod tests {
                 attributes: api_types::catalog_album::AlbumAttributes {
                     name: "Album".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
                     track_count: 2,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
                 },
                 relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
                     tracks: api_types::catalog_album::AlbumRelationshipsTracks {
                         data: vec![
                             api_types::catalog_album::Song {
                                 id: "1".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 1".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC1".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC1".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                             api_types::catalog_album::Song {
                                 id: "2".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 2".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC2".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC2".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                         ],
@@ -463,34 +848,46 @@ mod tests {
                 attributes: api_types::catalog_album::AlbumAttributes {
                     name: "Album".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
                     track_count: 2,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
                 },
                 relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
                     tracks: api_types::catalog_album::AlbumRelationshipsTracks {
                         data: vec![
                             api_types::catalog_album::Song {
                                 id: "1".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 1".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC1".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC1".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                             api_types::catalog_album::Song {
                                 id: "1".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 2".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC2".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC2".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 2,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                         ],
@@ -509,34 +906,46 @@ mod tests {
                 attributes: api_types::catalog_album::AlbumAttributes {
                     name: "Album".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
                     track_count: 2,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
                 },
                 relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
                     tracks: api_types::catalog_album::AlbumRelationshipsTracks {
                         data: vec![
                             api_types::catalog_album::Song {
                                 id: "1".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 1".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC1".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC1".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                             api_types::catalog_album::Song {
                                 id: "1".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 2".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 2,
-                                    isrc: "ISRC2".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC2".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                         ],
@@ -555,34 +964,46 @@ mod tests {
                 attributes: api_types::catalog_album::AlbumAttributes {
                     name: "Album".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
                     track_count: 2,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
                 },
                 relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
                     tracks: api_types::catalog_album::AlbumRelationshipsTracks {
                         data: vec![
                             api_types::catalog_album::Song {
                                 id: "1".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 1".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC1".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC1".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                             api_types::catalog_album::Song {
                                 id: "3".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 3".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC3".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC3".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 3,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                         ],
@@ -601,34 +1022,46 @@ mod tests {
                 attributes: api_types::catalog_album::AlbumAttributes {
                     name: "Album".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    release_date: Some("2000-01-01".to_owned()),
                     track_count: 2,
+                    upc: None,
+                    audio_variants: vec![],
+                    is_mastered_for_itunes: false,
                 },
                 relationships: api_types::catalog_album::AlbumRelationshipsWithTracks {
                     tracks: api_types::catalog_album::AlbumRelationshipsTracks {
                         data: vec![
                             api_types::catalog_album::Song {
                                 id: "1".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 1".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 1,
-                                    isrc: "ISRC1".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC1".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 1,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                             api_types::catalog_album::Song {
                                 id: "3".to_owned(),
+                                kind: api_types::catalog_album::SongType::Songs,
                                 attributes: api_types::catalog_album::SongAttributes {
                                     name: "Song 3".to_owned(),
                                     artist_name: "Artist".to_owned(),
+                                    genre_names: vec![],
                                     content_rating: None,
                                     disc_number: 2,
-                                    isrc: "ISRC3".to_owned(),
-                                    release_date: "2000-01-01".to_owned(),
+                                    duration_in_millis: None,
+                                    isrc: Some("ISRC3".to_owned()),
+                                    release_date: Some("2000-01-01".to_owned()),
                                     track_number: 2,
+                                    play_params: Some(serde::de::IgnoredAny),
                                 },
                             },
                         ],
@@ -642,32 +1075,51 @@ mod tests {
     #[test]
     fn test_with_library_info_single_track_added() {
         let album = Album {
-            catalog_id: "0".to_owned(),
+            catalog_id: "0".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let library_response = api_types::library_album::Root {
             data: vec![api_types::library_album::LibraryAlbum {
                 id: "l.0".to_owned(),
+                attributes: api_types::library_album::LibraryAlbumAttributes {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    date_added: None,
+                },
                 relationships:
                     api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
                         catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
@@ -676,12 +1128,15 @@ mod tests {
                             }],
                         },
                         tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
+                            next: None,
                             data: vec![api_types::library_album::LibrarySong {
                                 id: "i.1".to_owned(),
                                 attributes: api_types::library_album::LibrarySongAttributes {
+                                    name: "Song 1".to_owned(),
                                     play_params: api_types::library_album::LibrarySongPlayParams {
-                                        catalog_id: "1".to_owned(),
+                                        catalog_id: Some("1".to_owned()),
                                     },
+                                    date_added: None,
                                 },
                             }],
                         },
@@ -689,30 +1144,44 @@ mod tests {
             }],
         };
         let expected = Album {
-            catalog_id: "0".to_owned(),
+            catalog_id: "0".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackWithLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
-                    library_id: Some("i.1".to_owned()),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.1".parse().unwrap()),
+                    date_added: None,
                 },
                 TrackWithLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
                     library_id: None,
+                    date_added: None,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let result = album.with_library_info(&library_response).unwrap();
         assert_eq!(result, expected);
@@ -721,89 +1190,126 @@ mod tests {
     #[test]
     fn test_with_library_info_two_tracks_out_of_order() {
         let album = Album {
-            catalog_id: "0".to_owned(),
+            catalog_id: "0".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
-        let library_response = api_types::library_album::Root {
-            data: vec![api_types::library_album::LibraryAlbum {
-                id: "l.0".to_owned(),
-                relationships:
-                    api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
-                        catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
-                            data: vec![api_types::library_album::LibraryAlbumCatalog {
-                                id: "0".to_owned(),
-                            }],
-                        },
-                        tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
-                            data: vec![
+        let library_response =
+            api_types::library_album::Root {
+                data: vec![api_types::library_album::LibraryAlbum {
+                    id: "l.0".to_owned(),
+                    attributes: api_types::library_album::LibraryAlbumAttributes {
+                        name: "Album".to_owned(),
+                        artist_name: "Artist".to_owned(),
+                        date_added: None,
+                    },
+                    relationships:
+                        api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
+                            catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
+                                data: vec![api_types::library_album::LibraryAlbumCatalog {
+                                    id: "0".to_owned(),
+                                }],
+                            },
+                            tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
+                                next: None,
+                                data: vec![
                                 api_types::library_album::LibrarySong {
                                     id: "i.2".to_owned(),
                                     attributes: api_types::library_album::LibrarySongAttributes {
+                                        name: "Song 2".to_owned(),
                                         play_params:
                                             api_types::library_album::LibrarySongPlayParams {
-                                                catalog_id: "2".to_owned(),
-                                            },
+                                                catalog_id: Some("2".to_owned()),
+                                            }, date_added: None,
                                     },
                                 },
                                 api_types::library_album::LibrarySong {
                                     id: "i.1".to_owned(),
                                     attributes: api_types::library_album::LibrarySongAttributes {
+                                        name: "Song 1".to_owned(),
                                         play_params:
                                             api_types::library_album::LibrarySongPlayParams {
-                                                catalog_id: "1".to_owned(),
-                                            },
+                                                catalog_id: Some("1".to_owned()),
+                                            }, date_added: None,
                                     },
                                 },
                             ],
+                            },
                         },
-                    },
-            }],
-        };
+                }],
+            };
         let expected = Album {
-            catalog_id: "0".to_owned(),
+            catalog_id: "0".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackWithLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
-                    library_id: Some("i.1".to_owned()),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.1".parse().unwrap()),
+                    date_added: None,
                 },
                 TrackWithLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
-                    library_id: Some("i.2".to_owned()),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.2".parse().unwrap()),
+                    date_added: None,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let result = album.with_library_info(&library_response).unwrap();
         assert_eq!(result, expected);
@@ -812,86 +1318,123 @@ mod tests {
     #[test]
     fn test_with_library_info_duplicate_tracks() {
         let album = Album {
-            catalog_id: "0".to_owned(),
+            catalog_id: "0".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2000-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2000-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
-        let library_response = api_types::library_album::Root {
-            data: vec![api_types::library_album::LibraryAlbum {
-                id: "l.0".to_owned(),
-                relationships:
-                    api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
-                        catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
-                            data: vec![api_types::library_album::LibraryAlbumCatalog {
-                                id: "0".to_owned(),
-                            }],
-                        },
-                        tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
-                            data: vec![
+        let library_response =
+            api_types::library_album::Root {
+                data: vec![api_types::library_album::LibraryAlbum {
+                    id: "l.0".to_owned(),
+                    attributes: api_types::library_album::LibraryAlbumAttributes {
+                        name: "Album".to_owned(),
+                        artist_name: "Artist".to_owned(),
+                        date_added: None,
+                    },
+                    relationships:
+                        api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
+                            catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
+                                data: vec![api_types::library_album::LibraryAlbumCatalog {
+                                    id: "0".to_owned(),
+                                }],
+                            },
+                            tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
+                                next: None,
+                                data: vec![
                                 api_types::library_album::LibrarySong {
                                     id: "i.1".to_owned(),
                                     attributes: api_types::library_album::LibrarySongAttributes {
+                                        name: "Song 1".to_owned(),
                                         play_params:
                                             api_types::library_album::LibrarySongPlayParams {
-                                                catalog_id: "1".to_owned(),
-                                            },
+                                                catalog_id: Some("1".to_owned()),
+                                            }, date_added: None,
                                     },
                                 },
                                 api_types::library_album::LibrarySong {
                                     id: "i.1".to_owned(),
                                     attributes: api_types::library_album::LibrarySongAttributes {
+                                        name: "Song 1".to_owned(),
                                         play_params:
                                             api_types::library_album::LibrarySongPlayParams {
-                                                catalog_id: "1".to_owned(),
-                                            },
+                                                catalog_id: Some("1".to_owned()),
+                                            }, date_added: None,
                                     },
                                 },
                             ],
+                            },
                         },
-                    },
-            }],
-        };
+                }],
+            };
         assert!(album.with_library_info(&library_response).is_err());
     }
 
     #[test]
     fn test_with_library_info_catalog_id_mismatch() {
         let album = Album {
-            catalog_id: "0".to_owned(),
+            catalog_id: "0".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2000-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2000-01-01".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let library_response = api_types::library_album::Root {
             data: vec![api_types::library_album::LibraryAlbum {
                 id: "l.2".to_owned(),
+                attributes: api_types::library_album::LibraryAlbumAttributes {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    date_added: None,
+                },
                 relationships:
                     api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
                         catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
@@ -900,12 +1443,15 @@ mod tests {
                             }],
                         },
                         tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
+                            next: None,
                             data: vec![api_types::library_album::LibrarySong {
                                 id: "i.1".to_owned(),
                                 attributes: api_types::library_album::LibrarySongAttributes {
+                                    name: "Song 1".to_owned(),
                                     play_params: api_types::library_album::LibrarySongPlayParams {
-                                        catalog_id: "1".to_owned(),
+                                        catalog_id: Some("1".to_owned()),
                                     },
+                                    date_added: None,
                                 },
                             }],
                         },
@@ -918,22 +1464,36 @@ mod tests {
     #[test]
     fn test_with_library_info_unknown_track() {
         let album = Album {
-            catalog_id: "0".to_owned(),
+            catalog_id: "0".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2000-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2000-01-01".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let library_response = api_types::library_album::Root {
             data: vec![api_types::library_album::LibraryAlbum {
                 id: "l.0".to_owned(),
+                attributes: api_types::library_album::LibraryAlbumAttributes {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    date_added: None,
+                },
                 relationships:
                     api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
                         catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
@@ -942,12 +1502,15 @@ mod tests {
                             }],
                         },
                         tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
+                            next: None,
                             data: vec![api_types::library_album::LibrarySong {
                                 id: "i.2".to_owned(),
                                 attributes: api_types::library_album::LibrarySongAttributes {
+                                    name: "Song 2".to_owned(),
                                     play_params: api_types::library_album::LibrarySongPlayParams {
-                                        catalog_id: "2".to_owned(),
+                                        catalog_id: Some("2".to_owned()),
                                     },
+                                    date_added: None,
                                 },
                             }],
                         },
@@ -960,22 +1523,36 @@ mod tests {
     #[test]
     fn test_with_library_info_no_tracks() {
         let album = Album {
-            catalog_id: "0".to_owned(),
+            catalog_id: "0".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2000-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2000-01-01".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let library_response = api_types::library_album::Root {
             data: vec![api_types::library_album::LibraryAlbum {
                 id: "l.0".to_owned(),
+                attributes: api_types::library_album::LibraryAlbumAttributes {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    date_added: None,
+                },
                 relationships:
                     api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
                         catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
@@ -984,6 +1561,7 @@ mod tests {
                             }],
                         },
                         tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
+                            next: None,
                             data: vec![],
                         },
                     },
@@ -995,34 +1573,51 @@ mod tests {
     #[test]
     fn test_with_library_info_no_catalog() {
         let album = Album {
-            catalog_id: "0".to_owned(),
+            catalog_id: "0".parse().unwrap(),
             name: "Album".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2000-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2000-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2000-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2000-01-01".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let library_response = api_types::library_album::Root {
             data: vec![api_types::library_album::LibraryAlbum {
                 id: "l.0".to_owned(),
+                attributes: api_types::library_album::LibraryAlbumAttributes {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    date_added: None,
+                },
                 relationships:
                     api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
                         catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
                             data: vec![],
                         },
                         tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
+                            next: None,
                             data: vec![api_types::library_album::LibrarySong {
                                 id: "i.2".to_owned(),
                                 attributes: api_types::library_album::LibrarySongAttributes {
+                                    name: "Song 2".to_owned(),
                                     play_params: api_types::library_album::LibrarySongPlayParams {
-                                        catalog_id: "2".to_owned(),
+                                        catalog_id: Some("2".to_owned()),
                                     },
+                                    date_added: None,
                                 },
                             }],
                         },
@@ -1031,4 +1626,157 @@ mod tests {
         };
         assert!(album.with_library_info(&library_response).is_err());
     }
+
+    #[test]
+    fn test_library_album_into_library_album() {
+        let response = api_types::library_album::Root {
+            data: vec![api_types::library_album::LibraryAlbum {
+                id: "l.0".to_owned(),
+                attributes: api_types::library_album::LibraryAlbumAttributes {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    date_added: None,
+                },
+                relationships:
+                    api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
+                        catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
+                            data: vec![api_types::library_album::LibraryAlbumCatalog {
+                                id: "0".to_owned(),
+                            }],
+                        },
+                        tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
+                            next: None,
+                            data: vec![api_types::library_album::LibrarySong {
+                                id: "i.1".to_owned(),
+                                attributes: api_types::library_album::LibrarySongAttributes {
+                                    name: "Song 1".to_owned(),
+                                    play_params: api_types::library_album::LibrarySongPlayParams {
+                                        catalog_id: Some("1".to_owned()),
+                                    },
+                                    date_added: None,
+                                },
+                            }],
+                        },
+                    },
+            }],
+        };
+        let expected = LibraryAlbum {
+            library_id: "l.0".parse().unwrap(),
+            catalog_id: Some("0".parse().unwrap()),
+            name: "Album".to_owned(),
+            artist_name: "Artist".to_owned(),
+            tracks: vec![LibraryTrack {
+                library_id: "i.1".parse().unwrap(),
+                catalog_id: "1".parse().unwrap(),
+                name: "Song 1".to_owned(),
+                date_added: None,
+            }],
+            date_added: None,
+        };
+        assert_eq!(LibraryAlbum::try_from(response).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_library_album_into_library_album_no_catalog() {
+        let response = api_types::library_album::Root {
+            data: vec![api_types::library_album::LibraryAlbum {
+                id: "l.0".to_owned(),
+                attributes: api_types::library_album::LibraryAlbumAttributes {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    date_added: None,
+                },
+                relationships:
+                    api_types::library_album::LibraryAlbumRelationshipsWithTracksCatalog {
+                        catalog: api_types::library_album::LibraryAlbumRelationshipsCatalog {
+                            data: vec![],
+                        },
+                        tracks: api_types::library_album::LibraryAlbumRelationshipsTracks {
+                            next: None,
+                            data: vec![],
+                        },
+                    },
+            }],
+        };
+        let album = LibraryAlbum::try_from(response).unwrap();
+        assert_eq!(album.catalog_id, None);
+    }
+
+    #[test]
+    fn test_library_album_into_library_album_uploaded_song() {
+        use api_types::UnknownValues;
+
+        let response: api_types::library_album::Root = serde_json::from_str(
+            r#"{"data": [{
+                "id": "l.0",
+                "attributes": {"name": "Album", "artistName": "Artist"},
+                "relationships": {
+                    "catalog": {"data": [{"id": "0"}]},
+                    "tracks": {"data": [
+                        {"id": "i.1", "attributes": {"name": "Song 1", "playParams": {"catalogId": "1"}}},
+                        {"id": "i.2", "attributes": {"name": "Demo", "playParams": {"isLibrary": true}}},
+                        {"id": "i.3", "attributes": {"name": "Voice Memo"}}
+                    ]}
+                }
+            }]}"#,
+        )
+        .unwrap();
+        // Not rejected in strict mode, only warned about
+        assert!(response.unknown_values().is_empty());
+        assert_eq!(response.warnings().len(), 2);
+        let album = LibraryAlbum::try_from(response).unwrap();
+        assert_eq!(
+            album.tracks,
+            vec![LibraryTrack {
+                library_id: "i.1".parse().unwrap(),
+                catalog_id: "1".parse().unwrap(),
+                name: "Song 1".to_owned(),
+                date_added: None,
+            }],
+        );
+    }
+
+    #[test]
+    fn test_library_albums_into_summaries() {
+        use api_types::Page;
+
+        let response: api_types::library_albums::Root =
+            serde_json::from_str(include_str!("../../../fixtures/library-albums.json")).unwrap();
+        let albums = response
+            .into_items()
+            .into_iter()
+            .map(LibraryAlbumSummary::try_from)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(albums[0].library_id, "l.DemoStandard");
+        assert_eq!(albums[0].artist_name, "Demo Artist");
+    }
+
+    #[test]
+    fn test_catalog_album_into_album_pre_release() {
+        fn remove_release_dates(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    map.remove("releaseDate");
+                    map.values_mut().for_each(remove_release_dates);
+                }
+                serde_json::Value::Array(items) => items.iter_mut().for_each(remove_release_dates),
+                _ => {}
+            }
+        }
+        let mut json: serde_json::Value = serde_json::from_str(include_str!(
+            "../../../fixtures/catalog-album-1000000001.json"
+        ))
+        .unwrap();
+        remove_release_dates(&mut json);
+        let response: api_types::catalog_album::Root = serde_json::from_value(json).unwrap();
+        let album: Album<TrackNoLibrary> = response.try_into().unwrap();
+        assert_eq!(album.release_date, None);
+        assert!(
+            album
+                .tracks
+                .iter()
+                .all(|track| track.release_date.is_none())
+        );
+    }
 }
@@ -0,0 +1,157 @@
+//! Example albums bundled into the binary for offline mode
+
+pub const STOREFRONT: &str = "us";
+
+const FIXTURES: &[(&str, &str)] = &[
+    (
+        "/v1/catalog/us/albums/1000000001",
+        include_str!("../../fixtures/catalog-album-1000000001.json"),
+    ),
+    (
+        "/v1/catalog/us/albums/1000000002",
+        include_str!("../../fixtures/catalog-album-1000000002.json"),
+    ),
+    (
+        "/v1/catalog/us/albums/1000000001/view/other-versions",
+        include_str!("../../fixtures/catalog-album-1000000001-other-versions.json"),
+    ),
+    (
+        "/v1/catalog/us/albums/1000000002/view/other-versions",
+        include_str!("../../fixtures/catalog-album-1000000002-other-versions.json"),
+    ),
+    (
+        // Every song on the bundled albums, whatever the `ids` or `filter[isrc]` query asks for
+        "/v1/catalog/us/songs",
+        include_str!("../../fixtures/catalog-songs.json"),
+    ),
+    (
+        // Both bundled catalog albums, whatever the search term
+        "/v1/catalog/us/search",
+        include_str!("../../fixtures/search-albums.json"),
+    ),
+    (
+        "/v1/me/recent/played",
+        include_str!("../../fixtures/me-recent-played.json"),
+    ),
+    (
+        "/v1/me/history/heavy-rotation",
+        include_str!("../../fixtures/me-heavy-rotation.json"),
+    ),
+    (
+        "/v1/me/storefront",
+        include_str!("../../fixtures/me-storefront.json"),
+    ),
+    (
+        "/v1/me/library/albums",
+        include_str!("../../fixtures/library-albums.json"),
+    ),
+    (
+        "/v1/me/library/playlists",
+        include_str!("../../fixtures/library-playlists.json"),
+    ),
+    (
+        "/v1/me/library/playlists/p.DemoPlaylist/tracks",
+        include_str!("../../fixtures/library-playlist-p.DemoPlaylist-tracks.json"),
+    ),
+    (
+        "/v1/me/library/albums/l.DemoStandard",
+        include_str!("../../fixtures/library-album-l.DemoStandard.json"),
+    ),
+    (
+        "/v1/me/ratings/library-albums/l.DemoStandard",
+        include_str!("../../fixtures/ratings-library-album-l.DemoStandard.json"),
+    ),
+];
+
+/// Look up the response body for a request path, ignoring the query string
+pub fn get(path: &str) -> Option<&'static str> {
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    FIXTURES
+        .iter()
+        .find(|(fixture_path, _)| *fixture_path == path)
+        .map(|(_, body)| *body)
+}
+
+/// Human-readable summary of the IDs that can be used in offline mode
+pub fn describe() -> &'static str {
+    "the library album `l.DemoStandard` (catalog album `1000000001`) and the catalog album `1000000002`"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apple_music::{
+        api_types::{self, Page},
+        custom_types,
+    };
+
+    #[test]
+    fn test_fixtures_convert() {
+        for id in ["1000000001", "1000000002"] {
+            let body = get(&format!("/v1/catalog/us/albums/{id}")).unwrap();
+            let root: api_types::catalog_album::Root = serde_json::from_str(body).unwrap();
+            let album: custom_types::Album<custom_types::TrackNoLibrary> = root.try_into().unwrap();
+            assert_eq!(album.catalog_id, id);
+        }
+
+        let body = get("/v1/me/library/albums/l.DemoStandard?include=catalog").unwrap();
+        let library_album: api_types::library_album::Root = serde_json::from_str(body).unwrap();
+        assert_eq!(library_album.catalog_id().unwrap(), "1000000001");
+        let body = get("/v1/me/ratings/library-albums/l.DemoStandard").unwrap();
+        let ratings: api_types::ratings::Root = serde_json::from_str(body).unwrap();
+        assert!(ratings.is_favorite());
+
+        let body = get("/v1/me/library/albums?limit=100&offset=0").unwrap();
+        let library_albums: api_types::library_albums::Root = serde_json::from_str(body).unwrap();
+        assert!(!library_albums.has_next());
+        assert_eq!(library_albums.total(), Some(1));
+        assert_eq!(library_albums.ids().collect::<Vec<_>>(), ["l.DemoStandard"]);
+
+        let body = get("/v1/catalog/us/search?term=Demo&types=albums").unwrap();
+        let search: api_types::search::Root = serde_json::from_str(body).unwrap();
+        assert!(!search.has_next());
+        assert_eq!(
+            search
+                .into_items()
+                .iter()
+                .map(|album| album.id())
+                .collect::<Vec<_>>(),
+            ["1000000001", "1000000002"],
+        );
+
+        let body = get("/v1/catalog/us/albums/1000000001/view/other-versions").unwrap();
+        let other_versions: api_types::album_views::Root = serde_json::from_str(body).unwrap();
+        assert_eq!(other_versions.albums()[0].id(), "1000000002");
+
+        let body = get("/v1/me/library/playlists?limit=100&offset=0").unwrap();
+        let playlists: api_types::library_playlists::Root = serde_json::from_str(body).unwrap();
+        assert!(!playlists.has_next());
+        let playlists = playlists.into_items();
+        assert_eq!(playlists[0].id(), "p.DemoPlaylist");
+        let body = get("/v1/me/library/playlists/p.DemoPlaylist/tracks").unwrap();
+        let tracks: api_types::library_playlists::TracksRoot = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            tracks
+                .into_items()
+                .iter()
+                .map(|track| track.catalog_id())
+                .collect::<Vec<_>>(),
+            [Some("1000000011"), Some("1000000099"), None],
+        );
+
+        let body = get("/v1/me/storefront").unwrap();
+        let storefront: api_types::storefront::Root = serde_json::from_str(body).unwrap();
+        assert_eq!(storefront.storefront().unwrap().id(), STOREFRONT);
+
+        let body = get("/v1/catalog/us/songs?filter[isrc]=USDEM2000001").unwrap();
+        let songs: api_types::catalog_songs::Root = serde_json::from_str(body).unwrap();
+        assert!(songs.ids_by_isrc().any(|(isrc, _)| isrc == "USDEM2000001"));
+
+        let body = get("/v1/me/recent/played").unwrap();
+        let recent: api_types::recent::Root = serde_json::from_str(body).unwrap();
+        assert_eq!(recent.album_ids().collect::<Vec<_>>(), ["1000000001"]);
+        let body = get("/v1/me/history/heavy-rotation").unwrap();
+        let heavy_rotation: api_types::recent::Root = serde_json::from_str(body).unwrap();
+        assert_eq!(heavy_rotation.album_ids().count(), 0);
+    }
+}
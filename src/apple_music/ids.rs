@@ -0,0 +1,170 @@
+//! IDs that are validated when they're made, so one kind can't be passed as another
+
+use std::{borrow::Borrow, fmt, ops::Deref, str::FromStr};
+
+use anyhow::{Result, bail, ensure};
+use serde::{Deserialize, Serialize};
+
+fn is_catalog_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_library_id(id: &str, prefix: &str) -> bool {
+    id.strip_prefix(prefix)
+        .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident, $description:literal, $is_valid:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = anyhow::Error;
+
+            fn try_from(id: String) -> Result<Self> {
+                let is_valid: fn(&str) -> bool = $is_valid;
+                ensure!(is_valid(&id), "invalid {}: `{id}`", $description);
+                Ok(Self(id))
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = anyhow::Error;
+
+            fn from_str(id: &str) -> Result<Self> {
+                id.to_owned().try_into()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+    };
+}
+
+id_type!(
+    /// Digits, e.g. `1440833098`
+    CatalogAlbumId,
+    "album catalog ID",
+    is_catalog_id
+);
+
+id_type!(
+    /// Digits, e.g. `1440833100`
+    CatalogSongId,
+    "song catalog ID",
+    is_catalog_id
+);
+
+id_type!(
+    /// Starts with `l.`
+    LibraryAlbumId,
+    "library album ID",
+    |id| is_library_id(id, "l.")
+);
+
+id_type!(
+    /// Starts with `i.`
+    LibrarySongId,
+    "library song ID",
+    |id| is_library_id(id, "i.")
+);
+
+/// A library album, or the catalog album it was added from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlbumId {
+    Library(LibraryAlbumId),
+    Catalog(CatalogAlbumId),
+}
+
+impl FromStr for AlbumId {
+    type Err = anyhow::Error;
+
+    fn from_str(id: &str) -> Result<Self> {
+        if let Ok(library_id) = id.parse() {
+            Ok(Self::Library(library_id))
+        } else if let Ok(catalog_id) = id.parse() {
+            Ok(Self::Catalog(catalog_id))
+        } else {
+            bail!(
+                "invalid album ID: `{id}`, expected a library ID (starting with `l.`) or a catalog ID"
+            )
+        }
+    }
+}
+
+impl fmt::Display for AlbumId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Library(id) => id.fmt(f),
+            Self::Catalog(id) => id.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ids() {
+        assert!("1440833098".parse::<CatalogAlbumId>().is_ok());
+        assert!("".parse::<CatalogAlbumId>().is_err());
+        assert!("l.abc".parse::<CatalogSongId>().is_err());
+        assert!("l.abc".parse::<LibraryAlbumId>().is_ok());
+        assert!("i.abc".parse::<LibraryAlbumId>().is_err());
+        assert!("l.".parse::<LibraryAlbumId>().is_err());
+        assert!("i.abc".parse::<LibrarySongId>().is_ok());
+        assert_eq!(
+            serde_json::from_str::<CatalogSongId>("\"12\"").unwrap(),
+            "12"
+        );
+        assert!(serde_json::from_str::<CatalogSongId>("\"i.1\"").is_err());
+        assert!(matches!("l.abc".parse(), Ok(AlbumId::Library(_))));
+        assert!(matches!("123".parse(), Ok(AlbumId::Catalog(_))));
+        assert!("i.abc".parse::<AlbumId>().is_err());
+    }
+}
@@ -0,0 +1,674 @@
+//! The layers every API request goes through before it's sent, so that retrying, throttling,
+//! caching, logging and adding the user token are kept apart from the endpoints. Each layer gets
+//! the request and the rest of the stack, which it can call any number of times or not at all.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    task::{Poll, Waker},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use futures_util::{
+    FutureExt,
+    future::{self, LocalBoxFuture},
+};
+use reqwest::header::{HeaderMap, HeaderValue};
+use tokio::time::Instant;
+
+use crate::{
+    apple_music::{
+        cache::{Entry, ResponseCache},
+        client::{ApiError, RateLimits, RetryPolicy, curl_command, execute, redact_url},
+    },
+    stats,
+};
+
+#[derive(Clone)]
+pub(super) struct Request {
+    pub method: reqwest::Method,
+    pub url: String,
+    /// Relative to the API host, for `--stats`
+    pub path: String,
+    /// Sent in addition to the client's default headers
+    pub headers: HeaderMap,
+    /// Sent as JSON
+    pub body: Option<String>,
+    pub with_user_token: bool,
+    pub library_endpoint: bool,
+}
+
+impl Request {
+    /// Whether sending it twice has the same effect as sending it once
+    fn is_idempotent(&self) -> bool {
+        self.method == reqwest::Method::GET
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct Response {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+pub(super) trait Middleware {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> LocalBoxFuture<'a, Result<Response>>;
+}
+
+/// The layers after the current one, ending with sending the request
+#[derive(Clone, Copy)]
+pub(super) struct Next<'a> {
+    client: &'a reqwest::Client,
+    max_response_size: usize,
+    layers: &'a [Box<dyn Middleware>],
+}
+
+impl<'a> Next<'a> {
+    pub fn run(self, request: Request) -> LocalBoxFuture<'a, Result<Response>> {
+        match self.layers.split_first() {
+            Some((layer, layers)) => layer.handle(
+                request,
+                Next {
+                    client: self.client,
+                    max_response_size: self.max_response_size,
+                    layers,
+                },
+            ),
+            None => async move {
+                let mut builder = self
+                    .client
+                    .request(request.method.clone(), &request.url)
+                    .headers(request.headers);
+                if let Some(body) = request.body {
+                    builder = builder
+                        .header(reqwest::header::CONTENT_TYPE, "application/json")
+                        .body(body);
+                }
+                let result =
+                    execute(builder, request.library_endpoint, self.max_response_size).await;
+                let bytes_received = result.as_ref().map_or(0, |(_, body)| body.len());
+                stats::record_request(&request.method, &request.path, bytes_received);
+                let (status, body) = result?;
+                Ok(Response { status, body })
+            }
+            .boxed_local(),
+        }
+    }
+}
+
+/// Layers in the order they handle a request, the first one sees it first
+pub(super) struct Stack {
+    client: reqwest::Client,
+    /// Bytes
+    max_response_size: usize,
+    layers: Vec<Box<dyn Middleware>>,
+}
+
+impl Stack {
+    pub fn new(client: reqwest::Client, max_response_size: usize) -> Self {
+        Self {
+            client,
+            max_response_size,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Added after (inside) the layers so far
+    pub fn layer(mut self, layer: impl Middleware + 'static) -> Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    pub async fn send(&self, request: Request) -> Result<Response> {
+        Next {
+            client: &self.client,
+            max_response_size: self.max_response_size,
+            layers: &self.layers,
+        }
+        .run(request)
+        .await
+    }
+}
+
+/// Catalog responses don't change within a run, so each is only requested once, and concurrent
+/// requests for the same URL wait for the first one instead of being sent too. Library responses
+/// aren't cached since adding and removing albums changes them.
+#[derive(Default)]
+pub(super) struct Cache {
+    /// How long a response is reused for, `None` for the rest of the run
+    ttl: Option<Duration>,
+    /// With when they were received
+    responses: RefCell<HashMap<String, (Instant, Response)>>,
+    /// URLs being requested, with the requests waiting for them
+    in_flight: RefCell<HashMap<String, Vec<Waker>>>,
+}
+
+/// Wakes the waiting requests when the request ends, even if it was dropped
+struct InFlight<'a> {
+    cache: &'a Cache,
+    url: String,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        let wakers = self.cache.in_flight.borrow_mut().remove(&self.url);
+        for waker in wakers.into_iter().flatten() {
+            waker.wake();
+        }
+    }
+}
+
+impl Cache {
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            ..Self::default()
+        }
+    }
+
+    async fn wait_for(&self, url: &str) {
+        future::poll_fn(|cx| match self.in_flight.borrow_mut().get_mut(url) {
+            Some(wakers) => {
+                wakers.push(cx.waker().clone());
+                Poll::Pending
+            }
+            None => Poll::Ready(()),
+        })
+        .await
+    }
+}
+
+impl Middleware for Cache {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> LocalBoxFuture<'a, Result<Response>> {
+        async move {
+            if !request.is_idempotent() || request.with_user_token {
+                return next.run(request).await;
+            }
+            loop {
+                if let Some((received, response)) = self.responses.borrow().get(&request.url)
+                    && self.ttl.is_none_or(|ttl| received.elapsed() < ttl)
+                {
+                    stats::record_cache_hit();
+                    return Ok(response.clone());
+                }
+                if !self.in_flight.borrow().contains_key(&request.url) {
+                    break;
+                }
+                // Sent again below if it failed
+                self.wait_for(&request.url).await;
+            }
+            self.in_flight
+                .borrow_mut()
+                .insert(request.url.clone(), Vec::new());
+            let in_flight = InFlight {
+                cache: self,
+                url: request.url.clone(),
+            };
+            let response = next.run(request).await?;
+            self.responses
+                .borrow_mut()
+                .insert(in_flight.url.clone(), (Instant::now(), response.clone()));
+            Ok(response)
+        }
+        .boxed_local()
+    }
+}
+
+/// Catalog responses from earlier runs, see [`ResponseCache`]. The response is requested instead
+/// if the cache can't be read, and a response that can't be stored is still returned.
+pub(super) struct DiskCache {
+    pub cache: ResponseCache,
+    /// How old a stored response can be to be used
+    pub ttl: Duration,
+    /// Whether stored responses are used, responses are stored either way so that the ones
+    /// requested without the cache replace the older ones
+    pub read: bool,
+}
+
+impl Middleware for DiskCache {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> LocalBoxFuture<'a, Result<Response>> {
+        async move {
+            if !request.is_idempotent() || request.with_user_token {
+                return next.run(request).await;
+            }
+            let ttl = i64::try_from(self.ttl.as_secs()).unwrap_or(i64::MAX);
+            match self.read.then(|| self.cache.get(&request.url)).transpose() {
+                Ok(Some(Some(entry)))
+                    if chrono::Utc::now().timestamp() - entry.received_at < ttl
+                        && let Ok(status) = reqwest::StatusCode::from_u16(entry.status) =>
+                {
+                    stats::record_cache_hit();
+                    return Ok(Response {
+                        status,
+                        body: entry.body,
+                    });
+                }
+                Ok(_) => {}
+                Err(error) => eprintln!(
+                    "Warning: failed to read {}: {error:#}",
+                    self.cache.path().display(),
+                ),
+            }
+            let (url, path) = (request.url.clone(), request.path.clone());
+            let response = next.run(request).await?;
+            let entry = Entry {
+                received_at: chrono::Utc::now().timestamp(),
+                status: response.status.as_u16(),
+                body: response.body.clone(),
+            };
+            if let Err(error) = self.cache.insert(&url, &path, &entry) {
+                eprintln!(
+                    "Warning: failed to write to {}: {error:#}",
+                    self.cache.path().display(),
+                );
+            }
+            Ok(response)
+        }
+        .boxed_local()
+    }
+}
+
+/// Prints an equivalent `curl` command for every request that's sent
+pub(super) struct Curl {
+    /// The client's default headers
+    pub headers: HeaderMap,
+}
+
+impl Middleware for Curl {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> LocalBoxFuture<'a, Result<Response>> {
+        eprintln!(
+            "{}",
+            curl_command(
+                &request.method,
+                &request.url,
+                &self.headers,
+                request.with_user_token,
+                request.body.as_deref(),
+            ),
+        );
+        next.run(request)
+    }
+}
+
+/// Records a span for every request, including the retries it took
+#[cfg(feature = "otel")]
+pub(super) struct Trace;
+
+#[cfg(feature = "otel")]
+impl Middleware for Trace {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> LocalBoxFuture<'a, Result<Response>> {
+        async move {
+            let url = redact_url(&request.url);
+            // Without the query so requests for different IDs are grouped together
+            let path = url.split('?').next().unwrap_or_default();
+            let mut span = crate::telemetry::span(format!("{} {path}", request.method));
+            span.attribute("http.request.method", &request.method);
+            span.attribute("url.full", &url);
+            let response = next.run(request).await;
+            if let Ok(response) = &response {
+                span.attribute("http.response.status_code", response.status.as_u16());
+            }
+            span.result(response)
+        }
+        .boxed_local()
+    }
+}
+
+/// Whether an error happened in transport (connection reset, timeout, etc.) rather than being a
+/// response from the API, so it is worth trying the request again
+fn is_transient(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<reqwest::Error>().is_some_and(|error| {
+        error.is_connect() || error.is_timeout() || error.is_request() || error.is_body()
+    })
+}
+
+/// Retries requests as configured by the [`RetryPolicy`], counting the rate-limited ones
+pub(super) struct Retry {
+    pub policy: RetryPolicy,
+    pub rate_limits: Rc<Cell<RateLimits>>,
+}
+
+impl Retry {
+    /// How long to wait before retrying a request that failed with `error`, if it was rate limited
+    /// and can be retried again
+    fn rate_limited_delay(&self, error: &anyhow::Error, retries: u32) -> Option<Duration> {
+        let error = error.downcast_ref::<ApiError>()?;
+        if error.status != reqwest::StatusCode::TOO_MANY_REQUESTS
+            || retries >= self.policy.max_rate_limited_retries
+        {
+            return None;
+        }
+        Some(
+            error
+                .retry_after
+                .unwrap_or(self.policy.rate_limited_backoff * 2u32.pow(retries)),
+        )
+    }
+
+    async fn wait_for_rate_limit(&self, request: &Request, delay: Duration) {
+        let mut rate_limits = self.rate_limits.get();
+        rate_limits.throttled += 1;
+        rate_limits.waited += delay;
+        self.rate_limits.set(rate_limits);
+        eprintln!(
+            "Rate limited on {} {}, retrying in {delay:?} ({rate_limits})",
+            request.method,
+            redact_url(&request.url),
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+impl Middleware for Retry {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> LocalBoxFuture<'a, Result<Response>> {
+        async move {
+            let mut attempt = 1;
+            let mut backoff = self.policy.initial_backoff;
+            let mut rate_limited_retries = 0;
+            loop {
+                let error = match next.run(request.clone()).await {
+                    Ok(response) => return Ok(response),
+                    Err(error) => error,
+                };
+                if let Some(delay) = self.rate_limited_delay(&error, rate_limited_retries) {
+                    stats::record_retry();
+                    self.wait_for_rate_limit(&request, delay).await;
+                    rate_limited_retries += 1;
+                    continue;
+                }
+                if request.is_idempotent()
+                    && attempt < self.policy.max_attempts
+                    && is_transient(&error)
+                {
+                    eprintln!(
+                        "Retrying {} {} in {backoff:?} after error (attempt {attempt} of {}): {error}",
+                        request.method,
+                        redact_url(&request.url),
+                        self.policy.max_attempts,
+                    );
+                    stats::record_retry();
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                    backoff *= 2;
+                    continue;
+                }
+                return Err(error);
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Waits at least `interval` between starting requests, including retries
+pub(super) struct Throttle {
+    pub interval: Duration,
+    pub last_request: Cell<Option<Instant>>,
+}
+
+impl Middleware for Throttle {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> LocalBoxFuture<'a, Result<Response>> {
+        async move {
+            let now = Instant::now();
+            let start = self
+                .last_request
+                .get()
+                .map_or(now, |last_request| (last_request + self.interval).max(now));
+            // Taken before waiting, so that concurrent requests wait for the ones before them
+            // instead of all starting once the interval has passed
+            self.last_request.set(Some(start));
+            tokio::time::sleep_until(start).await;
+            next.run(request).await
+        }
+        .boxed_local()
+    }
+}
+
+/// Adds the `Media-User-Token` header to the requests that need it
+pub(super) struct UserToken {
+    pub user_token: Option<String>,
+}
+
+impl Middleware for UserToken {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> LocalBoxFuture<'a, Result<Response>> {
+        async move {
+            if request.with_user_token {
+                let user_token = self.user_token.as_deref().context(
+                    "no user token given, pass `--user-token` or run `auth` to store one",
+                )?;
+                request
+                    .headers
+                    .insert("Media-User-Token", HeaderValue::from_str(user_token)?);
+            }
+            next.run(request).await
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers every request itself after yielding once, counting them
+    struct Counter(Rc<Cell<u32>>);
+
+    impl Middleware for Counter {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> LocalBoxFuture<'a, Result<Response>> {
+            self.0.set(self.0.get() + 1);
+            async {
+                tokio::task::yield_now().await;
+                Ok(Response {
+                    status: reqwest::StatusCode::OK,
+                    body: String::new(),
+                })
+            }
+            .boxed_local()
+        }
+    }
+
+    fn request(method: reqwest::Method, with_user_token: bool) -> Request {
+        Request {
+            method,
+            url: "https://example.com/v1/catalog/us/albums/1".to_owned(),
+            path: "/v1/catalog/us/albums/1".to_owned(),
+            headers: HeaderMap::new(),
+            body: None,
+            with_user_token,
+            library_endpoint: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let sent = Rc::new(Cell::new(0));
+        let stack = Stack::new(reqwest::Client::new(), 0)
+            .layer(Cache::new(None))
+            .layer(Counter(sent.clone()));
+        for _ in 0..2 {
+            stack
+                .send(request(reqwest::Method::GET, false))
+                .await
+                .unwrap();
+        }
+        assert_eq!(sent.get(), 1);
+
+        let mut other = request(reqwest::Method::GET, false);
+        other.url += "0";
+        let (first, second) = future::join(stack.send(other.clone()), stack.send(other)).await;
+        assert!(first.is_ok() && second.is_ok());
+        assert_eq!(sent.get(), 2);
+
+        stack
+            .send(request(reqwest::Method::GET, true))
+            .await
+            .unwrap();
+        stack
+            .send(request(reqwest::Method::POST, false))
+            .await
+            .unwrap();
+        assert_eq!(sent.get(), 4);
+
+        let sent = Rc::new(Cell::new(0));
+        let stack = Stack::new(reqwest::Client::new(), 0)
+            .layer(Cache::new(Some(Duration::ZERO)))
+            .layer(Counter(sent.clone()));
+        for _ in 0..2 {
+            stack
+                .send(request(reqwest::Method::GET, false))
+                .await
+                .unwrap();
+        }
+        assert_eq!(sent.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache() {
+        let directory = std::env::temp_dir().join(format!(
+            "{}-test-disk-cache-{}",
+            env!("CARGO_PKG_NAME"),
+            std::process::id(),
+        ));
+        let path = directory.join("responses.sqlite3");
+        let sent = Rc::new(Cell::new(0));
+        // Each stack is a run of its own
+        let stack = |ttl, read| {
+            Stack::new(reqwest::Client::new(), 0)
+                .layer(DiskCache {
+                    cache: ResponseCache::open(&path).unwrap(),
+                    ttl,
+                    read,
+                })
+                .layer(Counter(sent.clone()))
+        };
+        for _ in 0..2 {
+            stack(Duration::from_secs(60), true)
+                .send(request(reqwest::Method::GET, false))
+                .await
+                .unwrap();
+        }
+        assert_eq!(sent.get(), 1);
+
+        stack(Duration::from_secs(60), true)
+            .send(request(reqwest::Method::GET, true))
+            .await
+            .unwrap();
+        stack(Duration::ZERO, true)
+            .send(request(reqwest::Method::GET, false))
+            .await
+            .unwrap();
+        assert_eq!(sent.get(), 3);
+
+        // Sent without reading the cache, and still stored
+        let cache = ResponseCache::open(&path).unwrap();
+        let request = request(reqwest::Method::GET, false);
+        let old = Entry {
+            received_at: 0,
+            status: 200,
+            body: String::new(),
+        };
+        cache.insert(&request.url, &request.path, &old).unwrap();
+        stack(Duration::MAX, false)
+            .send(request.clone())
+            .await
+            .unwrap();
+        assert_eq!(sent.get(), 4);
+        assert!(cache.get(&request.url).unwrap().unwrap().received_at > 0);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttle() {
+        /// Records when each request reaches it
+        struct Sent(Rc<RefCell<Vec<Instant>>>);
+
+        impl Middleware for Sent {
+            fn handle<'a>(
+                &'a self,
+                _request: Request,
+                _next: Next<'a>,
+            ) -> LocalBoxFuture<'a, Result<Response>> {
+                self.0.borrow_mut().push(Instant::now());
+                future::ok(Response {
+                    status: reqwest::StatusCode::OK,
+                    body: String::new(),
+                })
+                .boxed_local()
+            }
+        }
+
+        let interval = Duration::from_millis(50);
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let stack = Stack::new(reqwest::Client::new(), 0)
+            .layer(Throttle {
+                interval,
+                last_request: Cell::new(None),
+            })
+            .layer(Sent(sent.clone()));
+        let results =
+            future::join_all((0..4).map(|_| stack.send(request(reqwest::Method::GET, false))))
+                .await;
+        assert!(results.iter().all(Result::is_ok));
+        let sent = sent.borrow();
+        assert_eq!(sent.len(), 4);
+        for pair in sent.windows(2) {
+            assert_eq!(pair[1] - pair[0], interval);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_user_token() {
+        let sent = Rc::new(Cell::new(0));
+        let stack = Stack::new(reqwest::Client::new(), 0)
+            .layer(UserToken { user_token: None })
+            .layer(Counter(sent.clone()));
+        assert!(
+            stack
+                .send(request(reqwest::Method::GET, true))
+                .await
+                .is_err()
+        );
+        stack
+            .send(request(reqwest::Method::GET, false))
+            .await
+            .unwrap();
+        assert_eq!(sent.get(), 1);
+    }
+}
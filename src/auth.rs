@@ -0,0 +1,187 @@
+use std::{
+    hash::{BuildHasher, RandomState},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail, ensure};
+use futures_util::{StreamExt, stream::FuturesUnordered};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Apple Music authorization</title>
+<script src="https://js-cdn.music.apple.com/musickit/v3/musickit.js" async></script>
+</head>
+<body>
+<p id="status">Loading MusicKit&hellip;</p>
+<button id="authorize" hidden>Sign in to Apple Music</button>
+<script>
+const status = document.getElementById("status");
+const button = document.getElementById("authorize");
+document.addEventListener("musickitloaded", async () => {
+  try {
+    await MusicKit.configure({
+      developerToken: __DEVELOPER_TOKEN__,
+      app: { name: __APP_NAME__, build: __APP_VERSION__ },
+    });
+  } catch (error) {
+    status.textContent = `MusicKit could not be configured: ${error}`;
+    return;
+  }
+  status.textContent = "Sign in to send the Media User Token back to the terminal.";
+  button.hidden = false;
+  button.addEventListener("click", async () => {
+    try {
+      const userToken = await MusicKit.getInstance().authorize();
+      const response = await fetch(__CALLBACK_PATH__, { method: "POST", body: userToken });
+      if (!response.ok) throw new Error(`HTTP ${response.status}`);
+      status.textContent = "Done, you can close this page.";
+      button.hidden = true;
+    } catch (error) {
+      status.textContent = `Authorization failed: ${error}`;
+    }
+  });
+});
+</script>
+</body>
+</html>
+"#;
+
+/// Encode a value as a JavaScript literal that is safe to embed in a `<script>` element
+fn js_literal(value: &str) -> String {
+    serde_json::Value::from(value)
+        .to_string()
+        .replace('<', "\\u003c")
+}
+
+fn render_page(developer_token: &str, callback_path: &str) -> String {
+    PAGE.replace("__DEVELOPER_TOKEN__", &js_literal(developer_token))
+        .replace("__APP_NAME__", &js_literal(env!("CARGO_PKG_NAME")))
+        .replace("__APP_VERSION__", &js_literal(env!("CARGO_PKG_VERSION")))
+        .replace("__CALLBACK_PATH__", &js_literal(callback_path))
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        bail!("malformed request line");
+    };
+    let (method, path) = (method.to_owned(), path.to_owned());
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        ensure!(reader.read_line(&mut line).await? > 0, "unexpected EOF");
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse()?;
+        }
+    }
+    ensure!(content_length <= 64 * 1024, "request body too large");
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+    Ok(Request { method, path, body })
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len(),
+    );
+    // The browser going away early doesn't matter, the terminal reports the outcome
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+/// Requests are read from every open connection at once, connections that don't send one in time
+/// (like a browser's speculative preconnect) are dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve a page that authorizes with MusicKit JS and wait for it to send back the Media User Token
+pub async fn authorize(developer_token: &str, port: u16) -> Result<String> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("failed to listen on port {port}"))?;
+    let port = listener.local_addr()?.port();
+
+    // Only the page served by this process knows the path to send the token to
+    let callback_path = format!("/token/{:016x}", RandomState::new().hash_one(port));
+    let page = render_page(developer_token, &callback_path);
+
+    println!("Open http://localhost:{port}/ in a browser and sign in to Apple Music");
+
+    let mut pending = FuturesUnordered::new();
+    loop {
+        let (mut stream, result) = tokio::select! {
+            accepted = listener.accept() => {
+                let (mut stream, _) = accepted?;
+                pending.push(async move {
+                    let result =
+                        tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await;
+                    (stream, result)
+                });
+                continue;
+            }
+            Some(read) = pending.next() => read,
+        };
+        let request = match result {
+            Ok(Ok(request)) => request,
+            Ok(Err(error)) => {
+                eprintln!("Ignoring malformed request: {error:#}");
+                continue;
+            }
+            Err(_) => continue,
+        };
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/") => respond(&mut stream, "200 OK", "text/html; charset=utf-8", &page).await,
+            ("POST", path) if path == callback_path => {
+                let user_token = String::from_utf8(request.body)
+                    .context("Media User Token is not valid UTF-8")?
+                    .trim()
+                    .to_owned();
+                ensure!(!user_token.is_empty(), "received an empty Media User Token");
+                respond(&mut stream, "204 No Content", "text/plain", "").await;
+                return Ok(user_token);
+            }
+            _ => respond(&mut stream, "404 Not Found", "text/plain", "Not Found").await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_js_literal_escapes_script_end() {
+        assert_eq!(js_literal("a\"</script>"), "\"a\\\"\\u003c/script>\"");
+    }
+
+    #[test]
+    fn test_render_page_replaces_placeholders() {
+        let page = render_page("a.b.c", "/token/0");
+        assert!(!page.contains("__"));
+        assert!(page.contains("developerToken: \"a.b.c\""));
+        assert!(page.contains("fetch(\"/token/0\""));
+    }
+}
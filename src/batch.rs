@@ -0,0 +1,415 @@
+use std::{fs, path::Path, str::FromStr};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use futures_util::{StreamExt, stream::FuturesUnordered};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    apple_music::{self, AlbumId, ApiError, CatalogAlbumId, LibraryAlbumId},
+    discovery, interrupt, matching, migrate, output, telemetry, yaml,
+};
+
+const BATCH_VERSION: u32 = 1;
+
+/// Migrations to review before running them
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Batch {
+    pub version: u32,
+    pub pairs: Vec<BatchPair>,
+    /// Links that couldn't be paired, for reference
+    pub skipped: Vec<SkippedLink>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchPair {
+    pub source_library_id: LibraryAlbumId,
+    pub source_name: String,
+    pub destination_catalog_id: CatalogAlbumId,
+    pub destination_name: String,
+    pub artist_name: String,
+}
+
+/// A pair as read by [`check`], with the IDs left unparsed so one bad ID doesn't stop the others
+/// from being checked
+#[derive(Deserialize)]
+struct UncheckedPair {
+    source_library_id: String,
+    #[serde(default)]
+    source_name: String,
+    destination_catalog_id: String,
+    #[serde(default)]
+    destination_name: String,
+}
+
+#[derive(Deserialize)]
+struct UncheckedBatch {
+    version: u32,
+    pairs: Vec<UncheckedPair>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedLink {
+    pub link: String,
+    pub reason: String,
+}
+
+/// An album link from the Music app or music.apple.com, either
+/// `https://music.apple.com/library/albums/l.…` or `https://music.apple.com/us/album/name/123…`,
+/// where the storefront is ignored
+#[derive(Debug, PartialEq, Eq)]
+pub struct AlbumLink(pub AlbumId);
+
+impl FromStr for AlbumLink {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let url = reqwest::Url::parse(value).context("not a URL")?;
+        ensure!(
+            url.host_str() == Some("music.apple.com"),
+            "not a music.apple.com link",
+        );
+        let segments: Vec<&str> = url
+            .path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        match segments.as_slice() {
+            ["library", "albums", id] => Ok(AlbumLink(AlbumId::Library(id.parse()?))),
+            [_, "album", .., id] => Ok(AlbumLink(AlbumId::Catalog(id.parse()?))),
+            _ => bail!("not an album link"),
+        }
+    }
+}
+
+enum Outcome {
+    Paired(BatchPair),
+    Skipped(String),
+}
+
+/// How many links are paired at once, since the API's rate limits aren't documented. Halved
+/// whenever a request was rate limited and raised by one for each link paired without any.
+struct Concurrency {
+    max: usize,
+    current: usize,
+    /// Rate-limited requests so far
+    throttled: u32,
+}
+
+impl Concurrency {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            current: max,
+            throttled: 0,
+        }
+    }
+
+    /// After a link is paired, with the total rate-limited requests so far
+    fn update(&mut self, throttled: u32) {
+        if throttled > self.throttled {
+            self.throttled = throttled;
+            self.current = (self.current / 2).max(1);
+        } else if self.current < self.max {
+            self.current += 1;
+        }
+    }
+}
+
+async fn pair(
+    client: &apple_music::Client,
+    link: &str,
+    options: &matching::MatchOptions,
+) -> Result<Outcome> {
+    let AlbumLink(album_id) = link.parse()?;
+    let library_id = migrate::resolve_library_id(client, &album_id).await?;
+    let source = migrate::load_source(client, &library_id).await?;
+    Ok(
+        match discovery::find_edition(client, &source, options).await? {
+            discovery::Edition::Newer(destination) => Outcome::Paired(BatchPair {
+                source_library_id: library_id,
+                source_name: source.name,
+                destination_catalog_id: destination.catalog_id,
+                destination_name: destination.name,
+                artist_name: source.artist_name,
+            }),
+            discovery::Edition::Current => {
+                Outcome::Skipped("already the most complete edition".to_owned())
+            }
+            discovery::Edition::Incomplete { album, missing } => Outcome::Skipped(format!(
+                "\"{}\" ({}) has more tracks but is missing {}",
+                album.name,
+                album.catalog_id,
+                missing.join(", "),
+            )),
+        },
+    )
+}
+
+/// Pair each library album in a list of links with its most complete catalog edition and write
+/// the pairs to review
+pub async fn build(
+    client: &apple_music::Client,
+    links_path: &Path,
+    output_path: &Path,
+    options: &matching::MatchOptions,
+    max_concurrency: usize,
+) -> Result<()> {
+    let links = fs::read_to_string(links_path)
+        .with_context(|| format!("failed to read {}", links_path.display()))?;
+
+    let links: Vec<&str> = links
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let _deferred = interrupt::defer();
+    let mut concurrency = Concurrency::new(max_concurrency);
+    let mut outcomes: Vec<Option<Result<Outcome>>> = links.iter().map(|_| None).collect();
+    let mut in_flight = FuturesUnordered::new();
+    let mut next = 0;
+    loop {
+        while in_flight.len() < concurrency.current
+            && next < links.len()
+            && !interrupt::interrupted()
+        {
+            let (index, link) = (next, links[next]);
+            in_flight.push(async move {
+                let mut span = telemetry::stage("pair");
+                span.attribute("link", link);
+                (index, span.result(pair(client, link, options).await))
+            });
+            next += 1;
+        }
+        let Some((index, outcome)) = in_flight.next().await else {
+            break;
+        };
+        println!("{}", links[index]);
+        match &outcome {
+            Ok(Outcome::Paired(pair)) => println!(
+                "  \"{}\" {} \"{}\" ({})",
+                pair.source_name,
+                output::arrow(),
+                pair.destination_name,
+                pair.destination_catalog_id,
+            ),
+            Ok(Outcome::Skipped(reason)) => println!("  Skipped: {reason}"),
+            Err(error) => println!("  Failed: {error:#}"),
+        }
+        outcomes[index] = Some(outcome);
+
+        let previous = concurrency.current;
+        concurrency.update(
+            client
+                .rate_limits()
+                .map_or(0, |rate_limits| rate_limits.throttled),
+        );
+        if concurrency.current < previous {
+            println!(
+                "Rate limited, pairing {} links at once",
+                concurrency.current,
+            );
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut skipped = Vec::new();
+    for (link, outcome) in links.into_iter().zip(outcomes) {
+        let reason = match outcome {
+            Some(Ok(Outcome::Paired(pair))) => {
+                pairs.push(pair);
+                continue;
+            }
+            Some(Ok(Outcome::Skipped(reason))) => reason,
+            Some(Err(error)) => format!("{error:#}"),
+            None => "interrupted before pairing".to_owned(),
+        };
+        skipped.push(SkippedLink {
+            link: link.to_owned(),
+            reason,
+        });
+    }
+
+    let batch = Batch {
+        version: BATCH_VERSION,
+        pairs,
+        skipped,
+    };
+    let contents = match output_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("yaml" | "yml") => yaml::to_string(&batch)?,
+        _ => serde_json::to_string_pretty(&batch)?,
+    };
+    fs::write(output_path, contents)
+        .with_context(|| format!("failed to write {}", output_path.display()))?;
+    println!();
+    println!(
+        "Wrote {} pairs to {} ({} links skipped)",
+        batch.pairs.len(),
+        output_path.display(),
+        batch.skipped.len(),
+    );
+    if let Some(rate_limits) = client.rate_limits() {
+        println!("Rate limits: {rate_limits}");
+    }
+    Ok(())
+}
+
+async fn check_pair(client: &apple_music::Client, pair: &UncheckedPair) -> Result<()> {
+    let source_library_id: LibraryAlbumId = pair
+        .source_library_id
+        .parse()
+        .context("invalid source library ID")?;
+    let destination_catalog_id: CatalogAlbumId = pair
+        .destination_catalog_id
+        .parse()
+        .context("invalid destination catalog ID")?;
+    let library_album = client
+        .get_library_album(&source_library_id)
+        .await
+        .map_err(|error| {
+            if ApiError::is_not_found(&error) {
+                anyhow!("source album isn't in the library")
+            } else {
+                error
+            }
+        })?;
+    let source_catalog_id = library_album
+        .catalog_id()
+        .context("source album isn't in the catalog")?;
+    ensure!(
+        source_catalog_id != destination_catalog_id.as_str(),
+        "source and destination are the same album",
+    );
+    client
+        .get_catalog_album(&destination_catalog_id)
+        .await
+        .map_err(|error| {
+            if ApiError::is_not_found(&error) {
+                anyhow!("destination album isn't in the catalog")
+            } else {
+                error
+            }
+        })?;
+    Ok(())
+}
+
+/// Check that every pair in a batch can be migrated as far as can be told without matching any
+/// tracks, failing if any can't
+pub async fn check(client: &apple_music::Client, path: &Path) -> Result<()> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let batch: UncheckedBatch = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml" | "yml") => yaml::from_str(&contents),
+        _ => serde_json::from_str(&contents).map_err(Into::into),
+    }
+    .with_context(|| format!("failed to parse {}", path.display()))?;
+    ensure!(
+        batch.version == BATCH_VERSION,
+        "unsupported batch version {}, expected {BATCH_VERSION}",
+        batch.version,
+    );
+
+    let mut problems = 0;
+    for pair in &batch.pairs {
+        println!(
+            "\"{}\" ({}) {} \"{}\" ({})",
+            pair.source_name,
+            pair.source_library_id,
+            output::arrow(),
+            pair.destination_name,
+            pair.destination_catalog_id,
+        );
+        match check_pair(client, pair).await {
+            Ok(()) => println!("  OK"),
+            Err(error) => {
+                problems += 1;
+                println!("  Problem: {error:#}");
+            }
+        }
+    }
+    println!();
+    ensure!(
+        problems == 0,
+        "{problems} of {} pairs can't be migrated",
+        batch.pairs.len(),
+    );
+    println!("All {} pairs can be migrated", batch.pairs.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrency() {
+        let mut concurrency = Concurrency::new(4);
+        concurrency.update(1);
+        assert_eq!(concurrency.current, 2);
+        concurrency.update(3);
+        assert_eq!(concurrency.current, 1);
+        concurrency.update(4);
+        assert_eq!(concurrency.current, 1);
+        for _ in 0..5 {
+            concurrency.update(4);
+        }
+        assert_eq!(concurrency.current, 4);
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let batch = Batch {
+            version: BATCH_VERSION,
+            pairs: vec![BatchPair {
+                source_library_id: "l.DemoStandard".parse().unwrap(),
+                source_name: "Demo Album".to_owned(),
+                destination_catalog_id: "1000000002".parse().unwrap(),
+                destination_name: "Demo Album (Deluxe Edition)".to_owned(),
+                artist_name: "Demo Artist".to_owned(),
+            }],
+            skipped: vec![SkippedLink {
+                link: "https://music.apple.com/us/artist/demo-artist/1".to_owned(),
+                reason: "not an album link".to_owned(),
+            }],
+        };
+        assert_eq!(
+            yaml::from_str::<Batch>(&yaml::to_string(&batch).unwrap()).unwrap(),
+            batch,
+        );
+    }
+
+    #[test]
+    fn test_parse_album_link() {
+        assert_eq!(
+            "https://music.apple.com/us/album/demo-album/1000000001"
+                .parse::<AlbumLink>()
+                .unwrap(),
+            AlbumLink(AlbumId::Catalog("1000000001".parse().unwrap())),
+        );
+        assert_eq!(
+            "https://music.apple.com/gb/album/1000000001?i=1000000011"
+                .parse::<AlbumLink>()
+                .unwrap(),
+            AlbumLink(AlbumId::Catalog("1000000001".parse().unwrap())),
+        );
+        assert_eq!(
+            "https://music.apple.com/library/albums/l.DemoStandard"
+                .parse::<AlbumLink>()
+                .unwrap(),
+            AlbumLink(AlbumId::Library("l.DemoStandard".parse().unwrap())),
+        );
+        assert!(
+            "https://music.apple.com/us/artist/demo-artist/1"
+                .parse::<AlbumLink>()
+                .is_err()
+        );
+        assert!(
+            "https://example.com/us/album/1"
+                .parse::<AlbumLink>()
+                .is_err()
+        );
+        assert!("1000000001".parse::<AlbumLink>().is_err());
+    }
+}
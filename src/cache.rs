@@ -0,0 +1,47 @@
+//! Showing and clearing the catalog responses kept between runs
+
+use anyhow::Result;
+use chrono::{TimeDelta, Utc};
+
+use crate::{
+    apple_music::cache::{Filter, ResponseCache},
+    output, stats,
+};
+
+fn format_age(received_at: i64, now: i64) -> String {
+    output::format_duration(TimeDelta::seconds(now - received_at))
+}
+
+/// The cache the API commands use
+fn open() -> Result<ResponseCache> {
+    ResponseCache::open(&ResponseCache::default_path()?)
+}
+
+pub fn print_stats() -> Result<()> {
+    let cache = open()?;
+    let stats = cache.stats()?;
+    println!("{}", cache.path().display());
+    println!(
+        "{} responses, {}",
+        stats.entries,
+        stats::format_bytes(stats.bytes),
+    );
+    if let (Some(oldest), Some(newest)) = (stats.oldest, stats.newest) {
+        let now = Utc::now().timestamp();
+        println!(
+            "Oldest received {} ago, newest {} ago",
+            format_age(oldest, now),
+            format_age(newest, now),
+        );
+    }
+    for (storefront, entries) in &stats.storefronts {
+        println!("  {entries:>5}  {storefront}");
+    }
+    Ok(())
+}
+
+pub fn clear(filter: &Filter) -> Result<()> {
+    let removed = open()?.clear(filter)?;
+    println!("Removed {removed} cached responses");
+    Ok(())
+}
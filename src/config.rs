@@ -0,0 +1,182 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::matching;
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Applied in order to titles and artist names before tracks are compared
+    pub rewrite_rules: Vec<RewriteRule>,
+    /// API options selected with `--profile`, by name
+    pub profiles: HashMap<String, Profile>,
+    /// Album IDs or music.apple.com links that can be given by name wherever an album is
+    pub aliases: HashMap<String, String>,
+}
+
+/// Used for any API option that isn't given on the command line
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub developer_token: Option<String>,
+    pub user_token: Option<String>,
+    /// Run to get the user token if `user_token` isn't set
+    pub user_token_command: Option<String>,
+    /// e.g. `amp-api`
+    pub host: Option<String>,
+    pub storefront: Option<String>,
+    /// e.g. `en-US`
+    pub language: Option<String>,
+    pub origin: Option<String>,
+    /// `Name: value`, sent before any given with `--header`
+    pub headers: Vec<String>,
+    /// Seconds
+    pub timeout: Option<u64>,
+    pub retries: Option<u32>,
+    /// Milliseconds
+    pub request_interval: Option<u64>,
+    pub user_agent: Option<String>,
+    pub proxy: Option<String>,
+    /// Seconds
+    pub keep_alive: Option<u64>,
+    /// Seconds
+    pub pool_idle_timeout: Option<u64>,
+    pub max_idle_connections: Option<usize>,
+    /// Megabytes
+    pub max_response_size: Option<usize>,
+    /// Seconds
+    pub cache_ttl: Option<u64>,
+}
+
+/// Either `find` (literal) or `regex` must be given, `replace` can use `$1` style groups with
+/// `regex`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteRule {
+    #[serde(default)]
+    pub field: matching::RewriteField,
+    pub find: Option<String>,
+    pub regex: Option<String>,
+    pub replace: String,
+}
+
+impl RewriteRule {
+    fn compile(&self) -> Result<matching::RewriteRule> {
+        let (pattern, replacement) = match (&self.find, &self.regex) {
+            (Some(find), None) => (regex::escape(find), self.replace.replace('$', "$$")),
+            (None, Some(regex)) => (regex.clone(), self.replace.clone()),
+            _ => bail!("exactly one of `find` and `regex` must be given"),
+        };
+        Ok(matching::RewriteRule {
+            field: self.field,
+            pattern: regex::Regex::new(&pattern)?,
+            replacement,
+        })
+    }
+}
+
+impl Config {
+    pub fn album_alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    pub fn into_profile(mut self, name: &str) -> Result<Profile> {
+        self.profiles
+            .remove(name)
+            .with_context(|| format!("no profile named `{name}` in the config file"))
+    }
+
+    pub fn compile_rewrite_rules(&self) -> Result<Vec<matching::RewriteRule>> {
+        self.rewrite_rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                rule.compile()
+                    .with_context(|| format!("invalid rewrite rule {}", i + 1))
+            })
+            .collect()
+    }
+}
+
+fn path() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("could not determine the config directory")?
+        .join(env!("CARGO_PKG_NAME"))
+        .join("config.json"))
+}
+
+pub fn load() -> Result<Config> {
+    let path = path()?;
+    match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .with_context(|| format!("invalid config file {}", path.display())),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(error) => Err(error).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_rewrite_rules() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "rewrite_rules": [
+                    { "field": "artist", "find": "JAY Z", "replace": "JAY-Z" },
+                    { "field": "title", "regex": " \\(Remastered( \\d{4})?\\)$", "replace": "" },
+                    { "find": "$", "replace": "$1" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let rules = config.compile_rewrite_rules().unwrap();
+        assert_eq!(rules[0].apply("JAY Z & Kanye West"), "JAY-Z & Kanye West");
+        assert_eq!(rules[1].apply("Song (Remastered 2011)"), "Song");
+        assert_eq!(rules[2].apply("$ign"), "$1ign");
+    }
+
+    #[test]
+    fn test_profiles() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "profiles": {
+                    "work": { "developer_token": "a.b.c", "storefront": "gb", "timeout": 30 }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(serde_json::from_str::<Config>(r#"{ "profiles": { "a": { "x": 1 } } }"#).is_err());
+        let profile = config.into_profile("work").unwrap();
+        assert_eq!(profile.storefront.as_deref(), Some("gb"));
+        assert_eq!(profile.timeout, Some(30));
+        assert!(profile.user_token.is_none());
+        assert!(Config::default().into_profile("work").is_err());
+    }
+
+    #[test]
+    fn test_album_alias() {
+        let config: Config =
+            serde_json::from_str(r#"{ "aliases": { "ok-computer-remaster": "1097861387" } }"#)
+                .unwrap();
+        assert_eq!(
+            config.album_alias("ok-computer-remaster"),
+            Some("1097861387")
+        );
+        assert_eq!(config.album_alias("1097861387"), None);
+    }
+
+    #[test]
+    fn test_compile_rewrite_rules_invalid() {
+        let config: Config =
+            serde_json::from_str(r#"{ "rewrite_rules": [{ "replace": "" }] }"#).unwrap();
+        assert!(config.compile_rewrite_rules().is_err());
+        let config: Config =
+            serde_json::from_str(r#"{ "rewrite_rules": [{ "regex": "(", "replace": "" }] }"#)
+                .unwrap();
+        assert!(config.compile_rewrite_rules().is_err());
+    }
+}
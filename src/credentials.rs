@@ -0,0 +1,121 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result, ensure};
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Credentials {
+    /// Apple Music User Token
+    pub user_token: Option<String>,
+}
+
+fn path() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("could not determine the config directory")?
+        .join(env!("CARGO_PKG_NAME"))
+        .join("credentials.json"))
+}
+
+pub fn load() -> Result<Credentials> {
+    let path = path()?;
+    match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .with_context(|| format!("invalid credentials file {}", path.display())),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Credentials::default()),
+        Err(error) => Err(error).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+pub fn save(credentials: &Credentials) -> Result<PathBuf> {
+    let path = path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(&serde_json::to_vec_pretty(credentials)?))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Read a secret from a file, or stdin for `-`, with surrounding whitespace removed
+pub fn from_file(path: &Path) -> Result<String> {
+    let contents = if path == Path::new("-") {
+        let mut contents = String::new();
+        std::io::stdin()
+            .read_to_string(&mut contents)
+            .context("failed to read stdin")?;
+        contents
+    } else {
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?
+    };
+    let secret = contents.trim();
+    ensure!(!secret.is_empty(), "{} is empty", path.display());
+    Ok(secret.to_owned())
+}
+
+/// Run a command through the shell and use its output as a secret, e.g. `op read ...` for a
+/// password manager, with surrounding whitespace removed
+pub fn from_command(command: &str) -> Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    // stderr is inherited so that prompts and errors from the command are shown
+    let output = shell
+        .arg(command)
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .with_context(|| format!("failed to run `{command}`"))?;
+    ensure!(
+        output.status.success(),
+        "`{command}` failed ({})",
+        output.status,
+    );
+    let secret = String::from_utf8(output.stdout)
+        .with_context(|| format!("`{command}` printed invalid UTF-8"))?;
+    let secret = secret.trim();
+    ensure!(!secret.is_empty(), "`{command}` printed nothing");
+    Ok(secret.to_owned())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_file() {
+        let path = std::env::temp_dir().join(format!("token-{}", std::process::id()));
+        fs::write(&path, "token\n").unwrap();
+        assert_eq!(from_file(&path).unwrap(), "token");
+        fs::write(&path, "\n").unwrap();
+        assert!(from_file(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_from_command() {
+        assert_eq!(from_command("printf ' token\\n'").unwrap(), "token");
+        assert!(from_command("exit 1").is_err());
+        assert!(from_command("true").is_err());
+    }
+}
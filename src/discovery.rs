@@ -0,0 +1,173 @@
+use anyhow::Result;
+use futures_util::{StreamExt, TryStreamExt};
+
+use crate::{
+    apple_music::{
+        self, CatalogAlbumId,
+        custom_types::{Album, TrackNoLibrary, TrackWithLibrary},
+    },
+    matching, migrate,
+};
+
+/// Other editions are almost always among the first results
+const MAX_SEARCH_RESULTS: usize = 50;
+
+pub enum Edition {
+    /// The source album is a reissue of, or has fewer tracks than, the returned album, which has
+    /// all of the source tracks that are in the library
+    Newer(Album<TrackNoLibrary>),
+    /// No other album has more tracks and all of the source tracks that are in the library
+    Current,
+    /// Albums with more tracks were found but are missing some of the source tracks that are in
+    /// the library, these are from the best of them
+    Incomplete {
+        album: Album<TrackNoLibrary>,
+        missing: Vec<String>,
+    },
+}
+
+/// Edition suffixes like ` (Deluxe Edition)`, ` [Remastered]` and ` - EP` would narrow the
+/// search to the edition that's already known
+pub fn base_title(name: &str) -> &str {
+    [" (", " [", " - "]
+        .iter()
+        .filter_map(|separator| name.find(separator))
+        .min()
+        .map_or(name, |end| &name[..end])
+}
+
+/// How strongly an album that has all of the library's tracks is preferred as the destination, an
+/// album without a release date yet loses to an otherwise equal one that has been released
+fn rank(
+    source: &Album<TrackWithLibrary>,
+    candidate: &Album<TrackNoLibrary>,
+    options: &matching::MatchOptions,
+) -> (bool, bool, usize, Option<String>) {
+    (
+        options.prefer_apple_digital_master && candidate.apple_digital_master,
+        matching::compare_upcs(source, candidate) == matching::UpcComparison::Same,
+        candidate.tracks.len(),
+        candidate.release_date.clone(),
+    )
+}
+
+/// Other editions of an album, from the other versions Apple lists for it or, if there are none,
+/// from searching the catalog for albums by the same artist with the same base title
+pub async fn other_editions(
+    client: &apple_music::Client,
+    catalog_id: &CatalogAlbumId,
+    artist_name: &str,
+    name: &str,
+) -> Result<Vec<CatalogAlbumId>> {
+    let other_versions: Vec<CatalogAlbumId> = client
+        .get_other_versions(catalog_id)
+        .await?
+        .albums()
+        .iter()
+        .map(|album| album.id().parse())
+        .collect::<Result<_>>()?;
+    if !other_versions.is_empty() {
+        return Ok(other_versions);
+    }
+    search_editions(client, artist_name, name).await
+}
+
+/// Albums by the artist found by searching for the title without its edition suffix, most
+/// relevant first
+pub async fn search_editions(
+    client: &apple_music::Client,
+    artist_name: &str,
+    name: &str,
+) -> Result<Vec<CatalogAlbumId>> {
+    client
+        .search_albums(&format!("{artist_name} {}", base_title(name)))
+        .take(MAX_SEARCH_RESULTS)
+        .try_filter(|album| {
+            std::future::ready(album.artist_name().eq_ignore_ascii_case(artist_name))
+        })
+        .and_then(|album| std::future::ready(album.id().parse()))
+        .try_collect()
+        .await
+}
+
+/// Find the other editions of the source album and pick the one to migrate to
+pub async fn find_edition(
+    client: &apple_music::Client,
+    source: &Album<TrackWithLibrary>,
+    options: &matching::MatchOptions,
+) -> Result<Edition> {
+    let candidate_ids = other_editions(
+        client,
+        &source.catalog_id,
+        &source.artist_name,
+        &source.name,
+    )
+    .await?;
+
+    let mut best: Option<Album<TrackNoLibrary>> = None;
+    let mut best_incomplete: Option<(Album<TrackNoLibrary>, Vec<String>)> = None;
+    for catalog_id in &candidate_ids {
+        if *catalog_id == source.catalog_id {
+            continue;
+        }
+        let candidate = match migrate::load_destination(client, catalog_id).await {
+            Ok(candidate) => candidate,
+            Err(error) => {
+                eprintln!("Skipping candidate {catalog_id}: {error:#}");
+                continue;
+            }
+        };
+        if matching::looks_unrelated(source, &candidate) {
+            continue;
+        }
+        // Ambiguous matches make a candidate unusable without manual mappings
+        let Ok(report) = matching::match_tracks(source, &candidate, options) else {
+            continue;
+        };
+        let missing: Vec<String> = report
+            .unmatched_in_library()
+            .iter()
+            .map(|track| track.name.clone())
+            .collect();
+        drop(report);
+
+        let is_newer = candidate.tracks.len() > source.tracks.len()
+            || matching::compare_upcs(source, &candidate) == matching::UpcComparison::Same;
+        if !is_newer {
+            continue;
+        }
+        if !missing.is_empty() {
+            if best_incomplete.as_ref().is_none_or(|(album, _)| {
+                rank(source, &candidate, options) > rank(source, album, options)
+            }) {
+                best_incomplete = Some((candidate, missing));
+            }
+            continue;
+        }
+        if best
+            .as_ref()
+            .is_none_or(|album| rank(source, &candidate, options) > rank(source, album, options))
+        {
+            best = Some(candidate);
+        }
+    }
+
+    Ok(match (best, best_incomplete) {
+        (Some(album), _) => Edition::Newer(album),
+        (None, Some((album, missing))) => Edition::Incomplete { album, missing },
+        (None, None) => Edition::Current,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_title() {
+        assert_eq!(base_title("Album (Deluxe Edition)"), "Album");
+        assert_eq!(base_title("Album - EP"), "Album");
+        assert_eq!(base_title("Album [Remastered] (Live)"), "Album");
+        assert_eq!(base_title("Album"), "Album");
+    }
+}
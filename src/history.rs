@@ -0,0 +1,587 @@
+//! Completed migrations, kept in a local SQLite database
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::{
+    apple_music::{
+        CatalogAlbumId,
+        custom_types::{Album, TrackNoLibrary, TrackWithLibrary},
+    },
+    matching, output,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS migrations (
+    id INTEGER PRIMARY KEY,
+    migrated_at TEXT NOT NULL,
+    source_library_id TEXT NOT NULL,
+    source_catalog_id TEXT NOT NULL,
+    source_name TEXT NOT NULL,
+    -- Comma separated, several for a box set split into several albums
+    destination_catalog_ids TEXT NOT NULL,
+    destination_name TEXT NOT NULL,
+    artist_name TEXT NOT NULL,
+    source_track_count INTEGER NOT NULL,
+    destination_track_count INTEGER NOT NULL,
+    added_track_count INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS track_mappings (
+    migration_id INTEGER NOT NULL REFERENCES migrations (id),
+    source_catalog_id TEXT NOT NULL,
+    source_name TEXT NOT NULL,
+    destination_catalog_id TEXT,
+    destination_name TEXT,
+    method TEXT,
+    source_date_added TEXT
+);
+CREATE TABLE IF NOT EXISTS pending_migrations (
+    source_library_id TEXT NOT NULL,
+    source_catalog_id TEXT NOT NULL,
+    -- Comma separated like in migrations
+    destination_catalog_ids TEXT NOT NULL,
+    destination_name TEXT NOT NULL,
+    songs_to_add TEXT NOT NULL,
+    whole_album INTEGER NOT NULL,
+    PRIMARY KEY (source_catalog_id, destination_catalog_ids)
+);
+";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Migration {
+    /// Assigned when recorded
+    pub id: i64,
+    /// RFC 3339
+    pub migrated_at: String,
+    pub source_library_id: String,
+    pub source_catalog_id: String,
+    pub source_name: String,
+    /// Several if the destination was split into several albums
+    pub destination_catalog_ids: Vec<String>,
+    pub destination_name: String,
+    pub artist_name: String,
+    pub source_track_count: usize,
+    pub destination_track_count: usize,
+    /// Destination songs that weren't already in the library
+    pub added_track_count: usize,
+}
+
+/// What a source track in the library was migrated to
+#[derive(Debug, PartialEq, Eq)]
+pub struct TrackMapping {
+    pub source_catalog_id: String,
+    pub source_name: String,
+    /// `(catalog_id, name)`, `None` if the track had no match and was dropped
+    pub destination: Option<(String, String)>,
+    pub method: Option<String>,
+    /// When the source track was added to the library, which the migration resets
+    pub source_date_added: Option<String>,
+}
+
+/// A migration that has started changing the library, kept until it finishes so that running it
+/// again can finish it
+#[derive(Debug, PartialEq, Eq)]
+pub struct Pending {
+    pub source_library_id: String,
+    pub source_catalog_id: String,
+    /// Several if the destination was split into several albums
+    pub destination_catalog_ids: Vec<String>,
+    pub destination_name: String,
+    /// Destination songs that weren't already in the library
+    pub songs_to_add: Vec<String>,
+    /// Whether the destination can be added as an album rather than song by song
+    pub whole_album: bool,
+}
+
+impl Migration {
+    pub fn new(
+        source_library_id: &str,
+        source: &Album<TrackWithLibrary>,
+        destination: &Album<TrackNoLibrary>,
+        destination_catalog_ids: &[CatalogAlbumId],
+        added_track_count: usize,
+    ) -> Self {
+        Self {
+            id: 0,
+            migrated_at: chrono::Utc::now().to_rfc3339(),
+            source_library_id: source_library_id.to_owned(),
+            source_catalog_id: source.catalog_id.to_string(),
+            source_name: source.name.clone(),
+            destination_catalog_ids: destination_catalog_ids
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            destination_name: destination.name.clone(),
+            artist_name: source.artist_name.clone(),
+            source_track_count: source.tracks.len(),
+            destination_track_count: destination.tracks.len(),
+            added_track_count,
+        }
+    }
+}
+
+/// Only the source tracks that are in the library
+pub fn track_mappings(report: &matching::MatchReport) -> Vec<TrackMapping> {
+    report
+        .results
+        .iter()
+        .filter_map(|result| match result {
+            matching::TrackMatchResult::Match {
+                source,
+                destination,
+                method,
+                ..
+            } => source.library_id.is_some().then(|| TrackMapping {
+                source_catalog_id: source.catalog_id.to_string(),
+                source_name: source.name.clone(),
+                destination: Some((destination.catalog_id.to_string(), destination.name.clone())),
+                method: Some(method.to_string()),
+                source_date_added: source.date_added.clone(),
+            }),
+            matching::TrackMatchResult::NoMatch { source } => {
+                source.library_id.is_some().then(|| TrackMapping {
+                    source_catalog_id: source.catalog_id.to_string(),
+                    source_name: source.name.clone(),
+                    destination: None,
+                    method: None,
+                    source_date_added: source.date_added.clone(),
+                })
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct Filter {
+    /// Part of the artist name, ignoring case
+    pub artist_name: Option<String>,
+    /// Part of the source or destination album name, ignoring case
+    pub album_name: Option<String>,
+    pub limit: Option<usize>,
+}
+
+pub struct History {
+    connection: Connection,
+}
+
+fn path() -> Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .context("could not determine the data directory")?
+        .join(env!("CARGO_PKG_NAME"))
+        .join("history.sqlite3"))
+}
+
+/// IDs are stored comma separated
+fn split_ids(ids: &str) -> Vec<String> {
+    ids.split(',')
+        .filter(|id| !id.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// A `LIKE` pattern matching values containing `value`
+fn contains_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+fn migration_from_row(row: &rusqlite::Row) -> rusqlite::Result<Migration> {
+    let destination_catalog_ids: String = row.get(5)?;
+    Ok(Migration {
+        id: row.get(0)?,
+        migrated_at: row.get(1)?,
+        source_library_id: row.get(2)?,
+        source_catalog_id: row.get(3)?,
+        source_name: row.get(4)?,
+        destination_catalog_ids: split_ids(&destination_catalog_ids),
+        destination_name: row.get(6)?,
+        artist_name: row.get(7)?,
+        source_track_count: row.get(8)?,
+        destination_track_count: row.get(9)?,
+        added_track_count: row.get(10)?,
+    })
+}
+
+const MIGRATION_COLUMNS: &str = "id, migrated_at, source_library_id, source_catalog_id, \
+    source_name, destination_catalog_ids, destination_name, artist_name, source_track_count, \
+    destination_track_count, added_track_count";
+
+impl History {
+    /// The database in the data directory, created if it doesn't exist
+    pub fn open() -> Result<Self> {
+        let path = path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let connection = Connection::open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        Self::with_connection(connection)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection
+            .execute_batch(SCHEMA)
+            .context("failed to create the history tables")?;
+        Ok(Self { connection })
+    }
+
+    /// Returns the ID of the recorded migration
+    pub fn record(&mut self, migration: &Migration, tracks: &[TrackMapping]) -> Result<i64> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO migrations (migrated_at, source_library_id, source_catalog_id, \
+             source_name, destination_catalog_ids, destination_name, artist_name, \
+             source_track_count, destination_track_count, added_track_count) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                migration.migrated_at,
+                migration.source_library_id,
+                migration.source_catalog_id,
+                migration.source_name,
+                migration.destination_catalog_ids.join(","),
+                migration.destination_name,
+                migration.artist_name,
+                migration.source_track_count,
+                migration.destination_track_count,
+                migration.added_track_count,
+            ],
+        )?;
+        let id = transaction.last_insert_rowid();
+        for track in tracks {
+            let (destination_catalog_id, destination_name) = track
+                .destination
+                .as_ref()
+                .map(|(catalog_id, name)| (catalog_id.as_str(), name.as_str()))
+                .unzip();
+            transaction.execute(
+                "INSERT INTO track_mappings (migration_id, source_catalog_id, source_name, \
+                 destination_catalog_id, destination_name, method, source_date_added) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    id,
+                    track.source_catalog_id,
+                    track.source_name,
+                    destination_catalog_id,
+                    destination_name,
+                    track.method,
+                    track.source_date_added,
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(id)
+    }
+
+    /// Record a migration as started, before the source album is removed
+    pub fn begin(&mut self, pending: &Pending) -> Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO pending_migrations (source_library_id, source_catalog_id, \
+             destination_catalog_ids, destination_name, songs_to_add, whole_album) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                pending.source_library_id,
+                pending.source_catalog_id,
+                pending.destination_catalog_ids.join(","),
+                pending.destination_name,
+                pending.songs_to_add.join(","),
+                pending.whole_album,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The started migration from a source album, by library or catalog ID, to the same
+    /// destination albums in the same order
+    pub fn pending(
+        &self,
+        source_id: &str,
+        destination_catalog_ids: &[String],
+    ) -> Result<Option<Pending>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT source_library_id, source_catalog_id, destination_catalog_ids, \
+                 destination_name, songs_to_add, whole_album FROM pending_migrations \
+                 WHERE (source_library_id = ?1 OR source_catalog_id = ?1) \
+                 AND destination_catalog_ids = ?2",
+                [source_id, &destination_catalog_ids.join(",")],
+                |row| {
+                    let destination_catalog_ids: String = row.get(2)?;
+                    let songs_to_add: String = row.get(4)?;
+                    Ok(Pending {
+                        source_library_id: row.get(0)?,
+                        source_catalog_id: row.get(1)?,
+                        destination_catalog_ids: split_ids(&destination_catalog_ids),
+                        destination_name: row.get(3)?,
+                        songs_to_add: split_ids(&songs_to_add),
+                        whole_album: row.get(5)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Forget a started migration once it has finished, or if it turns out it didn't change the
+    /// library
+    pub fn finish(&mut self, pending: &Pending) -> Result<()> {
+        self.connection.execute(
+            "DELETE FROM pending_migrations WHERE source_catalog_id = ?1 \
+             AND destination_catalog_ids = ?2",
+            [
+                &pending.source_catalog_id,
+                &pending.destination_catalog_ids.join(","),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// What each recorded source track was last migrated to, by source catalog ID, `None` if it
+    /// had no match
+    pub fn migrated_tracks(&self) -> Result<HashMap<String, Option<(String, String)>>> {
+        let mut statement = self.connection.prepare(
+            "SELECT source_catalog_id, destination_catalog_id, destination_name \
+             FROM track_mappings ORDER BY rowid",
+        )?;
+        let tracks = statement
+            .query_map([], |row| {
+                let destination_catalog_id: Option<String> = row.get(1)?;
+                let destination_name: Option<String> = row.get(2)?;
+                Ok((row.get(0)?, destination_catalog_id.zip(destination_name)))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tracks)
+    }
+
+    /// Most recent first
+    pub fn list(&self, filter: &Filter) -> Result<Vec<Migration>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {MIGRATION_COLUMNS} FROM migrations \
+             WHERE (?1 IS NULL OR artist_name LIKE ?1 ESCAPE '\\') \
+             AND (?2 IS NULL OR source_name LIKE ?2 ESCAPE '\\' \
+                  OR destination_name LIKE ?2 ESCAPE '\\') \
+             ORDER BY id DESC LIMIT ?3",
+        ))?;
+        let migrations = statement
+            .query_map(
+                params![
+                    filter.artist_name.as_deref().map(contains_pattern),
+                    filter.album_name.as_deref().map(contains_pattern),
+                    filter.limit.map_or(-1, |limit| limit as i64),
+                ],
+                migration_from_row,
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(migrations)
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<(Migration, Vec<TrackMapping>)>> {
+        let Some(migration) = self
+            .connection
+            .query_row(
+                &format!("SELECT {MIGRATION_COLUMNS} FROM migrations WHERE id = ?1"),
+                [id],
+                migration_from_row,
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let mut statement = self.connection.prepare(
+            "SELECT source_catalog_id, source_name, destination_catalog_id, destination_name, \
+             method, source_date_added FROM track_mappings WHERE migration_id = ?1 \
+             ORDER BY rowid",
+        )?;
+        let tracks = statement
+            .query_map([id], |row| {
+                let destination_catalog_id: Option<String> = row.get(2)?;
+                let destination_name: Option<String> = row.get(3)?;
+                Ok(TrackMapping {
+                    source_catalog_id: row.get(0)?,
+                    source_name: row.get(1)?,
+                    destination: destination_catalog_id.zip(destination_name),
+                    method: row.get(4)?,
+                    source_date_added: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(Some((migration, tracks)))
+    }
+}
+
+pub fn print_list(migrations: &[Migration]) {
+    if migrations.is_empty() {
+        println!("No migrations recorded");
+        return;
+    }
+    let width = output::terminal_width();
+    let arrow = output::arrow();
+    for migration in migrations {
+        let other = format!(
+            "#{} {} \"\" {arrow} \"\" by  ({} songs added)",
+            migration.id, migration.migrated_at, migration.added_track_count,
+        );
+        let [source_name, destination_name, artist_name] = output::fit_names(
+            [
+                &migration.source_name,
+                &migration.destination_name,
+                &migration.artist_name,
+            ],
+            other.chars().count(),
+            width,
+        );
+        println!(
+            "#{} {} \"{source_name}\" {arrow} \"{destination_name}\" by {artist_name} ({} songs added)",
+            migration.id, migration.migrated_at, migration.added_track_count,
+        );
+    }
+}
+
+pub fn print_details(migration: &Migration, tracks: &[TrackMapping]) {
+    println!("Migration #{} at {}", migration.id, migration.migrated_at);
+    println!(
+        "Source: \"{}\" ({}, library ID {}, {} tracks)",
+        migration.source_name,
+        migration.source_catalog_id,
+        migration.source_library_id,
+        migration.source_track_count,
+    );
+    println!(
+        "Destination: \"{}\" ({}, {} tracks, {} added)",
+        migration.destination_name,
+        migration.destination_catalog_ids.join(", "),
+        migration.destination_track_count,
+        migration.added_track_count,
+    );
+    println!("Artist: {}", migration.artist_name);
+    println!();
+    println!("Tracks:");
+    let arrow = output::arrow();
+    for track in tracks {
+        let added = track
+            .source_date_added
+            .as_deref()
+            .map(|date_added| format!(", originally added {}", output::format_date(date_added)))
+            .unwrap_or_default();
+        match (&track.destination, &track.method) {
+            (Some((catalog_id, name)), method) => println!(
+                "  {} {arrow} {name} ({catalog_id}, {}{added})",
+                track.source_name,
+                method.as_deref().unwrap_or("unknown method"),
+            ),
+            (None, _) => println!("  {} {arrow} no match, dropped{added}", track.source_name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_list_get() {
+        let mut history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        let migration = |source_name: &str| Migration {
+            id: 0,
+            migrated_at: "2020-01-01T00:00:00+00:00".to_owned(),
+            source_library_id: "l.A".to_owned(),
+            source_catalog_id: "1".to_owned(),
+            source_name: source_name.to_owned(),
+            destination_catalog_ids: vec!["2".to_owned(), "3".to_owned()],
+            destination_name: "Album (Deluxe)".to_owned(),
+            artist_name: "Artist".to_owned(),
+            source_track_count: 2,
+            destination_track_count: 3,
+            added_track_count: 1,
+        };
+        let tracks = vec![
+            TrackMapping {
+                source_catalog_id: "10".to_owned(),
+                source_name: "Song".to_owned(),
+                destination: Some(("20".to_owned(), "Song".to_owned())),
+                method: Some("ISRC".to_owned()),
+                source_date_added: Some("2020-02-03T10:20:30Z".to_owned()),
+            },
+            TrackMapping {
+                source_catalog_id: "11".to_owned(),
+                source_name: "Interlude".to_owned(),
+                destination: None,
+                method: None,
+                source_date_added: None,
+            },
+        ];
+        let first = history.record(&migration("Album"), &tracks).unwrap();
+        let second = history.record(&migration("100% Album"), &[]).unwrap();
+
+        let ids = |filter: &Filter| -> Vec<i64> {
+            history
+                .list(filter)
+                .unwrap()
+                .iter()
+                .map(|migration| migration.id)
+                .collect()
+        };
+        assert_eq!(ids(&Filter::default()), [second, first]);
+        assert_eq!(
+            ids(&Filter {
+                album_name: Some("100%".to_owned()),
+                ..Filter::default()
+            }),
+            [second],
+        );
+        assert_eq!(
+            ids(&Filter {
+                artist_name: Some("artist".to_owned()),
+                limit: Some(1),
+                ..Filter::default()
+            }),
+            [second],
+        );
+
+        let (recorded, recorded_tracks) = history.get(first).unwrap().unwrap();
+        assert_eq!(
+            recorded,
+            Migration {
+                id: first,
+                ..migration("Album")
+            },
+        );
+        assert_eq!(recorded_tracks, tracks);
+        assert!(history.get(second + 1).unwrap().is_none());
+
+        let migrated = history.migrated_tracks().unwrap();
+        assert_eq!(migrated["10"], Some(("20".to_owned(), "Song".to_owned())),);
+        assert_eq!(migrated["11"], None);
+    }
+
+    #[test]
+    fn test_pending() {
+        let mut history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        let pending = Pending {
+            source_library_id: "l.A".to_owned(),
+            source_catalog_id: "1".to_owned(),
+            destination_catalog_ids: vec!["2".to_owned(), "3".to_owned()],
+            destination_name: "Album (Deluxe)".to_owned(),
+            songs_to_add: vec!["20".to_owned(), "21".to_owned()],
+            whole_album: false,
+        };
+        history.begin(&pending).unwrap();
+        let ids = |ids: &[&str]| ids.iter().map(|id| (*id).to_owned()).collect::<Vec<_>>();
+        assert_eq!(
+            history.pending("l.A", &ids(&["2", "3"])).unwrap().as_ref(),
+            Some(&pending)
+        );
+        assert_eq!(
+            history.pending("1", &ids(&["2", "3"])).unwrap().as_ref(),
+            Some(&pending)
+        );
+        assert_eq!(history.pending("1", &ids(&["2"])).unwrap(), None);
+        history.finish(&pending).unwrap();
+        assert_eq!(history.pending("1", &ids(&["2", "3"])).unwrap(), None);
+    }
+}
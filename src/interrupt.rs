@@ -0,0 +1,43 @@
+//! Ctrl-C handling that doesn't leave a migration half done
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static DEFERRED: AtomicUsize = AtomicUsize::new(0);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Exit code of a process killed by SIGINT
+const EXIT_CODE: i32 = 130;
+
+/// Handle Ctrl-C by quitting immediately, unless a [`Deferred`] guard is held, in which case it is
+/// only recorded for the holder to stop at a safe point. A second Ctrl-C always quits.
+pub fn install() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if DEFERRED.load(Ordering::SeqCst) == 0 || INTERRUPTED.swap(true, Ordering::SeqCst) {
+                std::process::exit(EXIT_CODE);
+            }
+            eprintln!(
+                "Interrupted, stopping after the current change (press Ctrl-C again to quit now)"
+            );
+        }
+    });
+}
+
+/// While held, Ctrl-C doesn't quit until the holder checks [`interrupted`]
+pub struct Deferred(());
+
+impl Drop for Deferred {
+    fn drop(&mut self) {
+        DEFERRED.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn defer() -> Deferred {
+    DEFERRED.fetch_add(1, Ordering::SeqCst);
+    Deferred(())
+}
+
+/// Whether Ctrl-C was pressed while a [`Deferred`] guard was held
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
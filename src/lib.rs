@@ -0,0 +1,16 @@
+//! The Apple Music API client and track matching behind the migrator, for tools that want to read
+//! a library or add to it without going through the CLI. The synchronous client in
+//! `apple_music::blocking` needs the `blocking` feature.
+
+pub mod apple_music;
+pub mod matching;
+mod transliterate;
+
+// Used by the client and shared with the CLI so they record to and print the same state, not
+// meant to be used otherwise
+#[doc(hidden)]
+pub mod output;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod telemetry;
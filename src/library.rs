@@ -0,0 +1,551 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result, bail, ensure};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    apple_music::{self, CatalogAlbumId, CatalogSongId, LibraryAlbumId, custom_types},
+    interrupt,
+};
+
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Maximum number of IDs sent in a single catalog lookup or library add request
+pub const IDS_PER_REQUEST: usize = 100;
+
+const ISRCS_PER_REQUEST: usize = 25;
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// The catalog storefront the snapshot was exported from
+    pub storefront: String,
+    pub albums: Vec<SnapshotAlbum>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotAlbum {
+    pub catalog_id: CatalogAlbumId,
+    pub name: String,
+    /// All of the album's artists
+    pub artist_name: String,
+    /// When the album was added to the library, ISO 8601, kept for the record since migrating or
+    /// importing resets it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_added: Option<String>,
+    /// Only the songs that were added to the library
+    pub songs: Vec<SnapshotSong>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotSong {
+    pub catalog_id: CatalogSongId,
+    pub name: String,
+    /// ISO 8601
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_added: Option<String>,
+}
+
+/// Writes a [`Snapshot`] one album at a time so the whole library never has to be in memory, with
+/// an album per line
+struct SnapshotWriter<W: Write> {
+    writer: W,
+    albums: usize,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    fn new(mut writer: W, storefront: &str) -> Result<Self> {
+        write!(
+            writer,
+            "{{\"version\":{SNAPSHOT_VERSION},\"storefront\":{},\"albums\":[",
+            serde_json::to_string(storefront)?,
+        )?;
+        Ok(Self { writer, albums: 0 })
+    }
+
+    fn push(&mut self, album: &SnapshotAlbum) -> Result<()> {
+        if self.albums > 0 {
+            self.writer.write_all(b",")?;
+        }
+        self.writer.write_all(b"\n")?;
+        serde_json::to_writer(&mut self.writer, album)?;
+        self.albums += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<W> {
+        self.writer.write_all(b"\n]}\n")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Resolution<'a> {
+    /// Deduplicated catalog IDs of the songs that can be added
+    resolved: Vec<&'a CatalogSongId>,
+    unresolved: Vec<(&'a SnapshotAlbum, &'a SnapshotSong)>,
+}
+
+fn resolve<'a>(snapshot: &'a Snapshot, available: &HashSet<CatalogSongId>) -> Resolution<'a> {
+    let mut seen = HashSet::new();
+    let mut resolved = Vec::new();
+    let mut unresolved = Vec::new();
+    for album in &snapshot.albums {
+        for song in &album.songs {
+            if !available.contains(&song.catalog_id) {
+                unresolved.push((album, song));
+            } else if seen.insert(&song.catalog_id) {
+                resolved.push(&song.catalog_id);
+            }
+        }
+    }
+    Resolution {
+        resolved,
+        unresolved,
+    }
+}
+
+pub async fn export(client: &apple_music::Client, path: &Path) -> Result<()> {
+    let library_ids: Vec<LibraryAlbumId> = client
+        .library_albums()
+        .map_ok(|album| album.library_id)
+        .try_collect()
+        .await?;
+
+    let file =
+        fs::File::create(path).with_context(|| format!("failed to write {}", path.display()))?;
+    let mut writer = SnapshotWriter::new(BufWriter::new(file), client.storefront())?;
+    let mut song_count = 0;
+    for library_id in &library_ids {
+        let album: custom_types::LibraryAlbum = match client
+            .get_library_album(library_id)
+            .await
+            .and_then(TryInto::try_into)
+        {
+            Ok(album) => album,
+            Err(error) => {
+                eprintln!("Skipping {library_id}: {error:#}");
+                continue;
+            }
+        };
+        let Some(catalog_id) = album.catalog_id else {
+            eprintln!(
+                "Skipping \"{}\" by {}: not available in the catalog",
+                album.name, album.artist_name,
+            );
+            continue;
+        };
+        song_count += album.tracks.len();
+        writer
+            .push(&SnapshotAlbum {
+                catalog_id,
+                name: album.name,
+                artist_name: album.artist_name,
+                date_added: album.date_added,
+                songs: album
+                    .tracks
+                    .into_iter()
+                    .map(|track| SnapshotSong {
+                        catalog_id: track.catalog_id,
+                        name: track.name,
+                        date_added: track.date_added,
+                    })
+                    .collect(),
+            })
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    let album_count = writer.albums;
+    writer
+        .finish()
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    println!(
+        "Exported {album_count} albums ({song_count} songs) to {}",
+        path.display(),
+    );
+    Ok(())
+}
+
+pub async fn import(client: &apple_music::Client, path: &Path, dry_run: bool) -> Result<()> {
+    let snapshot: Snapshot = serde_json::from_slice(
+        &fs::read(path).with_context(|| format!("failed to read {}", path.display()))?,
+    )
+    .context("invalid snapshot")?;
+    ensure!(
+        snapshot.version == SNAPSHOT_VERSION,
+        "unsupported snapshot version: {}",
+        snapshot.version,
+    );
+
+    if snapshot.storefront != client.storefront() {
+        println!(
+            "Note: snapshot was exported from the `{}` storefront, resolving in `{}`",
+            snapshot.storefront,
+            client.storefront(),
+        );
+    }
+
+    let catalog_ids: Vec<&CatalogSongId> = snapshot
+        .albums
+        .iter()
+        .flat_map(|album| album.songs.iter().map(|song| &song.catalog_id))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let mut available = HashSet::new();
+    for chunk in catalog_ids.chunks(IDS_PER_REQUEST) {
+        available.extend(
+            client
+                .get_catalog_songs(chunk)
+                .await?
+                .ids()
+                .map(str::parse)
+                .collect::<Result<Vec<_>>>()?,
+        );
+    }
+
+    let resolution = resolve(&snapshot, &available);
+
+    if !resolution.unresolved.is_empty() {
+        println!("Can't be resolved anymore:");
+        for (album, song) in &resolution.unresolved {
+            println!(
+                "  {} (from \"{}\" by {})",
+                song.name, album.name, album.artist_name,
+            );
+        }
+        println!();
+    }
+
+    if dry_run {
+        println!(
+            "{} songs would be added to the library",
+            resolution.resolved.len(),
+        );
+        return Ok(());
+    }
+
+    add_songs(client, &resolution.resolved, resolution.unresolved.len()).await
+}
+
+/// Add the resolved songs of an import, a request at a time so that an interrupt stops between
+/// them
+async fn add_songs(
+    client: &apple_music::Client,
+    songs: &[&CatalogSongId],
+    unresolved: usize,
+) -> Result<()> {
+    ensure!(!songs.is_empty(), "no songs to import");
+
+    let _deferred = interrupt::defer();
+    let mut added = 0;
+    for chunk in songs.chunks(IDS_PER_REQUEST) {
+        if interrupt::interrupted() {
+            println!(
+                "Interrupted after adding {added} of {} songs, importing again adds the rest",
+                songs.len(),
+            );
+            return Ok(());
+        }
+        client.add_songs_to_library(chunk).await?;
+        added += chunk.len();
+    }
+
+    println!(
+        "Added {} songs to the library ({unresolved} unresolved)",
+        songs.len(),
+    );
+    Ok(())
+}
+
+/// The fields of a CSV line, with double-quoted fields unquoted
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Uppercase without hyphens if it's a valid ISRC, e.g. `US-DEM-20-00001` as `USDEM2000001`
+fn normalize_isrc(isrc: &str) -> Option<String> {
+    let isrc: String = isrc
+        .trim()
+        .chars()
+        .filter(|&c| c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    // By byte, so that other characters are rejected rather than splitting them
+    let bytes = isrc.as_bytes();
+    (bytes.len() == 12
+        && bytes[..2].iter().all(u8::is_ascii_alphabetic)
+        && bytes[2..5].iter().all(u8::is_ascii_alphanumeric)
+        && bytes[5..].iter().all(u8::is_ascii_digit))
+    .then_some(isrc)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct IsrcList {
+    /// Deduplicated and normalized
+    isrcs: Vec<String>,
+    /// Values in the ISRC column that aren't ISRCs, with their line numbers
+    invalid: Vec<(usize, String)>,
+}
+
+/// The ISRCs in the column with an `ISRC` header, or in the first column if there's no header
+fn parse_isrc_csv(csv: &str) -> Result<IsrcList> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .peekable();
+    let Some((_, header)) = lines.peek() else {
+        bail!("the CSV is empty");
+    };
+    let header = csv_fields(header.trim_start_matches('\u{feff}'));
+    let column = match header
+        .iter()
+        .position(|field| field.trim().eq_ignore_ascii_case("isrc"))
+    {
+        Some(column) => {
+            lines.next();
+            column
+        }
+        None => {
+            ensure!(
+                normalize_isrc(&header[0]).is_some(),
+                "the CSV needs an `ISRC` column or ISRCs in the first column",
+            );
+            0
+        }
+    };
+
+    let mut seen = HashSet::new();
+    let mut list = IsrcList {
+        isrcs: Vec::new(),
+        invalid: Vec::new(),
+    };
+    for (line_number, line) in lines {
+        let value = csv_fields(line).into_iter().nth(column).unwrap_or_default();
+        match normalize_isrc(&value) {
+            Some(isrc) => {
+                if seen.insert(isrc.clone()) {
+                    list.isrcs.push(isrc);
+                }
+            }
+            None => list.invalid.push((line_number, value)),
+        }
+    }
+    Ok(list)
+}
+
+/// Add the songs with the ISRCs in a CSV, e.g. exported from another service, to the library
+pub async fn import_isrcs(client: &apple_music::Client, path: &Path, dry_run: bool) -> Result<()> {
+    let list = parse_isrc_csv(
+        &fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?,
+    )?;
+
+    if !list.invalid.is_empty() {
+        println!("Not ISRCs:");
+        for (line_number, value) in &list.invalid {
+            println!("  Line {line_number}: \"{value}\"");
+        }
+        println!();
+    }
+
+    let requested: Vec<&str> = list.isrcs.iter().map(String::as_str).collect();
+    // The first catalog song for each ISRC, since a recording can be on several albums
+    let mut catalog_ids: HashMap<String, CatalogSongId> = HashMap::new();
+    for chunk in requested.chunks(ISRCS_PER_REQUEST) {
+        let songs = client
+            .get_catalog_songs_by_isrc(client.storefront(), chunk)
+            .await?;
+        for (isrc, catalog_id) in songs.ids_by_isrc() {
+            if chunk.contains(&isrc) && !catalog_ids.contains_key(isrc) {
+                catalog_ids.insert(isrc.to_owned(), catalog_id.parse()?);
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut resolved = Vec::new();
+    let mut unresolved = Vec::new();
+    for isrc in &list.isrcs {
+        match catalog_ids.get(isrc) {
+            Some(catalog_id) => {
+                if seen.insert(catalog_id) {
+                    resolved.push(catalog_id);
+                }
+            }
+            None => unresolved.push(isrc),
+        }
+    }
+
+    if !unresolved.is_empty() {
+        println!("Not in the `{}` storefront:", client.storefront());
+        for isrc in &unresolved {
+            println!("  {isrc}");
+        }
+        println!();
+    }
+
+    if dry_run {
+        println!("{} songs would be added to the library", resolved.len());
+        return Ok(());
+    }
+
+    add_songs(client, &resolved, unresolved.len()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            storefront: "us".to_owned(),
+            albums: vec![
+                SnapshotAlbum {
+                    catalog_id: "10".parse().unwrap(),
+                    name: "Album 1".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    songs: vec![
+                        SnapshotSong {
+                            catalog_id: "1".parse().unwrap(),
+                            name: "Song 1".to_owned(),
+                            date_added: None,
+                        },
+                        SnapshotSong {
+                            catalog_id: "2".parse().unwrap(),
+                            name: "Song 2".to_owned(),
+                            date_added: None,
+                        },
+                    ],
+                    date_added: None,
+                },
+                SnapshotAlbum {
+                    catalog_id: "20".parse().unwrap(),
+                    name: "Album 2".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    songs: vec![SnapshotSong {
+                        catalog_id: "1".parse().unwrap(),
+                        name: "Song 1".to_owned(),
+                        date_added: None,
+                    }],
+                    date_added: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_resolve_all_available() {
+        let snapshot = snapshot();
+        let available = HashSet::from(["1".parse().unwrap(), "2".parse().unwrap()]);
+        let expected = Resolution {
+            resolved: vec![
+                &snapshot.albums[0].songs[0].catalog_id,
+                &snapshot.albums[0].songs[1].catalog_id,
+            ],
+            unresolved: vec![],
+        };
+        assert_eq!(resolve(&snapshot, &available), expected);
+    }
+
+    #[test]
+    fn test_resolve_unavailable() {
+        let snapshot = snapshot();
+        let available = HashSet::from(["2".parse().unwrap()]);
+        let expected = Resolution {
+            resolved: vec![&snapshot.albums[0].songs[1].catalog_id],
+            unresolved: vec![
+                (&snapshot.albums[0], &snapshot.albums[0].songs[0]),
+                (&snapshot.albums[1], &snapshot.albums[1].songs[0]),
+            ],
+        };
+        assert_eq!(resolve(&snapshot, &available), expected);
+    }
+
+    #[test]
+    fn test_snapshot_writer() {
+        let snapshot = snapshot();
+        let mut writer = SnapshotWriter::new(Vec::new(), &snapshot.storefront).unwrap();
+        for album in &snapshot.albums {
+            writer.push(album).unwrap();
+        }
+        let json = writer.finish().unwrap();
+        assert_eq!(serde_json::from_slice::<Snapshot>(&json).unwrap(), snapshot);
+
+        let json = SnapshotWriter::new(Vec::new(), "us")
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Snapshot>(&json).unwrap().albums,
+            vec![],
+        );
+    }
+
+    #[test]
+    fn test_normalize_isrc() {
+        assert_eq!(
+            normalize_isrc(" us-dem-20-00001 ").as_deref(),
+            Some("USDEM2000001"),
+        );
+        assert_eq!(normalize_isrc("USDEM200001"), None);
+        // 12 bytes, with the Ä across the boundary between the country and registrant codes
+        assert_eq!(normalize_isrc("AÄ123456789"), None);
+        assert_eq!(normalize_isrc("USDEM20000０1"), None);
+    }
+
+    #[test]
+    fn test_parse_isrc_csv() {
+        let csv = "\u{feff}Title,Artist,ISRC\n\
+                   \"Song, 1\",Artist,US-DEM-20-00001\n\
+                   Song 2,Artist,usdem2000002\n\
+                   \n\
+                   Song 1 again,Artist,USDEM2000001\n\
+                   Song 3,Artist,\n";
+        assert_eq!(
+            parse_isrc_csv(csv).unwrap(),
+            IsrcList {
+                isrcs: vec!["USDEM2000001".to_owned(), "USDEM2000002".to_owned()],
+                invalid: vec![(6, String::new())],
+            },
+        );
+        assert_eq!(
+            parse_isrc_csv("USDEM2000001\nUSDEM2000002,extra\n")
+                .unwrap()
+                .isrcs,
+            vec!["USDEM2000001".to_owned(), "USDEM2000002".to_owned()],
+        );
+        assert!(parse_isrc_csv("Title,Artist\nSong,Artist\n").is_err());
+        assert!(parse_isrc_csv("").is_err());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let snapshot = snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(serde_json::from_str::<Snapshot>(&json).unwrap(), snapshot);
+    }
+}
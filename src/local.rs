@@ -0,0 +1,433 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, ensure};
+use futures_util::TryStreamExt;
+use serde::Deserialize;
+
+use crate::{
+    apple_music::{self, CatalogAlbumId, CatalogSongId, custom_types::ContentRating},
+    discovery, matching, migrate, output, tags,
+};
+
+/// The parts of an exported `Library.xml` that are used
+#[derive(Deserialize)]
+struct LibraryXml {
+    #[serde(rename = "Tracks", default)]
+    tracks: BTreeMap<String, Track>,
+}
+
+#[derive(Deserialize)]
+struct Track {
+    #[serde(rename = "Album")]
+    album: Option<String>,
+    #[serde(rename = "Artist")]
+    artist: Option<String>,
+    #[serde(rename = "Album Artist")]
+    album_artist: Option<String>,
+    /// Streamed from or downloaded from Apple Music, rather than a purchased, matched or uploaded
+    /// file
+    #[serde(rename = "Apple Music", default)]
+    apple_music: bool,
+    /// Added to a playlist without being added to the library
+    #[serde(rename = "Playlist Only", default)]
+    playlist_only: bool,
+    #[serde(rename = "Podcast", default)]
+    podcast: bool,
+    #[serde(rename = "Has Video", default)]
+    has_video: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Issue {
+    /// Some of the album's tracks aren't from Apple Music, so they aren't matched to the catalog
+    /// album
+    MixedSources {
+        apple_music_tracks: usize,
+        other_tracks: usize,
+    },
+    /// Other editions of the album by the same artist also have tracks in the library
+    SplitAcrossEditions { other_albums: Vec<String> },
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::MixedSources {
+                apple_music_tracks,
+                other_tracks,
+            } => write!(
+                f,
+                "{apple_music_tracks} tracks from Apple Music and {other_tracks} purchased, matched or uploaded",
+            ),
+            Issue::SplitAcrossEditions { other_albums } => write!(
+                f,
+                "other editions are also in the library: {}",
+                other_albums
+                    .iter()
+                    .map(|name| format!("\"{name}\""))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct LocalAlbum {
+    name: String,
+    artist_name: String,
+    track_count: usize,
+    issues: Vec<Issue>,
+}
+
+/// Albums with issues, sorted by artist and name
+fn find_issues(library: &LibraryXml) -> Vec<LocalAlbum> {
+    // (artist, album) to (Apple Music tracks, other tracks)
+    let mut albums: BTreeMap<(&str, &str), (usize, usize)> = BTreeMap::new();
+    for track in library.tracks.values() {
+        if track.playlist_only || track.podcast || track.has_video {
+            continue;
+        }
+        let (Some(album), Some(artist_name)) = (
+            track.album.as_deref(),
+            track.album_artist.as_deref().or(track.artist.as_deref()),
+        ) else {
+            continue;
+        };
+        let counts = albums.entry((artist_name, album)).or_default();
+        if track.apple_music {
+            counts.0 += 1;
+        } else {
+            counts.1 += 1;
+        }
+    }
+
+    albums
+        .iter()
+        .filter_map(
+            |(&(artist_name, name), &(apple_music_tracks, other_tracks))| {
+                let mut issues = Vec::new();
+                if apple_music_tracks > 0 && other_tracks > 0 {
+                    issues.push(Issue::MixedSources {
+                        apple_music_tracks,
+                        other_tracks,
+                    });
+                }
+                let base_title = discovery::base_title(name);
+                let other_albums: Vec<String> = albums
+                    .keys()
+                    .filter(|&&(other_artist_name, other_name)| {
+                        other_artist_name == artist_name
+                            && other_name != name
+                            && discovery::base_title(other_name).eq_ignore_ascii_case(base_title)
+                    })
+                    .map(|&(_, other_name)| other_name.to_owned())
+                    .collect();
+                if !other_albums.is_empty() {
+                    issues.push(Issue::SplitAcrossEditions { other_albums });
+                }
+                (!issues.is_empty()).then(|| LocalAlbum {
+                    name: name.to_owned(),
+                    artist_name: artist_name.to_owned(),
+                    track_count: apple_music_tracks + other_tracks,
+                    issues,
+                })
+            },
+        )
+        .collect()
+}
+
+fn read(path: &Path) -> Result<LibraryXml> {
+    plist::from_file(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+/// Find albums in an exported `Library.xml` that likely need migrating and the library IDs the
+/// API has for them
+pub async fn scan(client: &apple_music::Client, path: &Path) -> Result<()> {
+    let albums = find_issues(&read(path)?);
+    if albums.is_empty() {
+        println!("No albums with missing or duplicated catalog entries");
+        return Ok(());
+    }
+
+    let library_albums: Vec<_> = client.library_albums().try_collect().await?;
+
+    for (i, album) in albums.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "\"{}\" by {} ({} tracks)",
+            album.name, album.artist_name, album.track_count,
+        );
+        for issue in &album.issues {
+            println!("  {issue}");
+        }
+        let library_ids: Vec<&str> = library_albums
+            .iter()
+            .filter(|library_album| {
+                library_album.name.eq_ignore_ascii_case(&album.name)
+                    && library_album
+                        .artist_name
+                        .eq_ignore_ascii_case(&album.artist_name)
+            })
+            .map(|library_album| library_album.library_id.as_str())
+            .collect();
+        if library_ids.is_empty() {
+            println!("  Not found in the library through the API");
+        } else {
+            println!("  Source library album: {}", library_ids.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// An audio file matched by its tags
+struct AudioFile {
+    /// Relative to the folder
+    path: PathBuf,
+    /// The file name without the extension if there's no title tag
+    name: String,
+    artist_name: String,
+    isrc: Option<String>,
+    duration: Option<Duration>,
+    disc_number: u8,
+    /// The position of the file on its disc in file name order if there's no track number tag
+    track_number: u8,
+}
+
+impl matching::MatchableTrack for AudioFile {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn artist_name(&self) -> &str {
+        &self.artist_name
+    }
+
+    fn isrc(&self) -> Option<&str> {
+        self.isrc.as_deref()
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    fn disc_number(&self) -> u8 {
+        self.disc_number
+    }
+
+    fn track_number(&self) -> u8 {
+        self.track_number
+    }
+
+    fn catalog_id(&self) -> Option<&CatalogSongId> {
+        None
+    }
+
+    fn content_rating(&self) -> Option<&ContentRating> {
+        None
+    }
+}
+
+/// Every file under the folder, sorted by path so that discs are in separate folders in order
+fn find_files(folder: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut folders = vec![folder.to_owned()];
+    while let Some(folder) = folders.pop() {
+        for entry in
+            fs::read_dir(&folder).with_context(|| format!("failed to read {}", folder.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                folders.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read_audio_files(folder: &Path) -> Result<Vec<AudioFile>> {
+    let mut files: Vec<AudioFile> = Vec::new();
+    for path in find_files(folder)? {
+        let tags = match tags::read(&path) {
+            Ok(Some(tags)) => tags,
+            Ok(None) => continue,
+            Err(error) => {
+                eprintln!("Warning: skipping a file, {error:#}");
+                continue;
+            }
+        };
+        let disc_number = tags.disc_number.unwrap_or(1);
+        let track_number = tags.track_number.unwrap_or_else(|| {
+            let on_disc = files
+                .iter()
+                .filter(|f| f.disc_number == disc_number)
+                .count();
+            u8::try_from(on_disc + 1).unwrap_or(u8::MAX)
+        });
+        files.push(AudioFile {
+            name: tags.title.unwrap_or_else(|| {
+                path.file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            }),
+            artist_name: tags.artist.unwrap_or_default(),
+            isrc: tags.isrc,
+            duration: tags.duration,
+            disc_number,
+            track_number,
+            path: path.strip_prefix(folder).unwrap_or(&path).to_owned(),
+        });
+    }
+    Ok(files)
+}
+
+/// Match the audio files in a folder against a catalog album's tracks and print which track each
+/// file is
+pub async fn match_files(
+    client: &apple_music::Client,
+    folder: &Path,
+    catalog_id: &CatalogAlbumId,
+    options: &matching::MatchOptions,
+) -> Result<()> {
+    let files = read_audio_files(folder)?;
+    ensure!(
+        !files.is_empty(),
+        "no FLAC, MP3 or M4A files in {}",
+        folder.display(),
+    );
+    let album = migrate::load_destination(client, catalog_id).await?;
+    let report = matching::match_track_lists(&files, &album.tracks, options)?;
+
+    let multi_disc = album.tracks.iter().any(|t| t.disc_number > 1);
+    let describe = |track: &apple_music::custom_types::TrackNoLibrary| {
+        format!(
+            "#{} \"{}\" ({})",
+            output::format_position(track.disc_number, track.track_number, multi_disc),
+            track.name,
+            track.catalog_id,
+        )
+    };
+    println!(
+        "\"{}\" by {} ({})",
+        album.name, album.artist_name, album.catalog_id,
+    );
+    let mut unmatched_files = Vec::new();
+    let mut matched_tracks = Vec::new();
+    for result in &report.results {
+        match result {
+            matching::TrackMatchResult::Match {
+                source,
+                destination,
+                method,
+                warnings,
+                ..
+            } => {
+                matched_tracks.push(&destination.catalog_id);
+                let warnings: String = warnings
+                    .iter()
+                    .map(|warning| format!(", {warning}"))
+                    .collect();
+                println!(
+                    "  {} {} {} by {method}{warnings}",
+                    source.path.display(),
+                    output::arrow(),
+                    describe(destination),
+                );
+            }
+            matching::TrackMatchResult::NoMatch { source } => unmatched_files.push(source),
+        }
+    }
+    if !unmatched_files.is_empty() {
+        println!("Files without a catalog track:");
+        for file in unmatched_files {
+            println!("  {}", file.path.display());
+        }
+    }
+    let missing: Vec<_> = album
+        .tracks
+        .iter()
+        .filter(|track| !matched_tracks.contains(&&track.catalog_id))
+        .collect();
+    if !missing.is_empty() {
+        println!("Catalog tracks without a file:");
+        for track in missing {
+            println!("  {}", describe(track));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(album: &str, apple_music: bool) -> String {
+        let apple_music = if apple_music {
+            "<key>Apple Music</key><true/>"
+        } else {
+            ""
+        };
+        format!(
+            "<dict><key>Album</key><string>{album}</string>\
+             <key>Artist</key><string>Artist</string>{apple_music}</dict>"
+        )
+    }
+
+    #[test]
+    fn test_find_issues() {
+        let tracks = [
+            track("Album", true),
+            track("Album", false),
+            track("Album (Deluxe Edition)", true),
+            track("Other", true),
+        ];
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <plist version=\"1.0\"><dict><key>Tracks</key><dict>{}</dict></dict></plist>",
+            tracks
+                .iter()
+                .enumerate()
+                .map(|(i, track)| format!("<key>{i}</key>{track}"))
+                .collect::<String>(),
+        );
+        let library: LibraryXml = plist::from_bytes(xml.as_bytes()).unwrap();
+        assert_eq!(
+            find_issues(&library),
+            vec![
+                LocalAlbum {
+                    name: "Album".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    track_count: 2,
+                    issues: vec![
+                        Issue::MixedSources {
+                            apple_music_tracks: 1,
+                            other_tracks: 1,
+                        },
+                        Issue::SplitAcrossEditions {
+                            other_albums: vec!["Album (Deluxe Edition)".to_owned()],
+                        },
+                    ],
+                },
+                LocalAlbum {
+                    name: "Album (Deluxe Edition)".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    track_count: 1,
+                    issues: vec![Issue::SplitAcrossEditions {
+                        other_albums: vec!["Album".to_owned()],
+                    }],
+                },
+            ],
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Keeps concurrent runs from changing the same library at once
+
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result, bail};
+
+use crate::apple_music;
+
+/// Held until dropped, the operating system also releases it if the process dies
+pub struct LibraryLock {
+    _file: File,
+}
+
+/// FNV-1a, so the fingerprint is the same across builds and the token isn't in the file name
+fn fingerprint(user_token: &str) -> String {
+    let hash = user_token
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+    format!("{hash:016x}")
+}
+
+fn path(user_token: &str) -> Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .context("could not determine the data directory")?
+        .join(env!("CARGO_PKG_NAME"))
+        .join("locks")
+        .join(format!("{}.lock", fingerprint(user_token))))
+}
+
+/// Lock the library of the client's account, `None` offline since no changes can be made
+pub fn acquire(client: &apple_music::Client) -> Result<Option<LibraryLock>> {
+    let Some(user_token) = client.user_token() else {
+        return Ok(None);
+    };
+    let path = path(user_token)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => {
+            let mut pid = String::new();
+            let _ = file.read_to_string(&mut pid);
+            bail!(
+                "another migration is in progress for this account (process {}), wait for it to \
+                 finish",
+                pid.trim(),
+            );
+        }
+        Err(fs::TryLockError::Error(error)) => {
+            return Err(error).with_context(|| format!("failed to lock {}", path.display()));
+        }
+    }
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", std::process::id())?;
+    Ok(Some(LibraryLock { _file: file }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(""), "cbf29ce484222325");
+        assert_eq!(fingerprint("a"), "af63dc4c8601ec8c");
+        assert_ne!(fingerprint("token1"), fingerprint("token2"));
+    }
+}
@@ -0,0 +1,113 @@
+//! Playlists of the migrated tracks to queue up and listen through
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, ensure};
+
+use crate::{apple_music::custom_types::TrackNoLibrary, matching};
+
+fn extension(path: &Path) -> Option<&str> {
+    path.extension().and_then(|extension| extension.to_str())
+}
+
+/// Checked up front so that a migration isn't made without its playlist
+pub fn parse_path(value: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(value);
+    ensure!(
+        matches!(extension(&path), Some("m3u" | "m3u8" | "txt")),
+        "playlist file must end with `.m3u`, `.m3u8` or `.txt`",
+    );
+    Ok(path)
+}
+
+fn song_url(storefront: &str, track: &TrackNoLibrary) -> String {
+    format!(
+        "https://music.apple.com/{storefront}/song/{}",
+        track.catalog_id
+    )
+}
+
+/// Extended M3U, or one URL a line for `.txt`
+fn to_string(tracks: &[&TrackNoLibrary], storefront: &str, extended: bool) -> String {
+    let mut contents = if extended {
+        "#EXTM3U\n".to_owned()
+    } else {
+        String::new()
+    };
+    for track in tracks {
+        if extended {
+            // -1 if the length isn't known
+            let seconds = track
+                .duration
+                .map_or(-1, |duration| duration.as_secs() as i64);
+            contents += &format!("#EXTINF:{seconds},{} - {}\n", track.artist_name, track.name);
+        }
+        contents += &song_url(storefront, track);
+        contents += "\n";
+    }
+    contents
+}
+
+/// Write the destination tracks matched to source tracks in the library, in album order
+pub fn write(report: &matching::MatchReport, storefront: &str, path: &Path) -> Result<()> {
+    let mut tracks: Vec<&TrackNoLibrary> = report
+        .results
+        .iter()
+        .filter_map(|result| match result {
+            matching::TrackMatchResult::Match {
+                source,
+                destination,
+                ..
+            } if source.library_id.is_some() => Some(*destination),
+            _ => None,
+        })
+        .collect();
+    tracks.sort_by_key(|track| (track.disc_number, track.track_number));
+    let extended = extension(path) != Some("txt");
+    fs::write(path, to_string(&tracks, storefront, extended))
+        .with_context(|| format!("failed to write playlist to {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_to_string() {
+        let track = |catalog_id: &str, name: &str, duration| TrackNoLibrary {
+            catalog_id: catalog_id.parse().unwrap(),
+            name: name.to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            disc_number: 1,
+            track_number: 1,
+            content_rating: None,
+            isrc: None,
+            release_date: None,
+            duration,
+            playable: true,
+        };
+        let tracks = [
+            track("10", "Song", Some(Duration::from_millis(185_600))),
+            track("11", "Interlude", None),
+        ];
+        let tracks: Vec<&TrackNoLibrary> = tracks.iter().collect();
+        assert_eq!(
+            to_string(&tracks, "gb", true),
+            "#EXTM3U\n\
+             #EXTINF:185,Artist - Song\n\
+             https://music.apple.com/gb/song/10\n\
+             #EXTINF:-1,Artist - Interlude\n\
+             https://music.apple.com/gb/song/11\n",
+        );
+        assert_eq!(
+            to_string(&tracks, "gb", false),
+            "https://music.apple.com/gb/song/10\nhttps://music.apple.com/gb/song/11\n",
+        );
+    }
+}
@@ -1,14 +1,52 @@
-mod apple_music;
-mod matching;
+mod account;
+mod album;
+mod auth;
+mod batch;
+mod cache;
+mod config;
+mod credentials;
+mod discovery;
+mod history;
+mod interrupt;
+mod library;
+mod local;
+mod lock;
+mod m3u;
+mod migrate;
+mod musicbrainz;
+mod notify;
+mod playlist;
+mod progress;
+mod report;
+mod storefronts;
+mod tags;
+mod token;
+mod yaml;
 
-use anyhow::{Result, ensure};
-use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 
-use crate::apple_music::custom_types;
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use apple_music_album_library_migrator::{apple_music, matching, output, stats, telemetry};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use reqwest::header::{HeaderName, HeaderValue};
 
 #[derive(Parser)]
 #[command(version, author, about, long_about = None)]
 struct Cli {
+    /// Print `->` and `...` instead of `→` and `…`, for terminals and logs that mangle Unicode
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// Print the requests made by endpoint, retries, cache hits and the time spent in each stage
+    /// when the command finishes
+    #[arg(long, global = true)]
+    stats: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -17,35 +55,223 @@ struct Cli {
 enum Commands {
     /// Migrates library status of songs from one album to another
     Migrate {
-        /// Apple Music developer token JWT
-        #[arg(short = 'D', long)]
-        developer_token: String,
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// Print the matched tracks from the source and destination and do not make any changes,
+        /// exits with 3 if there are no tracks in the library to migrate
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Migrate even if the albums have different UPCs and no recordings in common, or matched
+        /// destination tracks can't be played in the storefront
+        #[arg(long)]
+        force: bool,
 
-        /// Origin header value
-        #[arg(short = 'O', long = "origin")]
-        origin_header: Option<String>,
+        /// Fail before making any changes if a track in the library has no match in the
+        /// destination, however many tracks either album has
+        #[arg(long, visible_alias = "require-full-coverage")]
+        fail_on_unmatched: bool,
 
-        /// Apple Music User Token
-        #[arg(short = 'U', long)]
-        user_token: String,
+        /// Fail before making any changes unless every track in the library matches an explicit
+        /// destination track, listing the tracks that wouldn't be explicit
+        #[arg(long)]
+        upgrade_to_explicit: bool,
+
+        /// Only print how many tracks in the library match for each album, not the track listings
+        #[arg(long)]
+        summary_only: bool,
+
+        /// Print the catalog, library and ISRC IDs of each track in the dry run, e.g. for `--map`
+        #[arg(long, conflicts_with = "summary_only")]
+        show_ids: bool,
+
+        /// Don't ask for confirmation before only removing the source album when the destination
+        /// album is already fully in the library, or before swapping the albums with
+        /// `--detect-direction`
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Given two catalog IDs, use whichever album has more songs in the library as the source
+        #[arg(long)]
+        detect_direction: bool,
+
+        /// Match a source track to a destination track by position instead of automatically (e.g.
+        /// `3=5`, or `1-3=2-1` for disc 1 track 3 and disc 2 track 1), can be repeated
+        #[arg(long = "map", value_name = "SOURCE=DESTINATION")]
+        mappings: Vec<matching::TrackMapping>,
+
+        /// Only match tracks on a source disc to tracks on a destination disc (e.g. `1=2`), for box
+        /// sets where the destination orders the discs differently, can be repeated
+        #[arg(long = "map-disc", value_name = "SOURCE=DESTINATION")]
+        disc_mappings: Vec<matching::DiscMapping>,
+
+        #[command(flatten)]
+        matching: MatchArgs,
+
+        /// Look up other ISRCs of the recordings on MusicBrainz for tracks without an ISRC match
+        #[arg(long)]
+        musicbrainz: bool,
+
+        /// Warn if the source album is in the recently played albums or heavy rotation, since
+        /// migrating resets its play history
+        #[arg(long)]
+        check_rotation: bool,
+
+        /// Also write the matched and unmatched tracks to a `.json`, `.yaml` or `.md` file
+        #[arg(long, value_name = "PATH", value_parser = report::parse_path)]
+        report: Option<PathBuf>,
+
+        /// Include song.link URLs for the destination tracks in the report
+        #[arg(long, requires = "report")]
+        song_links: bool,
+
+        /// Don't mark the destination album as a favorite when the source album is one
+        #[arg(long)]
+        no_favorite: bool,
+
+        /// Also write the destination tracks that are added to an M3U playlist (`.m3u` or
+        /// `.m3u8`) or a list of their Apple Music URLs (`.txt`), to listen through them
+        #[arg(long, value_name = "PATH", value_parser = m3u::parse_path)]
+        playlist: Option<PathBuf>,
+
+        /// Show a desktop notification when done if it took at least this many seconds
+        #[arg(long, value_name = "SECONDS")]
+        notify_after: Option<u64>,
 
-        /// Apple Music API host
-        #[arg(short = 'H', long)]
-        host: Host,
+        /// The library ID (starts with `l.`) of the album that has songs added to the library, or
+        /// the catalog ID it was added from, a music.apple.com link or an alias from the config
+        /// file
+        #[arg(value_parser = parse_album_id)]
+        source_album_id: apple_music::AlbumId,
 
-        /// Apple Music catalog storefront (e.g. `us`)
-        #[arg(short = 'S', long)]
-        storefront: String,
+        /// The catalog ID (numeric) of the album that will have songs added to the library, a
+        /// music.apple.com link or an alias from the config file, or several for a box set that
+        /// the catalog has split into separate albums
+        #[arg(value_parser = parse_catalog_album_id, required = true)]
+        destination_album_catalog_ids: Vec<apple_music::CatalogAlbumId>,
+    },
+
+    /// Migrates every album by an artist in the library to its most complete catalog edition
+    MigrateArtist {
+        #[command(flatten)]
+        api: ApiArgs,
 
-        /// Print the matched tracks from the source and destination and do not make any changes
+        /// Print the planned migrations and do not make any changes
         #[arg(long)]
         dry_run: bool,
 
-        /// The library ID (starts with `l.`) of the album that has songs added to the library
-        source_album_library_id: String,
+        /// Fail before making any changes if a track in the library has no match in the
+        /// destination, however many tracks either album has
+        #[arg(long, visible_alias = "require-full-coverage")]
+        fail_on_unmatched: bool,
 
-        /// The catalog ID (numeric) of the album that will have songs added to the library
-        destination_album_catalog_id: String,
+        /// Only print how many tracks in the library match for each album, not the track listings
+        #[arg(long)]
+        summary_only: bool,
+
+        #[command(flatten)]
+        matching: MatchArgs,
+
+        /// Prefer Apple Digital Master editions when picking the edition to migrate to
+        #[arg(long)]
+        prefer_adm: bool,
+
+        /// Don't mark the destination album as a favorite when the source album is one
+        #[arg(long)]
+        no_favorite: bool,
+
+        /// Also write progress events as JSON lines to a file or named pipe
+        /// (`--progress-json=PATH`), or stderr if no path is given
+        #[arg(long, value_name = "PATH", num_args = 0..=1, require_equals = true, default_missing_value = "-")]
+        progress_json: Option<PathBuf>,
+
+        /// Show a desktop notification when done if it took at least this many seconds
+        #[arg(long, value_name = "SECONDS")]
+        notify_after: Option<u64>,
+
+        /// The artist name as shown on the albums in the library, ignoring case
+        artist_name: String,
+    },
+
+    /// Plan migrations for many albums at once
+    Batch {
+        #[command(subcommand)]
+        command: BatchCommands,
+    },
+
+    /// Show information about catalog albums
+    Album {
+        #[command(subcommand)]
+        command: AlbumCommands,
+    },
+
+    /// Check whether an album and its tracks are available in other storefronts
+    CompareStorefronts {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// Storefronts to check (e.g. `gb,jp`)
+        #[arg(long, value_delimiter = ',', required = true, value_parser = StorefrontParser, hide_possible_values = true)]
+        storefronts: Vec<String>,
+
+        /// The catalog ID (numeric) of the album in the `--storefront` storefront, a
+        /// music.apple.com link or an alias from the config file
+        #[arg(value_parser = parse_catalog_album_id)]
+        catalog_id: apple_music::CatalogAlbumId,
+    },
+
+    /// Export or import library snapshots
+    Library {
+        #[command(subcommand)]
+        command: LibraryCommands,
+    },
+
+    /// Work with the library as exported from the Music app
+    Local {
+        #[command(subcommand)]
+        command: LocalCommands,
+    },
+
+    /// Check library playlists
+    Playlist {
+        #[command(subcommand)]
+        command: PlaylistCommands,
+    },
+
+    /// Sign in with MusicKit JS in a browser and store the resulting Media User Token
+    Auth {
+        /// Apple Music developer token JWT, its origin must allow `http://localhost`
+        #[arg(short = 'D', long)]
+        developer_token: String,
+
+        /// Local port to serve the sign-in page on
+        #[arg(long, default_value_t = 8765)]
+        port: u16,
+    },
+
+    /// Show the storefront and library size of the account the user token belongs to
+    Whoami {
+        #[command(flatten)]
+        api: ApiArgs,
+    },
+
+    /// Show the migrations that have been made
+    History {
+        #[command(subcommand)]
+        command: HistoryCommands,
+    },
+
+    /// Show or clear the catalog responses kept between runs
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+
+    /// Inspect Apple Music tokens
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands,
     },
 
     /// Generate shell completions
@@ -54,6 +280,613 @@ enum Commands {
         #[arg(value_enum)]
         shell: clap_complete_command::Shell,
     },
+
+    /// Generate roff man pages for the command and every subcommand
+    Man {
+        /// The directory to write the pages to, it must already exist
+        output_dir: PathBuf,
+    },
+}
+
+impl Commands {
+    /// From `--notify-after`, for the commands that can take a long time
+    fn notify_after(&self) -> Option<Duration> {
+        match self {
+            Commands::Migrate { notify_after, .. }
+            | Commands::MigrateArtist { notify_after, .. }
+            | Commands::Batch {
+                command: BatchCommands::Build { notify_after, .. },
+            } => notify_after.map(Duration::from_secs),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum AlbumCommands {
+    /// Print an album's details and track listing
+    Info {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// The catalog ID (numeric) of the album, a music.apple.com link or an alias from the
+        /// config file
+        #[arg(value_parser = parse_catalog_album_id)]
+        catalog_id: apple_music::CatalogAlbumId,
+    },
+
+    /// List the editions of an album (e.g. standard, deluxe, clean, remastered or spatial audio)
+    /// with their catalog IDs, release dates and track counts
+    Editions {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// Search for the album by this artist instead of giving its catalog ID
+        #[arg(long, requires = "title", conflicts_with = "catalog_id")]
+        artist: Option<String>,
+
+        /// The album's title, with or without an edition suffix like ` (Deluxe Edition)`
+        #[arg(long, requires = "artist")]
+        title: Option<String>,
+
+        /// The catalog ID (numeric) of any edition of the album, a music.apple.com link or an
+        /// alias from the config file
+        #[arg(value_parser = parse_catalog_album_id, required_unless_present = "artist")]
+        catalog_id: Option<apple_music::CatalogAlbumId>,
+    },
+}
+
+#[derive(Subcommand)]
+enum LibraryCommands {
+    /// Write a snapshot of the albums and songs in the library to a file
+    Export {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// The file to write the snapshot to
+        output: PathBuf,
+    },
+
+    /// Add the songs listed in a snapshot to the library
+    Import {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// Print which songs would be added and which can't be resolved and do not make any changes
+        #[arg(long)]
+        dry_run: bool,
+
+        /// The snapshot file to import
+        input: PathBuf,
+    },
+
+    /// Add the songs with the ISRCs in a CSV file, e.g. exported from another service, to the
+    /// library
+    ImportIsrcs {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// Print which songs would be added and which ISRCs can't be resolved and do not make any
+        /// changes
+        #[arg(long)]
+        dry_run: bool,
+
+        /// A CSV file with an `ISRC` column, or with ISRCs in the first column and no header
+        input: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum BatchCommands {
+    /// Pair the albums in a file of Apple Music links with their most complete catalog editions
+    /// and write the pairs to a batch file to review
+    Build {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        #[command(flatten)]
+        matching: MatchArgs,
+
+        /// Prefer Apple Digital Master editions when picking the edition to migrate to
+        #[arg(long)]
+        prefer_adm: bool,
+
+        /// Most links to pair at once, fewer are paired while the API is rate limiting and more
+        /// again once it stops
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+        concurrency: u16,
+
+        /// Show a desktop notification when done if it took at least this many seconds
+        #[arg(long, value_name = "SECONDS")]
+        notify_after: Option<u64>,
+
+        /// A file with one library or catalog album link per line, lines starting with `#` are
+        /// ignored
+        links: PathBuf,
+
+        /// The file to write the batch to, as YAML if it ends with `.yaml` and otherwise JSON
+        output: PathBuf,
+    },
+
+    /// Check that the albums in a batch file, JSON or YAML, are still in the library and catalog
+    /// without matching any tracks or making changes
+    Check {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        batch: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum LocalCommands {
+    /// Find albums with missing or duplicated catalog entries in an exported `Library.xml` and
+    /// their library IDs to migrate from
+    Scan {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// The file exported with File > Library > Export Library in the Music app
+        library_xml: PathBuf,
+    },
+
+    /// Match the FLAC, MP3 and M4A files in a folder against a catalog album's tracks by their
+    /// tags and print which track each file is
+    MatchFiles {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        #[command(flatten)]
+        matching: MatchArgs,
+
+        /// Searched recursively
+        folder: PathBuf,
+
+        /// The catalog ID (numeric) of the album, a music.apple.com link or an alias from the
+        /// config file
+        #[arg(value_parser = parse_catalog_album_id)]
+        catalog_id: apple_music::CatalogAlbumId,
+    },
+}
+
+#[derive(Subcommand)]
+enum PlaylistCommands {
+    /// List the songs in library playlists that are from migrated albums or no longer available,
+    /// as a to-do list of playlists to repair
+    Audit {
+        #[command(flatten)]
+        api: ApiArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommands {
+    /// List recorded migrations, most recent first
+    List {
+        /// Only migrations of albums whose artist name contains this, ignoring case
+        #[arg(long)]
+        artist: Option<String>,
+
+        /// Only migrations of albums whose source or destination name contains this, ignoring
+        /// case
+        #[arg(long)]
+        album: Option<String>,
+
+        /// Show at most this many migrations
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+
+    /// Show a recorded migration and what each track was migrated to
+    Show {
+        /// The number shown by `history list`
+        id: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Show how many responses are cached, the space they take and how old they are
+    Stats,
+
+    /// Remove cached responses so that they're requested again, all of them unless limited to a
+    /// storefront or album
+    Clear {
+        /// Only responses from this storefront
+        #[arg(long)]
+        storefront: Option<String>,
+
+        /// Only responses for this album, from every storefront unless `--storefront` is given
+        #[arg(long, value_parser = parse_catalog_album_id)]
+        album: Option<apple_music::CatalogAlbumId>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenCommands {
+    /// Print the header and claims of a developer token and describe a Media User Token
+    Inspect {
+        /// Apple Music developer token JWT
+        #[arg(short = 'D', long)]
+        developer_token: String,
+
+        /// Apple Music User Token
+        #[arg(short = 'U', long)]
+        user_token: Option<String>,
+    },
+}
+
+#[derive(Args)]
+struct ApiArgs {
+    /// Use the options of a profile in the config file for any not given here
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Apple Music developer token JWT
+    #[arg(short = 'D', long, required_unless_present_any = ["offline", "profile", "developer_token_file"])]
+    developer_token: Option<String>,
+
+    /// Read the developer token from a file, or stdin for `-`
+    #[arg(long, value_name = "PATH", conflicts_with = "developer_token")]
+    developer_token_file: Option<PathBuf>,
+
+    /// Origin header value
+    #[arg(short = 'O', long = "origin")]
+    origin_header: Option<String>,
+
+    /// Apple Music User Token, defaults to the one stored by `auth`
+    #[arg(short = 'U', long)]
+    user_token: Option<String>,
+
+    /// Run a command (e.g. `op read op://vault/apple-music/token`) and use what it prints as the
+    /// user token, so the token can stay in a password manager
+    #[arg(long, value_name = "COMMAND", conflicts_with = "user_token")]
+    user_token_command: Option<String>,
+
+    /// Read the user token from a file, or stdin for `-`
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["user_token", "user_token_command"])]
+    user_token_file: Option<PathBuf>,
+
+    /// Apple Music API host
+    #[arg(short = 'H', long, required_unless_present_any = ["offline", "profile"])]
+    host: Option<Host>,
+
+    /// Apple Music catalog storefront (e.g. `us`)
+    #[arg(short = 'S', long, required_unless_present_any = ["offline", "profile"], value_parser = StorefrontParser, hide_possible_values = true)]
+    storefront: Option<String>,
+
+    /// Language of catalog names (e.g. `en-US` for romanized names in the `jp` storefront),
+    /// defaults to the storefront's
+    #[arg(long, value_name = "TAG")]
+    language: Option<String>,
+
+    /// Extra header to send with every request, replacing any default with the same name, can be
+    /// repeated
+    #[arg(long = "header", value_name = "NAME: VALUE", value_parser = parse_header)]
+    headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// Print an equivalent `curl` command for every request, with `$DEVELOPER_TOKEN` and
+    /// `$MEDIA_USER_TOKEN` in place of the tokens
+    #[arg(long)]
+    dump_curl: bool,
+
+    /// Seconds before each attempt of a request times out [default: 60]
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Times to retry requests that don't change the library after transient network errors
+    /// [default: 2]
+    #[arg(long)]
+    retries: Option<u32>,
+
+    /// Minimum milliseconds between starting requests
+    #[arg(long, value_name = "MILLISECONDS")]
+    request_interval: Option<u64>,
+
+    /// User-Agent header value
+    #[arg(long)]
+    user_agent: Option<String>,
+
+    /// Proxy URL for all requests (e.g. `http://localhost:8080` or `socks5://localhost:1080`)
+    #[arg(long)]
+    proxy: Option<String>,
+
+    /// Seconds between TCP keep-alive probes on idle connections, off by default
+    #[arg(long, value_name = "SECONDS")]
+    keep_alive: Option<u64>,
+
+    /// Seconds an idle connection is kept for reuse, lower it if the server closes idle
+    /// connections first [default: 90]
+    #[arg(long, value_name = "SECONDS")]
+    pool_idle_timeout: Option<u64>,
+
+    /// Idle connections kept for reuse, 0 opens a new connection for every request [default: no
+    /// limit]
+    #[arg(long, value_name = "COUNT")]
+    max_idle_connections: Option<usize>,
+
+    /// Megabytes a response body can be before the request fails, in case a broken response never
+    /// ends [default: 32]
+    #[arg(long, value_name = "MB")]
+    max_response_size: Option<usize>,
+
+    /// Send every catalog request instead of reusing responses from earlier in the run or from
+    /// earlier runs, for when an album has changed. The responses still replace the cached ones.
+    #[arg(long, conflicts_with = "cache_ttl")]
+    no_cache: bool,
+
+    /// Seconds a catalog response is reused for before it's requested again, in this run or later
+    /// ones [default: the whole run, and a day in later runs]
+    #[arg(long, value_name = "SECONDS")]
+    cache_ttl: Option<u64>,
+
+    /// How to handle values in API responses that aren't known to this tool
+    #[arg(long, value_enum, default_value_t)]
+    parse_mode: apple_music::ParseMode,
+
+    /// Use bundled example albums instead of the API, no tokens or network access needed
+    #[arg(long, conflicts_with_all = ["profile", "developer_token", "developer_token_file", "user_token", "user_token_command", "user_token_file", "origin_header", "host", "storefront", "language", "headers", "dump_curl", "request_interval", "user_agent", "proxy", "keep_alive", "pool_idle_timeout", "max_idle_connections", "max_response_size", "no_cache", "cache_ttl"])]
+    offline: bool,
+}
+
+impl ApiArgs {
+    fn into_client(self) -> Result<apple_music::Client> {
+        let Self {
+            profile,
+            developer_token,
+            developer_token_file,
+            origin_header,
+            user_token,
+            user_token_command,
+            user_token_file,
+            host,
+            storefront,
+            language,
+            headers,
+            dump_curl,
+            timeout,
+            retries,
+            request_interval,
+            user_agent,
+            proxy,
+            keep_alive,
+            pool_idle_timeout,
+            max_idle_connections,
+            max_response_size,
+            no_cache,
+            cache_ttl,
+            parse_mode,
+            offline,
+        } = self;
+        if offline {
+            return Ok(apple_music::Client::offline(parse_mode));
+        }
+        let profile = match &profile {
+            Some(name) => config::load()?.into_profile(name)?,
+            None => config::Profile::default(),
+        };
+        ensure!(
+            !(developer_token_file.as_deref() == Some(Path::new("-"))
+                && user_token_file.as_deref() == Some(Path::new("-"))),
+            "only one of the tokens can be read from stdin",
+        );
+        let developer_token = match developer_token_file {
+            Some(path) => Some(credentials::from_file(&path)?),
+            None => developer_token,
+        };
+        // clap enforces these when there's no profile
+        let developer_token = developer_token.or(profile.developer_token).context(
+            "missing developer token, pass `--developer-token` or set it in the profile",
+        )?;
+        let storefront = storefront
+            .or(profile.storefront)
+            .context("missing storefront, pass `--storefront` or set it in the profile")?;
+        ensure!(
+            apple_music::validate_developer_token(&developer_token),
+            "invalid developer token (run `token inspect` to examine it)",
+        );
+        apple_music::check_storefront(&storefront)?;
+        let host = match (host, profile.host) {
+            (Some(host), _) => host,
+            (None, Some(host)) => Host::from_str(&host, true)
+                .map_err(|error| anyhow!("invalid host `{host}` in the profile: {error}"))?,
+            (None, None) => bail!("missing host, pass `--host` or set it in the profile"),
+        };
+
+        let mut builder = apple_music::Client::builder(developer_token, storefront)
+            .base_url(host.base_url().to_owned())
+            .parse_mode(parse_mode)
+            .dump_curl(dump_curl);
+        if let Some(timeout) = timeout.or(profile.timeout) {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(retries) = retries.or(profile.retries) {
+            builder = builder.retry_policy(apple_music::RetryPolicy {
+                max_attempts: retries + 1,
+                ..apple_music::RetryPolicy::default()
+            });
+        }
+        // Catalog requests work without one
+        let user_token = match (user_token, user_token_command, user_token_file) {
+            (Some(user_token), _, _) => Some(user_token),
+            (None, Some(command), _) => Some(credentials::from_command(&command)?),
+            (None, None, Some(path)) => Some(credentials::from_file(&path)?),
+            (None, None, None) => match (profile.user_token, profile.user_token_command) {
+                (Some(user_token), _) => Some(user_token),
+                (None, Some(command)) => Some(credentials::from_command(&command)?),
+                (None, None) => credentials::load()?.user_token,
+            },
+        };
+        if let Some(user_token) = user_token {
+            builder = builder.user_token(user_token);
+        }
+        if let Some(origin) = origin_header.or(profile.origin) {
+            builder = builder.origin(origin);
+        }
+        let profile_headers = profile
+            .headers
+            .iter()
+            .map(|header| {
+                parse_header(header)
+                    .with_context(|| format!("invalid header `{header}` in the profile"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (name, value) in profile_headers.into_iter().chain(headers) {
+            builder = builder.header(name, value);
+        }
+        if let Some(request_interval) = request_interval.or(profile.request_interval) {
+            builder = builder.min_request_interval(Duration::from_millis(request_interval));
+        }
+        if let Some(user_agent) = user_agent.or(profile.user_agent) {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(proxy) = proxy.or(profile.proxy) {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(keep_alive) = keep_alive.or(profile.keep_alive) {
+            builder = builder.keep_alive(Duration::from_secs(keep_alive));
+        }
+        if let Some(pool_idle_timeout) = pool_idle_timeout.or(profile.pool_idle_timeout) {
+            builder = builder.pool_idle_timeout(Duration::from_secs(pool_idle_timeout));
+        }
+        if let Some(max_idle_connections) = max_idle_connections.or(profile.max_idle_connections) {
+            builder = builder.max_idle_connections(max_idle_connections);
+        }
+        if let Some(language) = language.or(profile.language) {
+            ensure!(
+                apple_music::validate_language(&language),
+                "invalid language `{language}`, expected a tag like `en-US`",
+            );
+            builder = builder.language(language);
+        }
+        match apple_music::cache::ResponseCache::default_path() {
+            Ok(path) => builder = builder.disk_cache(path),
+            Err(error) => eprintln!("Warning: not keeping responses between runs, {error:#}"),
+        }
+        builder = builder.cache(!no_cache);
+        if let Some(cache_ttl) = cache_ttl.or(profile.cache_ttl) {
+            builder = builder.cache_ttl(Duration::from_secs(cache_ttl));
+        }
+        if let Some(max_response_size) = max_response_size.or(profile.max_response_size) {
+            builder = builder.max_response_size(max_response_size * 1024 * 1024);
+        }
+        builder.build()
+    }
+}
+
+#[derive(Args)]
+struct MatchArgs {
+    /// Minimum title similarity from 0.0 to 1.0 for tracks by the same artist with different
+    /// titles to match, 1.0 only allows differences in case, whitespace and abbreviations or
+    /// numerals like `Pt. II` and `Part 2`
+    #[arg(long, default_value_t = matching::DEFAULT_FUZZY_THRESHOLD, value_parser = parse_fuzzy_threshold)]
+    fuzzy_threshold: f64,
+
+    /// Ignore punctuation (e.g. curly and straight apostrophes, dashes and hyphens) and repeated
+    /// whitespace when comparing titles
+    #[arg(long)]
+    ignore_punctuation: bool,
+
+    /// Also match titles in Hangul or kana to their romanization (e.g. `사랑해` and `Saranghae`)
+    /// with `--fuzzy-threshold`
+    #[arg(long)]
+    transliterate: bool,
+
+    /// Warn about matched tracks whose durations differ by more than this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = matching::DEFAULT_DURATION_TOLERANCE.as_secs())]
+    duration_tolerance: u64,
+}
+
+impl MatchArgs {
+    fn into_options(
+        self,
+        mappings: Vec<matching::TrackMapping>,
+        disc_mappings: Vec<matching::DiscMapping>,
+    ) -> Result<matching::MatchOptions> {
+        Ok(matching::MatchOptions {
+            mappings,
+            disc_mappings,
+            rewrite_rules: config::load()?.compile_rewrite_rules()?,
+            fuzzy_threshold: self.fuzzy_threshold,
+            ignore_punctuation: self.ignore_punctuation,
+            transliterate: self.transliterate,
+            duration_tolerance: Duration::from_secs(self.duration_tolerance),
+            ..matching::MatchOptions::default()
+        })
+    }
+}
+
+fn parse_fuzzy_threshold(value: &str) -> Result<f64> {
+    let threshold: f64 = value.parse()?;
+    ensure!(
+        (0.0..=1.0).contains(&threshold),
+        "must be between 0.0 and 1.0",
+    );
+    Ok(threshold)
+}
+
+/// Checks the storefront and lists the known ones for shell completions
+#[derive(Clone)]
+struct StorefrontParser;
+
+impl clap::builder::TypedValueParser for StorefrontParser {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<String, clap::Error> {
+        clap::builder::StringValueParser::new()
+            .try_map(|storefront| {
+                apple_music::check_storefront(&storefront)?;
+                anyhow::Ok(storefront)
+            })
+            .parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(
+        &self,
+    ) -> Option<Box<dyn Iterator<Item = clap::builder::PossibleValue> + '_>> {
+        Some(Box::new(
+            apple_music::STOREFRONTS
+                .iter()
+                .map(clap::builder::PossibleValue::new),
+        ))
+    }
+}
+
+fn parse_header(value: &str) -> Result<(HeaderName, HeaderValue)> {
+    let (name, value) = value
+        .split_once(':')
+        .context("must be in the form `Name: value`")?;
+    Ok((name.trim().parse()?, value.trim().parse()?))
+}
+
+/// An album ID or music.apple.com link, or an alias for one in the config file
+fn parse_album_id(value: &str) -> Result<apple_music::AlbumId> {
+    let config = config::load()?;
+    let value = config.album_alias(value).unwrap_or(value);
+    if let Ok(album_id) = value.parse() {
+        return Ok(album_id);
+    }
+    match value.parse::<batch::AlbumLink>() {
+        Ok(batch::AlbumLink(album_id)) => Ok(album_id),
+        Err(_) => bail!(
+            "invalid album `{value}`, expected a library ID (starting with `l.`), a catalog ID, a \
+             music.apple.com link or an alias from the config file"
+        ),
+    }
+}
+
+fn parse_catalog_album_id(value: &str) -> Result<apple_music::CatalogAlbumId> {
+    match parse_album_id(value)? {
+        apple_music::AlbumId::Catalog(catalog_id) => Ok(catalog_id),
+        apple_music::AlbumId::Library(library_id) => {
+            bail!("`{library_id}` is a library album, expected a catalog album")
+        }
+    }
 }
 
 #[derive(ValueEnum, Clone, Copy)]
@@ -61,211 +894,508 @@ enum Host {
     AmpApi,
 }
 
+impl Host {
+    fn base_url(self) -> &'static str {
+        match self {
+            Host::AmpApi => apple_music::DEFAULT_BASE_URL,
+        }
+    }
+}
+
+/// Exit code of a `migrate --dry-run` that found no tracks in the library to migrate, distinct
+/// from 1 for errors and 2 for invalid arguments
+const NOTHING_TO_MIGRATE: u8 = 3;
+
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let cli = Cli::parse();
+    output::set_ascii(cli.ascii);
+    interrupt::install();
 
-    match cli.command {
+    let notify_after = cli.command.notify_after();
+    let started = Instant::now();
+    let result = telemetry::span("run").result(run(cli.command).await);
+    telemetry::export().await;
+    if cli.stats {
+        stats::print(started);
+    }
+    if let Some(notify_after) = notify_after
+        && started.elapsed() >= notify_after
+    {
+        notify::finished(started.elapsed(), &result);
+    }
+    result
+}
+
+async fn run(command: Commands) -> Result<ExitCode> {
+    match command {
         Commands::Migrate {
-            developer_token,
-            origin_header,
-            user_token,
-            host: _,
-            storefront,
+            api,
             dry_run,
-            source_album_library_id,
-            destination_album_catalog_id,
+            force,
+            fail_on_unmatched,
+            upgrade_to_explicit,
+            summary_only,
+            show_ids,
+            yes,
+            detect_direction,
+            mappings,
+            disc_mappings,
+            matching,
+            musicbrainz,
+            check_rotation,
+            report: report_path,
+            song_links,
+            no_favorite,
+            playlist,
+            notify_after: _,
+            mut source_album_id,
+            mut destination_album_catalog_ids,
         } => {
             ensure!(
-                apple_music::validate_developer_token(&developer_token),
-                "invalid developer token",
-            );
-            ensure!(
-                apple_music::validate_storefront(&storefront),
-                "invalid storefront",
-            );
-            ensure!(
-                apple_music::validate_library_album_id(&source_album_library_id),
-                "invalid source album library ID",
-            );
-            ensure!(
-                apple_music::validate_catalog_id(&destination_album_catalog_id),
-                "invalid destination album catalog ID",
+                !(musicbrainz && api.offline),
+                "`--musicbrainz` needs network access",
             );
 
-            let client =
-                apple_music::Client::new(&developer_token, origin_header, user_token, storefront)?;
-            let source_album = {
-                let library_album = client.get_library_album(&source_album_library_id).await?;
-                ensure!(library_album.library_id()? == source_album_library_id);
-                let catalog_album = client
-                    .get_catalog_album(library_album.catalog_id()?)
-                    .await?;
-                let album: custom_types::Album<custom_types::TrackNoLibrary> =
-                    catalog_album.try_into()?;
-                album.with_library_info(&library_album)?
+            let client = api.into_client()?;
+            let _lock = if dry_run {
+                None
+            } else {
+                lock::acquire(&client)?
             };
-            let destination_album: custom_types::Album<custom_types::TrackNoLibrary> = client
-                .get_catalog_album(&destination_album_catalog_id)
-                .await?
-                .try_into()?;
-            ensure!(destination_album.catalog_id == destination_album_catalog_id);
-            ensure!(
-                source_album.catalog_id != destination_album.catalog_id,
-                "source and destination albums are the same",
-            );
-
-            let matches = matching::match_tracks(&source_album, &destination_album)?;
-
-            if dry_run {
-                println!(
-                    "Source: \"{}\" by {} ({}, {} tracks)",
-                    source_album.name,
-                    source_album.artist_name,
-                    source_album.release_date,
-                    source_album.tracks.len(),
-                );
-                println!(
-                    "Destination: \"{}\" by {} ({}, {} tracks)",
-                    destination_album.name,
-                    destination_album.artist_name,
-                    destination_album.release_date,
-                    destination_album.tracks.len(),
-                );
-                println!();
-
-                let mut matched = Vec::new();
-                let mut unmatched = Vec::new();
-
-                for result in &matches {
-                    match result {
-                        matching::TrackMatchResult::Match {
-                            source,
-                            destination,
-                        } => {
-                            if source.library_id.is_none() {
-                                continue;
-                            }
-                            let src_num = source_album
-                                .tracks
-                                .iter()
-                                .position(|t| t.catalog_id == source.catalog_id)
-                                .unwrap()
-                                + 1;
-                            let dst_num = destination_album
-                                .tracks
-                                .iter()
-                                .position(|t| t.catalog_id == destination.catalog_id)
-                                .unwrap()
-                                + 1;
-                            matched.push((src_num, source, dst_num, destination));
-                        }
-                        matching::TrackMatchResult::NoMatch { source } => {
-                            if source.library_id.is_none() {
-                                continue;
-                            }
-                            let src_num = source_album
-                                .tracks
-                                .iter()
-                                .position(|t| t.catalog_id == source.catalog_id)
-                                .unwrap()
-                                + 1;
-                            unmatched.push((src_num, *source));
-                        }
+            if detect_direction {
+                let apple_music::AlbumId::Catalog(source_catalog_id) = &source_album_id else {
+                    bail!("`--detect-direction` needs a catalog ID for the source album");
+                };
+                let [destination_album_catalog_id] = destination_album_catalog_ids.as_mut_slice()
+                else {
+                    bail!("`--detect-direction` needs a single destination album");
+                };
+                if migrate::should_swap(&client, source_catalog_id, destination_album_catalog_id)
+                    .await?
+                {
+                    if dry_run || yes {
+                        println!(
+                            "Migrating from {destination_album_catalog_id} to {source_catalog_id} instead"
+                        );
+                        println!();
+                    } else {
+                        let prompt = format!(
+                            "Migrate from {destination_album_catalog_id} to {source_catalog_id} instead?",
+                        );
+                        ensure!(migrate::confirm(&prompt).await?, "cancelled");
                     }
+                    let source_catalog_id = source_catalog_id.clone();
+                    source_album_id = apple_music::AlbumId::Catalog(std::mem::replace(
+                        destination_album_catalog_id,
+                        source_catalog_id,
+                    ));
                 }
-
-                if !matched.is_empty() {
-                    println!("Matched tracks:");
-                    for (src_num, source, dst_num, destination) in &matched {
-                        let both_explicit = source.is_explicit && destination.is_explicit;
-                        let src_explicit = if source.is_explicit && !both_explicit {
-                            " [E]"
-                        } else {
-                            ""
-                        };
-                        let dst_explicit = if destination.is_explicit && !both_explicit {
-                            " [E]"
-                        } else {
-                            ""
-                        };
-                        if source.name == destination.name
-                            && source.artist_name == destination.artist_name
-                        {
-                            println!(
-                                "  #{src_num}{src_explicit} \u{2192} #{dst_num}{dst_explicit} {}",
-                                source.name,
-                            );
-                        } else {
-                            println!(
-                                "  #{src_num} {}{src_explicit} \u{2192} #{dst_num} {}{dst_explicit}",
-                                source.name, destination.name,
-                            );
-                        }
+            }
+            // Dry and offline runs can't finish a migration, so they don't need the history
+            if !dry_run
+                && !client.is_offline()
+                && let Some((mut history, pending)) = migrate::find_pending(
+                    &source_album_id.to_string(),
+                    &destination_album_catalog_ids,
+                )
+            {
+                if migrate::in_library(&client, &pending.source_catalog_id.parse()?).await? {
+                    // Removing the source failed, so the library wasn't changed
+                    if let Err(error) = history.finish(&pending) {
+                        eprintln!("Warning: failed to clear the unfinished migration: {error:#}");
                     }
+                } else {
+                    migrate::resume(&client, history, &pending).await?;
+                    return Ok(ExitCode::SUCCESS);
                 }
+            }
+            if dry_run
+                && let apple_music::AlbumId::Catalog(catalog_id) = &source_album_id
+                && migrate::find_library_album(&client, catalog_id)
+                    .await?
+                    .is_none()
+            {
+                println!("Album {catalog_id} isn't in the library, nothing to migrate");
+                return Ok(ExitCode::from(NOTHING_TO_MIGRATE));
+            }
+            let source_album_library_id =
+                migrate::resolve_library_id(&client, &source_album_id).await?;
+            let source_album = telemetry::stage("load source album")
+                .result(migrate::load_source(&client, &source_album_library_id).await)?;
+            let mut destination_albums = Vec::new();
+            let mut destination_in_library = HashSet::new();
+            for catalog_id in &destination_album_catalog_ids {
+                let destination_album = telemetry::stage("load destination album")
+                    .result(migrate::load_destination(&client, catalog_id).await)?;
+                ensure!(
+                    source_album.catalog_id != destination_album.catalog_id,
+                    "source and destination albums are the same",
+                );
+                destination_in_library.extend(
+                    migrate::destination_tracks_in_library(&client, &destination_album).await?,
+                );
+                destination_albums.push(destination_album);
+            }
+            let destination_album = migrate::combine_destinations(destination_albums)?;
+            let destination_fully_in_library = destination_album
+                .tracks
+                .iter()
+                .all(|track| destination_in_library.contains(&track.catalog_id));
+            if destination_fully_in_library {
+                println!(
+                    "The destination album is already fully in the library, only the source album will be removed"
+                );
+                println!();
+            }
 
-                if !unmatched.is_empty() {
-                    if !matched.is_empty() {
-                        println!();
-                    }
-                    println!("Unmatched tracks (in library, no match in destination):");
-                    for (src_num, source) in &unmatched {
-                        let src_explicit = if source.is_explicit { " [E]" } else { "" };
-                        println!("  #{src_num} {}{src_explicit}", source.name);
-                    }
+            if check_rotation {
+                let activity =
+                    migrate::listening_activity(&client, &source_album_library_id, &source_album)
+                        .await?;
+                if !activity.is_empty() {
+                    println!(
+                        "Warning: \"{}\" is in your {}, migrating resets its play history and \
+                         where it is in the queue",
+                        source_album.name,
+                        activity.join(" and "),
+                    );
+                    println!();
                 }
+            }
 
-                if matched.is_empty() && unmatched.is_empty() {
-                    println!("No tracks in the library to migrate.");
+            if matching::looks_unrelated(&source_album, &destination_album) {
+                let message =
+                    "source and destination albums have different UPCs and no recordings in common";
+                if dry_run {
+                    println!("Warning: {message}");
+                    println!();
+                } else {
+                    ensure!(force, "{message}, pass `--force` to migrate anyway");
                 }
+            }
 
-                return Ok(());
+            let mut options = matching.into_options(mappings, disc_mappings)?;
+            let match_span = telemetry::stage("match tracks");
+            let mut report = matching::match_tracks(&source_album, &destination_album, &options)?;
+            if musicbrainz {
+                let musicbrainz = musicbrainz::Client::new()?;
+                options.musicbrainz_matches =
+                    musicbrainz::find_matches(&musicbrainz, &client, &report).await?;
+                report = matching::match_tracks(&source_album, &destination_album, &options)?;
             }
+            match_span.end();
 
-            let songs_to_add: Vec<&str> = matches
-                .iter()
-                .filter_map(|result| match result {
-                    matching::TrackMatchResult::Match {
-                        source,
-                        destination,
-                    } if source.library_id.is_some() => Some(destination.catalog_id.as_str()),
-                    _ => None,
-                })
-                .collect();
-
-            ensure!(!songs_to_add.is_empty(), "no tracks to migrate");
-
-            println!("Before:");
-            for (i, track) in source_album.tracks.iter().enumerate() {
-                let in_library = if track.library_id.is_some() {
-                    " [in library]"
+            let unplayable = report.unplayable_songs_to_add();
+            if !unplayable.is_empty() {
+                let names: Vec<String> = unplayable
+                    .iter()
+                    .map(|track| format!("\"{}\" ({})", track.name, track.catalog_id))
+                    .collect();
+                let message = format!(
+                    "{} matched destination tracks can't be played in the `{}` storefront and may \
+                     not be added to the library: {}",
+                    unplayable.len(),
+                    client.storefront(),
+                    names.join(", "),
+                );
+                if dry_run {
+                    println!("Warning: {message}");
+                    println!();
                 } else {
-                    ""
-                };
-                println!("  #{} {}{in_library}", i + 1, track.name);
+                    ensure!(force, "{message}, pass `--force` to migrate anyway");
+                }
             }
 
-            client
-                .remove_album_from_library(&source_album_library_id)
+            if dry_run {
+                migrate::print_plan(
+                    &source_album,
+                    &destination_album,
+                    &report,
+                    &destination_in_library,
+                    summary_only,
+                    show_ids,
+                );
+            }
+            if fail_on_unmatched {
+                migrate::ensure_all_matched(&report)?;
+            }
+            if upgrade_to_explicit {
+                migrate::ensure_all_explicit(&report)?;
+            }
+            if !dry_run {
+                if destination_fully_in_library && !yes {
+                    ensure!(
+                        migrate::confirm("Remove the source album from the library?").await?,
+                        "cancelled",
+                    );
+                }
+                migrate::execute(
+                    &client,
+                    &source_album_library_id,
+                    &source_album,
+                    &destination_album,
+                    &report,
+                    &destination_in_library,
+                    &destination_album_catalog_ids,
+                    summary_only,
+                    !no_favorite,
+                )
                 .await?;
+            }
 
-            client.add_songs_to_library(&songs_to_add).await?;
-
-            println!();
-            println!("After:");
-            for (i, track) in destination_album.tracks.iter().enumerate() {
-                let added = if songs_to_add.contains(&track.catalog_id.as_str()) {
-                    " [added]"
-                } else {
-                    ""
-                };
-                println!("  #{} {}{added}", i + 1, track.name);
+            if let Some(report_path) = report_path {
+                let report = report::build(
+                    &source_album,
+                    &destination_album,
+                    &report,
+                    &options,
+                    dry_run,
+                    song_links,
+                );
+                report::write(&report, &report_path)?;
+            }
+            if let Some(playlist) = playlist {
+                m3u::write(&report, client.storefront(), &playlist)?;
+            }
+            if dry_run && report.songs_to_add().is_empty() {
+                return Ok(ExitCode::from(NOTHING_TO_MIGRATE));
+            }
+        }
+        Commands::MigrateArtist {
+            api,
+            dry_run,
+            fail_on_unmatched,
+            summary_only,
+            matching,
+            prefer_adm,
+            no_favorite,
+            progress_json,
+            notify_after: _,
+            artist_name,
+        } => {
+            let progress = match progress_json {
+                Some(path) => progress::Progress::open(&path)?,
+                None => progress::Progress::disabled(),
+            };
+            let client = api.into_client()?;
+            let _lock = if dry_run {
+                None
+            } else {
+                lock::acquire(&client)?
+            };
+            let options = matching::MatchOptions {
+                prefer_apple_digital_master: prefer_adm,
+                ..matching.into_options(Vec::new(), Vec::new())?
+            };
+            migrate::migrate_artist(
+                &client,
+                &artist_name,
+                &options,
+                dry_run,
+                fail_on_unmatched,
+                summary_only,
+                !no_favorite,
+                &progress,
+            )
+            .await?;
+        }
+        Commands::Batch {
+            command:
+                BatchCommands::Build {
+                    api,
+                    matching,
+                    prefer_adm,
+                    concurrency,
+                    notify_after: _,
+                    links,
+                    output,
+                },
+        } => {
+            let client = api.into_client()?;
+            let options = matching::MatchOptions {
+                prefer_apple_digital_master: prefer_adm,
+                ..matching.into_options(Vec::new(), Vec::new())?
+            };
+            batch::build(&client, &links, &output, &options, concurrency.into()).await?;
+        }
+        Commands::Batch {
+            command: BatchCommands::Check { api, batch },
+        } => {
+            let client = api.into_client()?;
+            batch::check(&client, &batch).await?;
+        }
+        Commands::Album {
+            command: AlbumCommands::Info { api, catalog_id },
+        } => {
+            let client = api.into_client()?;
+            album::info(&client, &catalog_id).await?;
+        }
+        Commands::Album {
+            command:
+                AlbumCommands::Editions {
+                    api,
+                    artist,
+                    title,
+                    catalog_id,
+                },
+        } => {
+            let query = match (catalog_id, artist, title) {
+                (Some(catalog_id), _, _) => album::Editions::Of(catalog_id),
+                (None, Some(artist_name), Some(name)) => {
+                    album::Editions::Search { artist_name, name }
+                }
+                _ => bail!("expected a catalog ID or `--artist` and `--title`"),
+            };
+            let client = api.into_client()?;
+            album::editions(&client, query).await?;
+        }
+        Commands::CompareStorefronts {
+            api,
+            storefronts,
+            catalog_id,
+        } => {
+            let client = api.into_client()?;
+            storefronts::compare(&client, &catalog_id, &storefronts).await?;
+        }
+        Commands::Library {
+            command: LibraryCommands::Export { api, output },
+        } => {
+            let client = api.into_client()?;
+            library::export(&client, &output).await?;
+        }
+        Commands::Library {
+            command:
+                LibraryCommands::Import {
+                    api,
+                    dry_run,
+                    input,
+                },
+        } => {
+            let client = api.into_client()?;
+            let _lock = if dry_run {
+                None
+            } else {
+                lock::acquire(&client)?
+            };
+            library::import(&client, &input, dry_run).await?;
+        }
+        Commands::Library {
+            command:
+                LibraryCommands::ImportIsrcs {
+                    api,
+                    dry_run,
+                    input,
+                },
+        } => {
+            let client = api.into_client()?;
+            let _lock = if dry_run {
+                None
+            } else {
+                lock::acquire(&client)?
+            };
+            library::import_isrcs(&client, &input, dry_run).await?;
+        }
+        Commands::Local {
+            command: LocalCommands::Scan { api, library_xml },
+        } => {
+            let client = api.into_client()?;
+            local::scan(&client, &library_xml).await?;
+        }
+        Commands::Local {
+            command:
+                LocalCommands::MatchFiles {
+                    api,
+                    matching,
+                    folder,
+                    catalog_id,
+                },
+        } => {
+            let client = api.into_client()?;
+            let options = matching.into_options(Vec::new(), Vec::new())?;
+            local::match_files(&client, &folder, &catalog_id, &options).await?;
+        }
+        Commands::Playlist {
+            command: PlaylistCommands::Audit { api },
+        } => {
+            let client = api.into_client()?;
+            playlist::audit(&client).await?;
+        }
+        Commands::Auth {
+            developer_token,
+            port,
+        } => {
+            ensure!(
+                apple_music::validate_developer_token(&developer_token),
+                "invalid developer token (run `token inspect` to examine it)",
+            );
+            let user_token = auth::authorize(&developer_token, port).await?;
+            let mut credentials = credentials::load()?;
+            credentials.user_token = Some(user_token);
+            let path = credentials::save(&credentials)?;
+            println!("Stored the Media User Token in {}", path.display());
+        }
+        Commands::Whoami { api } => {
+            let client = api.into_client()?;
+            account::whoami(&client).await?;
+        }
+        Commands::History {
+            command:
+                HistoryCommands::List {
+                    artist,
+                    album,
+                    limit,
+                },
+        } => {
+            let history = history::History::open()?;
+            history::print_list(&history.list(&history::Filter {
+                artist_name: artist,
+                album_name: album,
+                limit,
+            })?);
+        }
+        Commands::History {
+            command: HistoryCommands::Show { id },
+        } => {
+            let history = history::History::open()?;
+            let (migration, tracks) = history
+                .get(id)?
+                .with_context(|| format!("no migration #{id} in the history"))?;
+            history::print_details(&migration, &tracks);
+        }
+        Commands::Cache {
+            command: CacheCommands::Stats,
+        } => {
+            cache::print_stats()?;
+        }
+        Commands::Cache {
+            command: CacheCommands::Clear { storefront, album },
+        } => {
+            if let Some(storefront) = &storefront {
+                apple_music::check_storefront(storefront)?;
             }
+            cache::clear(&apple_music::cache::Filter {
+                storefront,
+                album_id: album,
+            })?;
+        }
+        Commands::Token {
+            command:
+                TokenCommands::Inspect {
+                    developer_token,
+                    user_token,
+                },
+        } => {
+            token::inspect(&developer_token, user_token.as_deref())?;
         }
         Commands::Completions { shell } => {
             shell.generate(&mut Cli::command(), &mut std::io::stdout());
         }
+        Commands::Man { output_dir } => {
+            clap_mangen::generate_to(Cli::command(), &output_dir).with_context(|| {
+                format!("failed to write man pages to {}", output_dir.display())
+            })?;
+        }
     }
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
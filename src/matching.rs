@@ -1,829 +1,3253 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::LazyLock,
+    time::Duration,
+};
 
-use anyhow::{Result, ensure};
+use anyhow::{Context, Result, bail, ensure};
 
-use crate::apple_music::custom_types::{Album, TrackNoLibrary, TrackWithLibrary};
+use crate::{
+    apple_music::{
+        CatalogSongId,
+        custom_types::{Album, ContentRating, TrackNoLibrary, TrackWithLibrary},
+    },
+    output, transliterate,
+};
+
+/// Title similarity used when `--fuzzy-threshold` isn't given
+pub const DEFAULT_FUZZY_THRESHOLD: f64 = 0.9;
+
+/// Duration difference used when `--duration-tolerance` isn't given
+pub const DEFAULT_DURATION_TOLERANCE: Duration = Duration::from_secs(10);
+
+/// Fuzzy match scores are the similarity in thousandths
+const FUZZY_SCALE: f64 = 1000.0;
+
+/// Which names a rewrite rule applies to
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RewriteField {
+    Title,
+    Artist,
+    #[default]
+    Both,
+}
 
+/// Rewrites titles or artist names before comparing them, for catalogs with systematic naming
+/// quirks
+pub struct RewriteRule {
+    pub field: RewriteField,
+    pub pattern: regex::Regex,
+    /// Can refer to capture groups as `$1`
+    pub replacement: String,
+}
+
+impl RewriteRule {
+    /// Only allocates if the pattern matches
+    pub fn apply<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        self.pattern.replace_all(value, self.replacement.as_str())
+    }
+}
+
+fn rewrite<'a>(rules: &[RewriteRule], field: RewriteField, value: &'a str) -> Cow<'a, str> {
+    let mut value = Cow::Borrowed(value);
+    for rule in rules
+        .iter()
+        .filter(|rule| rule.field == field || rule.field == RewriteField::Both)
+    {
+        if let Cow::Owned(rewritten) = rule.apply(&value) {
+            value = Cow::Owned(rewritten);
+        }
+    }
+    value
+}
+
+/// Joins between the artists of a multi-artist name, which editions often format differently
+/// (e.g. `A & B` and `A, B`, or `A feat. B` and `A ft. B`)
+static ARTIST_CONNECTIVE: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"(?i)\s*,\s+|\s+(?:&|and|feat\.?|ft\.?|featuring)\s+").unwrap()
+});
+
+/// Every connective replaced by `, `, only allocates if the name has one
+fn normalize_artist(artist_name: Cow<'_, str>) -> Cow<'_, str> {
+    if !ARTIST_CONNECTIVE.is_match(&artist_name) {
+        return artist_name;
+    }
+    Cow::Owned(
+        ARTIST_CONNECTIVE
+            .replace_all(&artist_name, ", ")
+            .into_owned(),
+    )
+}
+
+/// Punctuation and symbols removed and whitespace collapsed, so that `Don't Stop - Live` and
+/// `Don’t Stop – Live` compare equal. Titles with nothing else are kept as they are.
+fn strip_punctuation(title: Cow<'_, str>) -> Cow<'_, str> {
+    let stripped = title
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if stripped.is_empty() || stripped == title {
+        title
+    } else {
+        Cow::Owned(stripped)
+    }
+}
+
+pub struct MatchOptions {
+    pub mappings: Vec<TrackMapping>,
+    /// Mapped source discs only match tracks on the destination discs they're mapped to, and those
+    /// destination discs only match tracks from the source discs mapped to them
+    pub disc_mappings: Vec<DiscMapping>,
+    pub rewrite_rules: Vec<RewriteRule>,
+    /// Minimum title similarity (0.0 to 1.0) for tracks by the same artist to match when their
+    /// titles differ, 1.0 only allows differences in case, whitespace and abbreviations or
+    /// numerals like `Pt. II` and `Part 2`
+    pub fuzzy_threshold: f64,
+    /// Compare titles without punctuation, symbols and repeated whitespace, so that `Don't Stop -
+    /// Live` and `Don’t Stop – Live` are equal
+    pub ignore_punctuation: bool,
+    /// Also fuzzy match titles in Hangul or kana against their romanization
+    pub transliterate: bool,
+    /// How much the durations of matched tracks can differ before the match gets a warning
+    pub duration_tolerance: Duration,
+    /// Catalog IDs of songs with an ISRC that MusicBrainz has for the same recording, by source
+    /// track catalog ID, only used for source tracks without a direct ISRC match
+    pub musicbrainz_matches: HashMap<CatalogSongId, HashSet<CatalogSongId>>,
+    /// When picking the edition to migrate to, rank Apple Digital Masters above the others
+    pub prefer_apple_digital_master: bool,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            mappings: Vec::new(),
+            disc_mappings: Vec::new(),
+            rewrite_rules: Vec::new(),
+            fuzzy_threshold: DEFAULT_FUZZY_THRESHOLD,
+            ignore_punctuation: false,
+            transliterate: false,
+            duration_tolerance: DEFAULT_DURATION_TOLERANCE,
+            musicbrainz_matches: HashMap::new(),
+            prefer_apple_digital_master: false,
+        }
+    }
+}
+
+/// Names after applying the rewrite rules, optionally stripping punctuation from titles and
+/// normalizing artist connectives, as (title, artist)
+pub fn normalized_names<'a>(
+    options: &MatchOptions,
+    name: &'a str,
+    artist_name: &'a str,
+) -> (Cow<'a, str>, Cow<'a, str>) {
+    let name = rewrite(&options.rewrite_rules, RewriteField::Title, name);
+    (
+        if options.ignore_punctuation {
+            strip_punctuation(name)
+        } else {
+            name
+        },
+        normalize_artist(rewrite(
+            &options.rewrite_rules,
+            RewriteField::Artist,
+            artist_name,
+        )),
+    )
+}
+
+/// What a match is based on
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MatchMethod {
+    Isrc,
+    /// An alternate ISRC of the recording found on MusicBrainz
+    MusicBrainz,
+    NameArtist,
+    /// Same artist and a similar title
+    Fuzzy {
+        similarity_percent: u8,
+    },
+    /// Given with `--map`
+    Manual,
+}
+
+impl std::fmt::Display for MatchMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&match self {
+            MatchMethod::Isrc => "ISRC".to_owned(),
+            MatchMethod::MusicBrainz => "ISRC from MusicBrainz".to_owned(),
+            MatchMethod::NameArtist => "name and artist".to_owned(),
+            MatchMethod::Fuzzy { similarity_percent } => {
+                format!("{similarity_percent}% similar name")
+            }
+            MatchMethod::Manual => "manual mapping".to_owned(),
+        })
+    }
+}
+
+/// Something worth checking about a match before migrating
 #[derive(Debug, PartialEq, Eq)]
-pub enum TrackMatchResult<'a> {
+pub enum MatchWarning {
+    ContentRatingChanged {
+        source: Option<ContentRating>,
+        destination: Option<ContentRating>,
+    },
+    DurationChanged {
+        source: Duration,
+        destination: Duration,
+    },
+}
+
+/// `m:ss`
+fn format_track_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+impl std::fmt::Display for MatchWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn describe(content_rating: &Option<ContentRating>) -> &str {
+            content_rating
+                .as_ref()
+                .map_or("unrated", ContentRating::as_str)
+        }
+        match self {
+            MatchWarning::ContentRatingChanged {
+                source,
+                destination,
+            } => write!(
+                f,
+                "content rating changes from {} to {}",
+                describe(source),
+                describe(destination),
+            ),
+            MatchWarning::DurationChanged {
+                source,
+                destination,
+            } => write!(
+                f,
+                "duration changes from {} to {}",
+                format_track_duration(*source),
+                format_track_duration(*destination),
+            ),
+        }
+    }
+}
+
+/// What matching needs from a track, so that tracks from anywhere can be matched and not only
+/// the ones on catalog albums
+pub trait MatchableTrack {
+    fn name(&self) -> &str;
+    fn artist_name(&self) -> &str;
+    fn isrc(&self) -> Option<&str>;
+    fn duration(&self) -> Option<Duration>;
+    /// Only used for `--map`, `--map-disc` and describing tracks, 1 if the tracks have no discs
+    fn disc_number(&self) -> u8;
+    fn track_number(&self) -> u8;
+    /// Only used for MusicBrainz matches and checking for duplicates
+    fn catalog_id(&self) -> Option<&CatalogSongId>;
+    /// Only used for warnings
+    fn content_rating(&self) -> Option<&ContentRating>;
+}
+
+impl MatchableTrack for TrackWithLibrary {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn artist_name(&self) -> &str {
+        &self.artist_name
+    }
+
+    fn isrc(&self) -> Option<&str> {
+        self.isrc.as_deref()
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    fn disc_number(&self) -> u8 {
+        self.disc_number
+    }
+
+    fn track_number(&self) -> u8 {
+        self.track_number
+    }
+
+    fn catalog_id(&self) -> Option<&CatalogSongId> {
+        Some(&self.catalog_id)
+    }
+
+    fn content_rating(&self) -> Option<&ContentRating> {
+        self.content_rating.as_ref()
+    }
+}
+
+impl MatchableTrack for TrackNoLibrary {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn artist_name(&self) -> &str {
+        &self.artist_name
+    }
+
+    fn isrc(&self) -> Option<&str> {
+        self.isrc.as_deref()
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    fn disc_number(&self) -> u8 {
+        self.disc_number
+    }
+
+    fn track_number(&self) -> u8 {
+        self.track_number
+    }
+
+    fn catalog_id(&self) -> Option<&CatalogSongId> {
+        Some(&self.catalog_id)
+    }
+
+    fn content_rating(&self) -> Option<&ContentRating> {
+        self.content_rating.as_ref()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TrackMatchResult<'a, S = TrackWithLibrary, D = TrackNoLibrary> {
     Match {
-        source: &'a TrackWithLibrary,
-        destination: &'a TrackNoLibrary,
+        source: &'a S,
+        destination: &'a D,
+        method: MatchMethod,
+        /// Whether the rewrite rules changed the names that the match is based on
+        normalized: bool,
+        warnings: Vec<MatchWarning>,
     },
     NoMatch {
-        source: &'a TrackWithLibrary,
+        source: &'a S,
     },
 }
 
+/// The result for every source track, in source order
+#[derive(Debug, PartialEq, Eq)]
+pub struct MatchReport<'a, S = TrackWithLibrary, D = TrackNoLibrary> {
+    pub results: Vec<TrackMatchResult<'a, S, D>>,
+}
+
+impl<'a> MatchReport<'a> {
+    /// Source tracks that are in the library without a destination track
+    pub fn unmatched_in_library(&self) -> Vec<&'a TrackWithLibrary> {
+        self.results
+            .iter()
+            .filter_map(|result| match result {
+                TrackMatchResult::NoMatch { source } if source.library_id.is_some() => {
+                    Some(*source)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Catalog IDs of the destination tracks matched to source tracks that are in the library
+    pub fn songs_to_add(&self) -> Vec<&CatalogSongId> {
+        self.results
+            .iter()
+            .filter_map(|result| match result {
+                TrackMatchResult::Match {
+                    source,
+                    destination,
+                    ..
+                } if source.library_id.is_some() => Some(&destination.catalog_id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Destination tracks to add that can't be played in the storefront
+    pub fn unplayable_songs_to_add(&self) -> Vec<&TrackNoLibrary> {
+        self.results
+            .iter()
+            .filter_map(|result| match result {
+                TrackMatchResult::Match {
+                    source,
+                    destination,
+                    ..
+                } if source.library_id.is_some() && !destination.playable => Some(*destination),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn match_warnings(
+    source: &impl MatchableTrack,
+    destination: &impl MatchableTrack,
+    duration_tolerance: Duration,
+) -> Vec<MatchWarning> {
+    let mut warnings = Vec::new();
+    if source.content_rating() != destination.content_rating() {
+        warnings.push(MatchWarning::ContentRatingChanged {
+            source: source.content_rating().cloned(),
+            destination: destination.content_rating().cloned(),
+        });
+    }
+    if let (Some(source), Some(destination)) = (source.duration(), destination.duration())
+        && source.abs_diff(destination) > duration_tolerance
+    {
+        warnings.push(MatchWarning::DurationChanged {
+            source,
+            destination,
+        });
+    }
+    warnings
+}
+
+/// A track's position on its album, written as `3` for the first disc or `2-3` (disc 2, track 3)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TrackPosition {
+    pub disc_number: u8,
+    pub track_number: u8,
+}
+
+impl FromStr for TrackPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (disc_number, track_number) = match s.split_once('-') {
+            Some((disc_number, track_number)) => (disc_number.parse()?, track_number.parse()?),
+            None => (1, s.parse()?),
+        };
+        ensure!(
+            disc_number > 0 && track_number > 0,
+            "disc and track numbers start at 1",
+        );
+        Ok(Self {
+            disc_number,
+            track_number,
+        })
+    }
+}
+
+impl std::fmt::Display for TrackPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.disc_number == 1 {
+            write!(f, "{}", self.track_number)
+        } else {
+            write!(f, "{}-{}", self.disc_number, self.track_number)
+        }
+    }
+}
+
+/// A manual override matching a source track to a destination track, written as
+/// `SOURCE=DESTINATION`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TrackMapping {
+    pub source: TrackPosition,
+    pub destination: TrackPosition,
+}
+
+impl FromStr for TrackMapping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (source, destination) = s.split_once('=').context("expected `SOURCE=DESTINATION`")?;
+        Ok(Self {
+            source: source.parse().context("invalid source position")?,
+            destination: destination
+                .parse()
+                .context("invalid destination position")?,
+        })
+    }
+}
+
+/// A source disc whose tracks are on another disc of the destination, written as
+/// `SOURCE=DESTINATION`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DiscMapping {
+    pub source: u8,
+    pub destination: u8,
+}
+
+impl FromStr for DiscMapping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (source, destination) = s.split_once('=').context("expected `SOURCE=DESTINATION`")?;
+        let mapping = Self {
+            source: source.parse().context("invalid source disc")?,
+            destination: destination.parse().context("invalid destination disc")?,
+        };
+        ensure!(
+            mapping.source > 0 && mapping.destination > 0,
+            "disc numbers start at 1",
+        );
+        Ok(mapping)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UpcComparison {
+    Same,
+    Different,
+    /// At least one of the albums has no UPC
+    Unknown,
+}
+
+/// UPC-A and EAN-13 forms of the same code only differ in leading zeros
+fn normalize_upc(upc: &str) -> &str {
+    upc.trim_start_matches('0')
+}
+
+pub fn compare_upcs<S, D>(source: &Album<S>, destination: &Album<D>) -> UpcComparison {
+    match (&source.upc, &destination.upc) {
+        (Some(source), Some(destination))
+            if normalize_upc(source) == normalize_upc(destination) =>
+        {
+            UpcComparison::Same
+        }
+        (Some(_), Some(_)) => UpcComparison::Different,
+        _ => UpcComparison::Unknown,
+    }
+}
+
+/// Different editions of an album have different UPCs but share recordings, so albums with
+/// different UPCs and no ISRCs in common are most likely unrelated
+pub fn looks_unrelated(
+    source: &Album<TrackWithLibrary>,
+    destination: &Album<TrackNoLibrary>,
+) -> bool {
+    if compare_upcs(source, destination) != UpcComparison::Different {
+        return false;
+    }
+    let source_isrcs: HashSet<&str> = source
+        .tracks
+        .iter()
+        .filter_map(|t| t.isrc.as_deref())
+        .collect();
+    !destination
+        .tracks
+        .iter()
+        .filter_map(|t| t.isrc.as_deref())
+        .any(|isrc| source_isrcs.contains(isrc))
+}
+
+fn find_position(
+    mut positions: impl Iterator<Item = (u8, u8)>,
+    position: TrackPosition,
+) -> Option<usize> {
+    positions.position(|p| p == (position.disc_number, position.track_number))
+}
+
+/// `#3 "Name"` as shown in the dry run
+fn describe_track(name: &str, disc_number: u8, track_number: u8, multi_disc: bool) -> String {
+    format!(
+        "#{} \"{name}\"",
+        output::format_position(disc_number, track_number, multi_disc),
+    )
+}
+
+/// Gives up with `limit + 1` as soon as the distance is known to be more than `limit`
+fn levenshtein_distance(a: &[char], b: &[char], limit: usize) -> usize {
+    if a.len().abs_diff(b.len()) > limit {
+        return limit + 1;
+    }
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    let mut current_row = vec![0; b.len() + 1];
+    for (i, a_char) in a.iter().enumerate() {
+        current_row[0] = i + 1;
+        let mut row_min = current_row[0];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != b_char);
+            current_row[j + 1] = substitution
+                .min(previous_row[j + 1] + 1)
+                .min(current_row[j] + 1);
+            row_min = row_min.min(current_row[j + 1]);
+        }
+        if row_min > limit {
+            return limit + 1;
+        }
+        std::mem::swap(&mut previous_row, &mut current_row);
+    }
+    previous_row[b.len()]
+}
+
+/// Abbreviations that remasters often spell out, with what they're short for
+const TITLE_ABBREVIATIONS: &[(&str, &str)] = &[("pt", "part"), ("vol", "volume")];
+
+/// Roman numerals are only converted after these words, elsewhere `I` or `X` is more likely a
+/// word or a collaboration
+const NUMBERED_WORDS: &[&str] = &["part", "volume", "chapter", "act", "book", "movement"];
+
+/// I to XXXIX
+fn parse_roman_numeral(numeral: &str) -> Option<u32> {
+    const ONES: [&str; 10] = ["", "i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "ix"];
+    let tens = numeral.len() - numeral.trim_start_matches('x').len();
+    let ones = ONES.iter().position(|ones| *ones == &numeral[tens..])?;
+    let value = (tens * 10 + ones) as u32;
+    (tens <= 3 && value > 0).then_some(value)
+}
+
+/// Lowercase characters with whitespace collapsed, abbreviations spelled out and Roman numerals
+/// after words like `Part` converted, computed once per track for [`title_similarity`]
+fn simplify_title(title: &str) -> Vec<char> {
+    let mut simplified = Vec::with_capacity(title.len());
+    let mut previous_word = String::new();
+    for word in title.split_whitespace() {
+        if !simplified.is_empty() {
+            simplified.push(' ');
+        }
+        let word = word.to_lowercase();
+        // Surrounding punctuation like in `(Pt. II)` is kept around the replacement
+        let start = word.find(char::is_alphanumeric).unwrap_or(word.len());
+        let end = word
+            .rfind(char::is_alphanumeric)
+            .map_or(start, |i| i + word[i..].chars().next().unwrap().len_utf8());
+        let (prefix, core, mut suffix) = (&word[..start], &word[start..end], &word[end..]);
+        let replacement = if let Some((_, full)) = TITLE_ABBREVIATIONS
+            .iter()
+            .find(|(abbreviation, _)| *abbreviation == core)
+        {
+            suffix = suffix.strip_prefix('.').unwrap_or(suffix);
+            Some((*full).to_owned())
+        } else if NUMBERED_WORDS.contains(&previous_word.as_str()) {
+            parse_roman_numeral(core).map(|value| value.to_string())
+        } else {
+            None
+        };
+        let core = replacement.as_deref().unwrap_or(core);
+        simplified.extend(prefix.chars().chain(core.chars()).chain(suffix.chars()));
+        previous_word.clear();
+        previous_word.push_str(core);
+    }
+    simplified
+}
+
+struct RomanizedTitle {
+    /// Whether the title had any Hangul or kana
+    native: bool,
+    /// Lowercase letters and digits, since romanized titles split words inconsistently
+    simplified: Vec<char>,
+}
+
+fn romanized_title(title: &str) -> RomanizedTitle {
+    RomanizedTitle {
+        native: transliterate::has_romanizable(title),
+        simplified: transliterate::romanize(title)
+            .chars()
+            .flat_map(char::to_lowercase)
+            .filter(|c| c.is_alphanumeric())
+            .collect(),
+    }
+}
+
+/// 1.0 for titles that only differ in case, whitespace and the abbreviations and numerals handled
+/// by [`simplify_title`], down to 0.0 for completely different titles, based on the edit distance
+/// of the simplified titles. Similarities below `minimum` aren't computed exactly, only kept below
+/// it, so that the many pairs of unrelated titles are cheap.
+fn title_similarity(a: &[char], b: &[char], minimum: f64) -> f64 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    // Rounded up, the caller still compares the result against `minimum`
+    let limit = ((1.0 - minimum) * longest as f64).ceil() as usize;
+    1.0 - levenshtein_distance(a, b, limit) as f64 / longest as f64
+}
+
+/// Hungarian algorithm, returns the column assigned to each row so that the total score is as high
+/// as possible. Rows can't outnumber columns, each takes O(rows × columns) so a short source album
+/// against a long destination stays cheap.
+fn max_score_assignment(scores: &[Vec<i64>]) -> Vec<usize> {
+    let n = scores.len();
+    let m = scores.first().map_or(0, Vec::len);
+    debug_assert!(n <= m);
+    let max_score = scores.iter().flatten().copied().max().unwrap_or(0);
+    let cost = |row: usize, column: usize| max_score - scores[row][column];
+
+    // 1-indexed with 0 as a sentinel, `row_of[column]` is the row assigned to the column
+    let mut row_potential = vec![0; n + 1];
+    let mut column_potential = vec![0; m + 1];
+    let mut row_of = vec![0; m + 1];
+    let mut previous_column = vec![0; m + 1];
+    let mut min_slack = vec![i64::MAX; m + 1];
+    let mut visited = vec![false; m + 1];
+    for row in 1..=n {
+        row_of[0] = row;
+        let mut column = 0;
+        min_slack.fill(i64::MAX);
+        visited.fill(false);
+        loop {
+            visited[column] = true;
+            let current_row = row_of[column];
+            let mut delta = i64::MAX;
+            let mut next_column = 0;
+            for candidate in 1..=m {
+                if visited[candidate] {
+                    continue;
+                }
+                let slack = cost(current_row - 1, candidate - 1)
+                    - row_potential[current_row]
+                    - column_potential[candidate];
+                if slack < min_slack[candidate] {
+                    min_slack[candidate] = slack;
+                    previous_column[candidate] = column;
+                }
+                if min_slack[candidate] < delta {
+                    delta = min_slack[candidate];
+                    next_column = candidate;
+                }
+            }
+            for candidate in 0..=m {
+                if visited[candidate] {
+                    row_potential[row_of[candidate]] += delta;
+                    column_potential[candidate] -= delta;
+                } else {
+                    min_slack[candidate] -= delta;
+                }
+            }
+            column = next_column;
+            if row_of[column] == 0 {
+                break;
+            }
+        }
+        // Flip the augmenting path
+        while column != 0 {
+            let previous = previous_column[column];
+            row_of[column] = row_of[previous];
+            column = previous;
+        }
+    }
+
+    let mut assignment = vec![0; n];
+    for column in 1..=m {
+        if row_of[column] != 0 {
+            assignment[row_of[column] - 1] = column - 1;
+        }
+    }
+    assignment
+}
+
 pub fn match_tracks<'a>(
     source: &'a Album<TrackWithLibrary>,
     destination: &'a Album<TrackNoLibrary>,
-) -> Result<Vec<TrackMatchResult<'a>>> {
+    options: &MatchOptions,
+) -> Result<MatchReport<'a>> {
     ensure!(
         source.catalog_id != destination.catalog_id,
         "source and destination albums have the same catalog ID: {}",
         source.catalog_id,
     );
-    ensure!(!source.tracks.is_empty(), "source album has no tracks");
-    ensure!(
-        !destination.tracks.is_empty(),
-        "destination album has no tracks",
-    );
+    match_track_lists(&source.tracks, &destination.tracks, options)
+}
+
+/// Like [`match_tracks`] for any tracks, such as ones from a local library or another service
+pub fn match_track_lists<'a, S: MatchableTrack, D: MatchableTrack>(
+    source: &'a [S],
+    destination: &'a [D],
+    options: &MatchOptions,
+) -> Result<MatchReport<'a, S, D>> {
+    ensure!(!source.is_empty(), "source album has no tracks");
+    ensure!(!destination.is_empty(), "destination album has no tracks",);
+
+    // The destination checks build the indices used for matching, so they're only hashed once
+    let mut catalog_id_map: HashMap<&CatalogSongId, usize> =
+        HashMap::with_capacity(destination.len());
+    let mut isrc_map: HashMap<&str, usize> = HashMap::with_capacity(destination.len());
+    for (i, track) in destination.iter().enumerate() {
+        if let Some(catalog_id) = track.catalog_id() {
+            ensure!(
+                catalog_id_map.insert(catalog_id, i).is_none(),
+                "duplicate catalog ID in destination: {catalog_id}",
+            );
+        }
+        if let Some(isrc) = track.isrc() {
+            ensure!(
+                isrc_map.insert(isrc, i).is_none(),
+                "duplicate ISRC in destination: {isrc}",
+            );
+        }
+    }
+    {
+        let mut source_catalog_ids = HashSet::with_capacity(source.len());
+        let mut source_isrcs = HashSet::with_capacity(source.len());
+        for track in source {
+            if let Some(catalog_id) = track.catalog_id() {
+                ensure!(
+                    source_catalog_ids.insert(catalog_id),
+                    "duplicate catalog ID in source: {catalog_id}",
+                );
+            }
+            if let Some(isrc) = track.isrc() {
+                ensure!(
+                    source_isrcs.insert(isrc),
+                    "duplicate ISRC in source: {isrc}",
+                );
+            }
+        }
+        ensure!(
+            !source_catalog_ids
+                .iter()
+                .any(|catalog_id| catalog_id_map.contains_key(catalog_id)),
+            "source and destination albums have overlapping track catalog IDs",
+        );
+    }
+
+    let normalize = |name, artist_name| normalized_names(options, name, artist_name);
+    let source_names: Vec<(Cow<str>, Cow<str>)> = source
+        .iter()
+        .map(|t| normalize(t.name(), t.artist_name()))
+        .collect();
+    let destination_names: Vec<(Cow<str>, Cow<str>)> = destination
+        .iter()
+        .map(|t| normalize(t.name(), t.artist_name()))
+        .collect();
+    // Only needed for fuzzy matching, but simplifying once per track rather than once per pair
+    // keeps large albums cheap
+    let destination_titles: Vec<Vec<char>> = destination_names
+        .iter()
+        .map(|(name, _)| simplify_title(name))
+        .collect();
+    let romanize = |names: &[(Cow<str>, Cow<str>)]| -> Vec<RomanizedTitle> {
+        if options.transliterate {
+            names
+                .iter()
+                .map(|(name, _)| romanized_title(name))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    };
+    let source_romanized = romanize(&source_names);
+    let destination_romanized = romanize(&destination_names);
+
+    let mut name_artist_map: HashMap<(&str, &str), Vec<usize>> =
+        HashMap::with_capacity(destination.len());
+    for (i, (name, artist_name)) in destination_names.iter().enumerate() {
+        name_artist_map
+            .entry((name.as_ref(), artist_name.as_ref()))
+            .or_default()
+            .push(i);
+    }
+
+    let source_multi_disc = source.iter().any(|t| t.disc_number() > 1);
+    let destination_multi_disc = destination.iter().any(|t| t.disc_number() > 1);
+    let describe_source = |index: usize| {
+        let track = &source[index];
+        describe_track(
+            track.name(),
+            track.disc_number(),
+            track.track_number(),
+            source_multi_disc,
+        )
+    };
+    let describe_destination = |index: usize| {
+        let track = &destination[index];
+        describe_track(
+            track.name(),
+            track.disc_number(),
+            track.track_number(),
+            destination_multi_disc,
+        )
+    };
+
+    let mut mapped: HashMap<usize, usize> = HashMap::new();
+    for mapping in &options.mappings {
+        let source_index = find_position(
+            source.iter().map(|t| (t.disc_number(), t.track_number())),
+            mapping.source,
+        )
+        .with_context(|| format!("no source track at position {}", mapping.source))?;
+        let destination_index = find_position(
+            destination
+                .iter()
+                .map(|t| (t.disc_number(), t.track_number())),
+            mapping.destination,
+        )
+        .with_context(|| format!("no destination track at position {}", mapping.destination))?;
+        ensure!(
+            mapped.insert(source_index, destination_index).is_none(),
+            "source track {} is mapped more than once",
+            describe_source(source_index),
+        );
+    }
+    {
+        let mut mapped_destinations = HashSet::new();
+        for &destination_index in mapped.values() {
+            ensure!(
+                mapped_destinations.insert(destination_index),
+                "destination track {} is mapped more than once",
+                describe_destination(destination_index),
+            );
+        }
+    }
+
+    for mapping in &options.disc_mappings {
+        ensure!(
+            source.iter().any(|t| t.disc_number() == mapping.source),
+            "source album has no disc {}",
+            mapping.source,
+        );
+        ensure!(
+            destination
+                .iter()
+                .any(|t| t.disc_number() == mapping.destination),
+            "destination album has no disc {}",
+            mapping.destination,
+        );
+    }
+    let allowed = |source_index: usize, destination_index: usize| {
+        let source_disc = source[source_index].disc_number();
+        let destination_disc = destination[destination_index].disc_number();
+        let mut constrained = false;
+        for mapping in &options.disc_mappings {
+            if mapping.source == source_disc || mapping.destination == destination_disc {
+                if mapping.source == source_disc && mapping.destination == destination_disc {
+                    return true;
+                }
+                constrained = true;
+            }
+        }
+        !constrained
+    };
+
+    // An ISRC match outweighs any number of name matches, which outweigh any number of fuzzy
+    // matches, so the assignment only gives up a stronger match if doing so doesn't lose another one
+    let name_artist_score = (source.len() as i64 + 1) * (FUZZY_SCALE as i64 + 1);
+    let isrc_score = (source.len() as i64 + 1) * name_artist_score;
+    let method = |score: i64| {
+        if score >= isrc_score {
+            MatchMethod::Isrc
+        } else if score >= name_artist_score {
+            MatchMethod::NameArtist
+        } else {
+            MatchMethod::Fuzzy {
+                similarity_percent: (score as f64 / FUZZY_SCALE * 100.0).floor() as u8,
+            }
+        }
+    };
+
+    // A row for each source track, padded with columns that match nothing when the source is longer
+    let mut scores = vec![vec![0; source.len().max(destination.len())]; source.len()];
+    let mut musicbrainz_matched: HashSet<(usize, usize)> = HashSet::new();
+    for (source_index, source_track) in source.iter().enumerate() {
+        if mapped.contains_key(&source_index) {
+            continue;
+        }
+
+        // Tracks without an ISRC can only be matched by name and artist
+        let isrc_match = source_track
+            .isrc()
+            .and_then(|isrc| isrc_map.get(isrc))
+            .filter(|&&destination_index| allowed(source_index, destination_index));
+        if let Some(&destination_index) = isrc_match {
+            scores[source_index][destination_index] += isrc_score;
+        } else if let Some(catalog_ids) = source_track
+            .catalog_id()
+            .and_then(|catalog_id| options.musicbrainz_matches.get(catalog_id))
+        {
+            for &destination_index in catalog_ids
+                .iter()
+                .filter_map(|catalog_id| catalog_id_map.get(catalog_id))
+            {
+                if allowed(source_index, destination_index) {
+                    scores[source_index][destination_index] += isrc_score;
+                    musicbrainz_matched.insert((source_index, destination_index));
+                }
+            }
+        }
+
+        if let Some(destination_indices) = name_artist_map.get(&(
+            &*source_names[source_index].0,
+            &*source_names[source_index].1,
+        )) {
+            let destination_indices: Vec<usize> = destination_indices
+                .iter()
+                .copied()
+                .filter(|&destination_index| allowed(source_index, destination_index))
+                .collect();
+            if isrc_match.is_none() && destination_indices.len() > 1 {
+                bail!(
+                    "source track {} matches destination tracks {} by name and artist, pick one with `--map SOURCE=DESTINATION`",
+                    describe_source(source_index),
+                    destination_indices
+                        .iter()
+                        .map(|&i| describe_destination(i))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+            }
+            for destination_index in destination_indices {
+                scores[source_index][destination_index] += name_artist_score;
+            }
+        }
+
+        let (source_name, source_artist_name) = &source_names[source_index];
+        let mut source_title = None;
+        for (destination_index, (destination_name, destination_artist_name)) in
+            destination_names.iter().enumerate()
+        {
+            if destination_artist_name != source_artist_name
+                || destination_name == source_name
+                || !allowed(source_index, destination_index)
+            {
+                continue;
+            }
+            let mut similarity = title_similarity(
+                source_title.get_or_insert_with(|| simplify_title(source_name)),
+                &destination_titles[destination_index],
+                options.fuzzy_threshold,
+            );
+            if let (Some(source_romanized), Some(destination_romanized)) = (
+                source_romanized.get(source_index),
+                destination_romanized.get(destination_index),
+            ) && (source_romanized.native || destination_romanized.native)
+            {
+                similarity = similarity.max(title_similarity(
+                    &source_romanized.simplified,
+                    &destination_romanized.simplified,
+                    options.fuzzy_threshold,
+                ));
+            }
+            if similarity >= options.fuzzy_threshold {
+                // At least 1 so that the pair still counts as a candidate
+                scores[source_index][destination_index] +=
+                    ((similarity * FUZZY_SCALE) as i64).max(1);
+            }
+        }
+    }
+
+    // Manual mappings take their destination away from every other source track
+    for (&source_index, &destination_index) in &mapped {
+        for row in &mut scores {
+            row[destination_index] = 0;
+        }
+        scores[source_index][destination_index] = 1;
+    }
+
+    let assignment = max_score_assignment(&scores);
+    // Padding and pairs without any evidence are assigned too, with a score of 0
+    let matched = |source_index: usize| {
+        let destination_index = assignment[source_index];
+        (destination_index < destination.len() && scores[source_index][destination_index] > 0)
+            .then_some(destination_index)
+    };
+
+    // A source track losing its destination to one with stronger evidence is expected, but with
+    // equal evidence the choice between them would be arbitrary
+    for source_index in (0..source.len()).filter(|&i| matched(i).is_none()) {
+        for (destination_index, &score) in scores[source_index].iter().enumerate() {
+            if score == 0 {
+                continue;
+            }
+            let Some(other_index) = assignment.iter().position(|&d| d == destination_index) else {
+                continue;
+            };
+            if scores[other_index][destination_index] == score {
+                bail!(
+                    "source tracks {} and {} both match destination track {} by {}, pick one with `--map SOURCE=DESTINATION`",
+                    describe_source(other_index),
+                    describe_source(source_index),
+                    describe_destination(destination_index),
+                    method(score),
+                );
+            }
+        }
+    }
+
+    let results = source
+        .iter()
+        .enumerate()
+        .map(|(source_index, source_track)| match matched(source_index) {
+            Some(destination_index) => {
+                let destination_track = &destination[destination_index];
+                let method = if mapped.contains_key(&source_index) {
+                    MatchMethod::Manual
+                } else if musicbrainz_matched.contains(&(source_index, destination_index)) {
+                    MatchMethod::MusicBrainz
+                } else {
+                    method(scores[source_index][destination_index])
+                };
+                // Only the rewrite rules count, punctuation and artist connectives are always
+                // ignored so name matches would otherwise often look rewritten
+                let rewritten = |name: &str, artist_name: &str| {
+                    rewrite(&options.rewrite_rules, RewriteField::Title, name) != name
+                        || rewrite(&options.rewrite_rules, RewriteField::Artist, artist_name)
+                            != artist_name
+                };
+                let normalized =
+                    matches!(method, MatchMethod::NameArtist | MatchMethod::Fuzzy { .. })
+                        && (rewritten(source_track.name(), source_track.artist_name())
+                            || rewritten(
+                                destination_track.name(),
+                                destination_track.artist_name(),
+                            ));
+                TrackMatchResult::Match {
+                    source: source_track,
+                    destination: destination_track,
+                    method,
+                    normalized,
+                    warnings: match_warnings(
+                        source_track,
+                        destination_track,
+                        options.duration_tolerance,
+                    ),
+                }
+            }
+            None => TrackMatchResult::NoMatch {
+                source: source_track,
+            },
+        })
+        .collect();
+
+    Ok(MatchReport { results })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upc_album<Track>(upc: Option<&str>, tracks: Vec<Track>) -> Album<Track> {
+        Album {
+            catalog_id: "10".parse().unwrap(),
+            name: "Album".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: upc.map(str::to_owned),
+            tracks,
+            audio_variants: vec![],
+            apple_digital_master: false,
+        }
+    }
+
+    #[test]
+    fn test_max_score_assignment() {
+        // Greedily taking the best score for each row in order would give 5 + 1 + 0
+        let scores = vec![vec![5, 4, 0], vec![6, 0, 1], vec![0, 0, 0]];
+        assert_eq!(max_score_assignment(&scores), vec![1, 0, 2]);
+        assert_eq!(max_score_assignment(&[]), Vec::<usize>::new());
+        // More columns than rows, as for a source album shorter than the destination
+        let scores = vec![vec![0, 1, 3, 0], vec![2, 0, 3, 0]];
+        assert_eq!(max_score_assignment(&scores), vec![2, 0]);
+
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(
+            levenshtein_distance(&chars("kitten"), &chars("sitting"), 10),
+            3
+        );
+        assert_eq!(
+            levenshtein_distance(&chars("kitten"), &chars("sitting"), 1),
+            2
+        );
+        assert_eq!(levenshtein_distance(&chars("a"), &chars("abcd"), 2), 3);
+    }
+
+    #[test]
+    fn test_match_tracks_name_match_does_not_steal_isrc_match() {
+        let source = Album {
+            catalog_id: "10".parse().unwrap(),
+            name: "Album 1".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
+            tracks: vec![
+                TrackWithLibrary {
+                    catalog_id: "1".parse().unwrap(),
+                    name: "Intro".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.1".parse().unwrap()),
+                    date_added: None,
+                },
+                TrackWithLibrary {
+                    catalog_id: "2".parse().unwrap(),
+                    name: "Intro (Remastered)".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.2".parse().unwrap()),
+                    date_added: None,
+                },
+            ],
+            audio_variants: vec![],
+            apple_digital_master: false,
+        };
+        let destination = Album {
+            catalog_id: "11".parse().unwrap(),
+            name: "Album 1".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
+            tracks: vec![TrackNoLibrary {
+                catalog_id: "3".parse().unwrap(),
+                name: "Intro".to_owned(),
+                artist_name: "Artist".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC2".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                playable: true,
+            }],
+            audio_variants: vec![],
+            apple_digital_master: false,
+        };
+        let expected = vec![
+            TrackMatchResult::NoMatch {
+                source: &source.tracks[0],
+            },
+            TrackMatchResult::Match {
+                source: &source.tracks[1],
+                destination: &destination.tracks[0],
+                method: MatchMethod::Isrc,
+                normalized: false,
+                warnings: vec![],
+            },
+        ];
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected
+        );
+
+        let mut destination = destination;
+        destination.tracks[0].playable = false;
+        let report = match_tracks(&source, &destination, &MatchOptions::default()).unwrap();
+        assert_eq!(
+            report.unplayable_songs_to_add(),
+            vec![&destination.tracks[0]]
+        );
+    }
+
+    #[test]
+    fn test_parse_track_mapping() {
+        let position = |disc_number, track_number| TrackPosition {
+            disc_number,
+            track_number,
+        };
+        assert_eq!(
+            "3=5".parse::<TrackMapping>().unwrap(),
+            TrackMapping {
+                source: position(1, 3),
+                destination: position(1, 5),
+            },
+        );
+        assert_eq!(
+            "1-3=2-1".parse::<TrackMapping>().unwrap(),
+            TrackMapping {
+                source: position(1, 3),
+                destination: position(2, 1),
+            },
+        );
+        assert!("3".parse::<TrackMapping>().is_err());
+        assert!("0=1".parse::<TrackMapping>().is_err());
+        assert!("1=a".parse::<TrackMapping>().is_err());
+    }
+
+    #[test]
+    fn test_match_tracks_two_sources_one_destination() {
+        let source_track = |catalog_id: &str, track_number, isrc: &str| TrackWithLibrary {
+            catalog_id: catalog_id.parse().unwrap(),
+            name: "Intro".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            disc_number: 1,
+            track_number,
+            content_rating: None,
+            isrc: Some(isrc.to_owned()),
+            release_date: Some("2020-01-01".to_owned()),
+            duration: None,
+            library_id: Some(format!("i.{catalog_id}").parse().unwrap()),
+            date_added: None,
+        };
+        let source = upc_album(
+            None,
+            vec![source_track("1", 1, "ISRC1"), source_track("2", 2, "ISRC2")],
+        );
+        let destination = Album {
+            catalog_id: "11".parse().unwrap(),
+            ..upc_album(
+                None,
+                vec![TrackNoLibrary {
+                    catalog_id: "3".parse().unwrap(),
+                    name: "Intro".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC3".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
+                }],
+            )
+        };
+        let error = match_tracks(&source, &destination, &MatchOptions::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "source tracks #1 \"Intro\" and #2 \"Intro\" both match destination track #1 \"Intro\" by name and artist, pick one with `--map SOURCE=DESTINATION`",
+        );
+
+        let options = MatchOptions {
+            mappings: vec!["2=1".parse().unwrap()],
+            ..MatchOptions::default()
+        };
+        let expected = vec![
+            TrackMatchResult::NoMatch {
+                source: &source.tracks[0],
+            },
+            TrackMatchResult::Match {
+                source: &source.tracks[1],
+                destination: &destination.tracks[0],
+                method: MatchMethod::Manual,
+                normalized: false,
+                warnings: vec![],
+            },
+        ];
+        assert_eq!(
+            match_tracks(&source, &destination, &options)
+                .unwrap()
+                .results,
+            expected,
+        );
+    }
+
+    #[test]
+    fn test_match_tracks_disc_mapping() {
+        let source = upc_album(
+            None,
+            vec![TrackWithLibrary {
+                catalog_id: "1".parse().unwrap(),
+                name: "Intro".to_owned(),
+                artist_name: "Artist".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: None,
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                library_id: Some("i.1".parse().unwrap()),
+                date_added: None,
+            }],
+        );
+        let destination_track = |catalog_id: &str, disc_number| TrackNoLibrary {
+            catalog_id: catalog_id.parse().unwrap(),
+            name: "Intro".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            disc_number,
+            track_number: 1,
+            content_rating: None,
+            isrc: None,
+            release_date: Some("2020-01-01".to_owned()),
+            duration: None,
+            playable: true,
+        };
+        let destination = Album {
+            catalog_id: "11".parse().unwrap(),
+            ..upc_album(
+                None,
+                vec![destination_track("2", 1), destination_track("3", 2)],
+            )
+        };
+        assert!(match_tracks(&source, &destination, &MatchOptions::default()).is_err());
+
+        let options = MatchOptions {
+            disc_mappings: vec!["1=2".parse().unwrap()],
+            ..MatchOptions::default()
+        };
+        assert_eq!(
+            match_tracks(&source, &destination, &options)
+                .unwrap()
+                .results,
+            vec![TrackMatchResult::Match {
+                source: &source.tracks[0],
+                destination: &destination.tracks[1],
+                method: MatchMethod::NameArtist,
+                normalized: false,
+                warnings: vec![],
+            }],
+        );
 
-    {
-        let mut source_catalog_ids = HashSet::new();
-        for track in &source.tracks {
-            ensure!(
-                source_catalog_ids.insert(&track.catalog_id),
-                "duplicate catalog ID in source: {}",
-                track.catalog_id,
-            );
-        }
+        let options = MatchOptions {
+            disc_mappings: vec!["1=3".parse().unwrap()],
+            ..MatchOptions::default()
+        };
+        assert!(match_tracks(&source, &destination, &options).is_err());
+        assert!("0=1".parse::<DiscMapping>().is_err());
+        assert!("1-2=1".parse::<DiscMapping>().is_err());
+    }
 
-        let mut destination_catalog_ids = HashSet::new();
-        for track in &destination.tracks {
-            ensure!(
-                destination_catalog_ids.insert(&track.catalog_id),
-                "duplicate catalog ID in destination: {}",
-                track.catalog_id,
-            );
-        }
+    #[test]
+    fn test_match_warnings_duration() {
+        let source = TrackWithLibrary {
+            catalog_id: "1".parse().unwrap(),
+            name: "Song".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            disc_number: 1,
+            track_number: 1,
+            content_rating: None,
+            isrc: Some("ISRC1".to_owned()),
+            release_date: Some("2020-01-01".to_owned()),
+            duration: Some(Duration::from_secs(201)),
+            library_id: Some("i.1".parse().unwrap()),
+            date_added: None,
+        };
+        let destination = |seconds: Option<u64>| TrackNoLibrary {
+            catalog_id: "2".parse().unwrap(),
+            name: "Song".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            disc_number: 1,
+            track_number: 1,
+            content_rating: None,
+            isrc: Some("ISRC1".to_owned()),
+            release_date: Some("2020-01-01".to_owned()),
+            duration: seconds.map(Duration::from_secs),
+            playable: true,
+        };
+        let tolerance = DEFAULT_DURATION_TOLERANCE;
+        assert_eq!(
+            match_warnings(&source, &destination(Some(210)), tolerance),
+            vec![]
+        );
+        assert_eq!(
+            match_warnings(&source, &destination(None), tolerance),
+            vec![]
+        );
+        let warnings = match_warnings(&source, &destination(Some(405)), tolerance);
+        assert_eq!(
+            warnings,
+            vec![MatchWarning::DurationChanged {
+                source: Duration::from_secs(201),
+                destination: Duration::from_secs(405),
+            }],
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "duration changes from 3:21 to 6:45"
+        );
+    }
 
-        ensure!(
-            source_catalog_ids.is_disjoint(&destination_catalog_ids),
-            "source and destination albums have overlapping track catalog IDs",
+    #[test]
+    fn test_normalize_artist() {
+        let normalize = |artist_name| normalize_artist(Cow::Borrowed(artist_name));
+        assert_eq!(normalize("A & B"), normalize("A and B"));
+        assert_eq!(normalize("A, B & C"), "A, B, C");
+        assert_eq!(normalize("A feat. B"), normalize("A ft. B"));
+        assert_eq!(normalize("A Featuring B"), "A, B");
+        assert_eq!(normalize("A FT B"), "A, B");
+        assert!(matches!(normalize("AC/DC"), Cow::Borrowed("AC/DC")));
+        assert!(matches!(normalize("Brandon"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_strip_punctuation() {
+        let strip = |title| strip_punctuation(Cow::Borrowed(title));
+        assert_eq!(
+            strip("Don't Stop - Live"),
+            strip("Don\u{2019}t Stop \u{2013} Live")
         );
+        assert_eq!(strip("Don't Stop - Live"), "Dont Stop Live");
+        assert_eq!(strip("\u{201c}Song\u{201d}  (Remix)"), "Song Remix");
+        assert!(matches!(strip("Song 2"), Cow::Borrowed("Song 2")));
+        assert!(matches!(strip("?!"), Cow::Borrowed("?!")));
     }
 
-    {
-        let mut source_isrcs = HashSet::new();
-        for track in &source.tracks {
-            ensure!(
-                source_isrcs.insert(&track.isrc),
-                "duplicate ISRC in source: {}",
-                track.isrc,
-            );
-        }
+    #[test]
+    fn test_rewrite_only_allocates_on_match() {
+        let rules = vec![RewriteRule {
+            field: RewriteField::Title,
+            pattern: regex::Regex::new(r" \(Remastered\)$").unwrap(),
+            replacement: String::new(),
+        }];
+        assert!(matches!(
+            rewrite(&rules, RewriteField::Title, "Song"),
+            Cow::Borrowed("Song"),
+        ));
+        assert!(matches!(
+            rewrite(&rules, RewriteField::Artist, "Song (Remastered)"),
+            Cow::Borrowed(_),
+        ));
+        assert_eq!(
+            rewrite(&rules, RewriteField::Title, "Song (Remastered)"),
+            "Song",
+        );
+    }
 
-        let mut destination_isrcs = HashSet::new();
-        for track in &destination.tracks {
-            ensure!(
-                destination_isrcs.insert(&track.isrc),
-                "duplicate ISRC in destination: {}",
-                track.isrc,
-            );
-        }
+    #[test]
+    fn test_title_similarity() {
+        let similarity = |a, b| title_similarity(&simplify_title(a), &simplify_title(b), 0.0);
+        assert_eq!(similarity("Song  One", "song one"), 1.0);
+        assert_eq!(similarity("abcd", "abce"), 0.75);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("Suite (Pt. II)", "Suite (Part 2)"), 1.0);
+        assert_eq!(similarity("Vol. IV", "Volume 4"), 1.0);
+        assert_eq!(similarity("Act XXXIX", "Act 39"), 1.0);
+        assert!(similarity("I Want You", "1 Want You") < 1.0);
+        assert!(similarity("Part IIII", "Part 4") < 1.0);
     }
 
-    let isrc_map: HashMap<&str, usize> = destination
-        .tracks
-        .iter()
-        .enumerate()
-        .map(|(i, t)| (t.isrc.as_str(), i))
-        .collect();
+    #[test]
+    fn test_parse_roman_numeral() {
+        assert_eq!(parse_roman_numeral("i"), Some(1));
+        assert_eq!(parse_roman_numeral("xiv"), Some(14));
+        assert_eq!(parse_roman_numeral("xxxix"), Some(39));
+        assert_eq!(parse_roman_numeral("xl"), None);
+        assert_eq!(parse_roman_numeral("xxxx"), None);
+        assert_eq!(parse_roman_numeral(""), None);
+        assert_eq!(parse_roman_numeral("mix"), None);
+    }
 
-    let mut name_artist_map: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
-    for (i, t) in destination.tracks.iter().enumerate() {
-        name_artist_map
-            .entry((&t.name, &t.artist_name))
-            .or_default()
-            .push(i);
+    #[test]
+    fn test_match_tracks_fuzzy_threshold() {
+        let source = upc_album(
+            None,
+            vec![TrackWithLibrary {
+                catalog_id: "1".parse().unwrap(),
+                name: "Don't Stop Me Now".to_owned(),
+                artist_name: "Artist".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: None,
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                library_id: Some("i.1".parse().unwrap()),
+                date_added: None,
+            }],
+        );
+        let destination = Album {
+            catalog_id: "11".parse().unwrap(),
+            ..upc_album(
+                None,
+                vec![TrackNoLibrary {
+                    catalog_id: "2".parse().unwrap(),
+                    name: "Dont Stop Me Now".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: None,
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
+                }],
+            )
+        };
+
+        let expected = vec![TrackMatchResult::Match {
+            source: &source.tracks[0],
+            destination: &destination.tracks[0],
+            method: MatchMethod::Fuzzy {
+                similarity_percent: 94,
+            },
+            normalized: false,
+            warnings: vec![],
+        }];
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected,
+        );
+
+        let options = MatchOptions {
+            fuzzy_threshold: 0.95,
+            ..MatchOptions::default()
+        };
+        let expected = vec![TrackMatchResult::NoMatch {
+            source: &source.tracks[0],
+        }];
+        assert_eq!(
+            match_tracks(&source, &destination, &options)
+                .unwrap()
+                .results,
+            expected,
+        );
     }
 
-    let mut used_destinations: HashSet<usize> = HashSet::new();
-    let mut results = Vec::with_capacity(source.tracks.len());
+    #[test]
+    fn test_match_tracks_transliterate() {
+        let source = upc_album(
+            None,
+            vec![TrackWithLibrary {
+                catalog_id: "1".parse().unwrap(),
+                name: "사랑해".to_owned(),
+                artist_name: "Artist".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: None,
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                library_id: Some("i.1".parse().unwrap()),
+                date_added: None,
+            }],
+        );
+        let destination = Album {
+            catalog_id: "11".parse().unwrap(),
+            ..upc_album(
+                None,
+                vec![TrackNoLibrary {
+                    catalog_id: "2".parse().unwrap(),
+                    name: "Saranghae".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: None,
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
+                }],
+            )
+        };
 
-    for source_track in &source.tracks {
-        if let Some(&destination_index) = isrc_map.get(source_track.isrc.as_str()) {
-            ensure!(used_destinations.insert(destination_index));
-            results.push(TrackMatchResult::Match {
-                source: source_track,
-                destination: &destination.tracks[destination_index],
-            });
-            continue;
-        }
+        let expected = vec![TrackMatchResult::NoMatch {
+            source: &source.tracks[0],
+        }];
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected,
+        );
 
-        if let Some(destination_indices) =
-            name_artist_map.get(&(&source_track.name, &source_track.artist_name))
-        {
-            let mut destination_indices = destination_indices.iter();
-            let Some(&destination_index) = destination_indices.next() else {
-                results.push(TrackMatchResult::NoMatch {
-                    source: source_track,
-                });
-                continue;
-            };
-            ensure!(
-                destination_indices.next().is_none(),
-                "ambiguous name and artist match",
-            );
+        let options = MatchOptions {
+            transliterate: true,
+            ..MatchOptions::default()
+        };
+        let expected = vec![TrackMatchResult::Match {
+            source: &source.tracks[0],
+            destination: &destination.tracks[0],
+            method: MatchMethod::Fuzzy {
+                similarity_percent: 100,
+            },
+            normalized: false,
+            warnings: vec![],
+        }];
+        assert_eq!(
+            match_tracks(&source, &destination, &options)
+                .unwrap()
+                .results,
+            expected,
+        );
+    }
 
-            results.push(TrackMatchResult::Match {
-                source: source_track,
-                destination: &destination.tracks[destination_index],
-            });
-            continue;
-        }
+    #[test]
+    fn test_match_tracks_rewrite_rules() {
+        let source = upc_album(
+            None,
+            vec![TrackWithLibrary {
+                catalog_id: "1".parse().unwrap(),
+                name: "Song 1 (Remastered 2011)".to_owned(),
+                artist_name: "JAY Z".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                library_id: Some("i.1".parse().unwrap()),
+                date_added: None,
+            }],
+        );
+        let destination = Album {
+            catalog_id: "11".parse().unwrap(),
+            ..upc_album(
+                None,
+                vec![TrackNoLibrary {
+                    catalog_id: "2".parse().unwrap(),
+                    name: "Song 1".to_owned(),
+                    artist_name: "JAY-Z".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
+                }],
+            )
+        };
+        let options = MatchOptions {
+            rewrite_rules: vec![
+                RewriteRule {
+                    field: RewriteField::Artist,
+                    pattern: regex::Regex::new("^JAY Z$").unwrap(),
+                    replacement: "JAY-Z".to_owned(),
+                },
+                RewriteRule {
+                    field: RewriteField::Title,
+                    pattern: regex::Regex::new(r" \(Remastered \d{4}\)$").unwrap(),
+                    replacement: String::new(),
+                },
+            ],
+            ..MatchOptions::default()
+        };
+        let expected = vec![TrackMatchResult::Match {
+            source: &source.tracks[0],
+            destination: &destination.tracks[0],
+            method: MatchMethod::NameArtist,
+            normalized: true,
+            warnings: vec![],
+        }];
+        assert_eq!(
+            match_tracks(&source, &destination, &options)
+                .unwrap()
+                .results,
+            expected,
+        );
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            vec![TrackMatchResult::NoMatch {
+                source: &source.tracks[0],
+            }],
+        );
+    }
 
-        results.push(TrackMatchResult::NoMatch {
-            source: source_track,
-        });
+    #[test]
+    fn test_match_tracks_artist_connective_not_normalized() {
+        let source = upc_album(
+            None,
+            vec![TrackWithLibrary {
+                catalog_id: "1".parse().unwrap(),
+                name: "The Boxer".to_owned(),
+                artist_name: "Simon & Garfunkel".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                library_id: Some("i.1".parse().unwrap()),
+                date_added: None,
+            }],
+        );
+        let destination = Album {
+            catalog_id: "11".parse().unwrap(),
+            ..upc_album(
+                None,
+                vec![TrackNoLibrary {
+                    catalog_id: "2".parse().unwrap(),
+                    name: "The Boxer".to_owned(),
+                    artist_name: "Simon & Garfunkel".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
+                }],
+            )
+        };
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            vec![TrackMatchResult::Match {
+                source: &source.tracks[0],
+                destination: &destination.tracks[0],
+                method: MatchMethod::NameArtist,
+                normalized: false,
+                warnings: vec![],
+            }],
+        );
     }
 
-    Ok(results)
-}
+    #[test]
+    fn test_compare_upcs() {
+        let album = |upc| upc_album::<TrackNoLibrary>(upc, vec![]);
+        assert_eq!(
+            compare_upcs(&album(Some("012345678905")), &album(Some("0012345678905"))),
+            UpcComparison::Same,
+        );
+        assert_eq!(
+            compare_upcs(&album(Some("012345678905")), &album(Some("012345678912"))),
+            UpcComparison::Different,
+        );
+        assert_eq!(
+            compare_upcs(&album(Some("012345678905")), &album(None)),
+            UpcComparison::Unknown,
+        );
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_looks_unrelated() {
+        let source = upc_album(
+            Some("1"),
+            vec![TrackWithLibrary {
+                catalog_id: "1".parse().unwrap(),
+                name: "Song 1".to_owned(),
+                artist_name: "Artist".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                library_id: Some("i.1".parse().unwrap()),
+                date_added: None,
+            }],
+        );
+        let destination = |upc, isrc: &str| {
+            upc_album(
+                upc,
+                vec![TrackNoLibrary {
+                    catalog_id: "2".parse().unwrap(),
+                    name: "Song 1".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some(isrc.to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
+                }],
+            )
+        };
+        assert!(looks_unrelated(&source, &destination(Some("2"), "ISRC2")));
+        assert!(!looks_unrelated(&source, &destination(Some("2"), "ISRC1")));
+        assert!(!looks_unrelated(&source, &destination(Some("1"), "ISRC2")));
+        assert!(!looks_unrelated(&source, &destination(None, "ISRC2")));
+    }
 
     #[test]
     fn test_match_tracks_simple() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackWithLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
                     library_id: None,
+                    date_added: None,
                 },
                 TrackWithLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
-                    library_id: Some("i.2".to_owned()),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.2".parse().unwrap()),
+                    date_added: None,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-02".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-02".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "3".to_owned(),
+                    catalog_id: "3".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "4".to_owned(),
+                    catalog_id: "4".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let expected = vec![
             TrackMatchResult::Match {
                 source: &source.tracks[0],
                 destination: &destination.tracks[0],
+                method: MatchMethod::Isrc,
+                normalized: false,
+                warnings: vec![],
             },
             TrackMatchResult::Match {
                 source: &source.tracks[1],
                 destination: &destination.tracks[1],
+                method: MatchMethod::Isrc,
+                normalized: false,
+                warnings: vec![],
             },
         ];
-        assert_eq!(match_tracks(&source, &destination).unwrap(), expected);
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected
+        );
     }
 
     #[test]
     fn test_match_tracks_match_source_order() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackWithLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
                     library_id: None,
+                    date_added: None,
                 },
                 TrackWithLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
-                    library_id: Some("i.2".to_owned()),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.2".parse().unwrap()),
+                    date_added: None,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-02".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-02".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "4".to_owned(),
+                    catalog_id: "4".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "3".to_owned(),
+                    catalog_id: "3".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let expected = vec![
             TrackMatchResult::Match {
                 source: &source.tracks[0],
                 destination: &destination.tracks[1],
+                method: MatchMethod::Isrc,
+                normalized: false,
+                warnings: vec![],
             },
             TrackMatchResult::Match {
                 source: &source.tracks[1],
                 destination: &destination.tracks[0],
+                method: MatchMethod::Isrc,
+                normalized: false,
+                warnings: vec![],
             },
         ];
-        assert_eq!(match_tracks(&source, &destination).unwrap(), expected);
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected
+        );
     }
 
     #[test]
     fn test_match_tracks_prefix_extra_songs() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "3".to_owned(),
+                    catalog_id: "3".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let expected = vec![TrackMatchResult::Match {
             source: &source.tracks[0],
             destination: &destination.tracks[1],
+            method: MatchMethod::Isrc,
+            normalized: false,
+            warnings: vec![],
         }];
-        assert_eq!(match_tracks(&source, &destination).unwrap(), expected);
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected
+        );
     }
 
     #[test]
     fn test_match_tracks_appended_extra_songs() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "3".to_owned(),
+                    catalog_id: "3".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "4".to_owned(),
+                    catalog_id: "4".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let expected = vec![TrackMatchResult::Match {
             source: &source.tracks[0],
             destination: &destination.tracks[0],
+            method: MatchMethod::Isrc,
+            normalized: false,
+            warnings: vec![],
         }];
-        assert_eq!(match_tracks(&source, &destination).unwrap(), expected);
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected
+        );
     }
 
     #[test]
     fn test_match_tracks_same() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
-        assert!(match_tracks(&source, &destination).is_err());
+        assert!(match_tracks(&source, &destination, &MatchOptions::default()).is_err());
     }
 
     #[test]
     fn test_match_tracks_same_tracks() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
-        assert!(match_tracks(&source, &destination).is_err());
+        assert!(match_tracks(&source, &destination, &MatchOptions::default()).is_err());
     }
 
     #[test]
     fn test_match_tracks_same_album_catalog_id() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-02".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-02".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "2".to_owned(),
+                catalog_id: "2".parse().unwrap(),
                 name: "Song 2".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC2".to_owned(),
-                release_date: "2020-01-02".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC2".to_owned()),
+                release_date: Some("2020-01-02".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
-        assert!(match_tracks(&source, &destination).is_err());
+        assert!(match_tracks(&source, &destination, &MatchOptions::default()).is_err());
     }
 
     #[test]
     fn test_match_tracks_same_title_artist_track() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "2".to_owned(),
+                catalog_id: "2".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC2".to_owned(),
-                release_date: "2020-01-02".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC2".to_owned()),
+                release_date: Some("2020-01-02".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let expected = vec![TrackMatchResult::Match {
             source: &source.tracks[0],
             destination: &destination.tracks[0],
+            method: MatchMethod::NameArtist,
+            normalized: false,
+            warnings: vec![],
         }];
-        assert_eq!(match_tracks(&source, &destination).unwrap(), expected);
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_match_tracks_missing_isrc() {
+        let source = Album {
+            catalog_id: "10".parse().unwrap(),
+            name: "Album 1".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
+            tracks: vec![
+                TrackWithLibrary {
+                    catalog_id: "1".parse().unwrap(),
+                    name: "Song 1".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: None,
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.1".parse().unwrap()),
+                    date_added: None,
+                },
+                TrackWithLibrary {
+                    catalog_id: "2".parse().unwrap(),
+                    name: "Song 2".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: None,
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.2".parse().unwrap()),
+                    date_added: None,
+                },
+            ],
+            audio_variants: vec![],
+            apple_digital_master: false,
+        };
+        let destination = Album {
+            catalog_id: "11".parse().unwrap(),
+            name: "Album 1".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
+            tracks: vec![
+                TrackNoLibrary {
+                    catalog_id: "3".parse().unwrap(),
+                    name: "Song 1".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
+                },
+                TrackNoLibrary {
+                    catalog_id: "4".parse().unwrap(),
+                    name: "Song 2 (Remix)".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: None,
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
+                },
+            ],
+            audio_variants: vec![],
+            apple_digital_master: false,
+        };
+        let expected = vec![
+            TrackMatchResult::Match {
+                source: &source.tracks[0],
+                destination: &destination.tracks[0],
+                method: MatchMethod::NameArtist,
+                normalized: false,
+                warnings: vec![],
+            },
+            TrackMatchResult::NoMatch {
+                source: &source.tracks[1],
+            },
+        ];
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected
+        );
+
+        let options = MatchOptions {
+            musicbrainz_matches: HashMap::from([(
+                "2".parse().unwrap(),
+                HashSet::from(["4".parse().unwrap()]),
+            )]),
+            ..MatchOptions::default()
+        };
+        assert_eq!(
+            match_tracks(&source, &destination, &options)
+                .unwrap()
+                .results[1],
+            TrackMatchResult::Match {
+                source: &source.tracks[1],
+                destination: &destination.tracks[1],
+                method: MatchMethod::MusicBrainz,
+                normalized: false,
+                warnings: vec![],
+            },
+        );
     }
 
     #[test]
     fn test_match_tracks_same_title_track() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "2".to_owned(),
+                catalog_id: "2".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist 2".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC2".to_owned(),
-                release_date: "2020-01-02".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC2".to_owned()),
+                release_date: Some("2020-01-02".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let expected = vec![TrackMatchResult::NoMatch {
             source: &source.tracks[0],
         }];
-        assert_eq!(match_tracks(&source, &destination).unwrap(), expected);
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected
+        );
     }
 
     #[test]
     fn test_match_tracks_clean() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackWithLibrary {
-                    catalog_id: "11".to_owned(),
+                    catalog_id: "11".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC11".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC11".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
                     library_id: None,
+                    date_added: None,
                 },
                 TrackWithLibrary {
-                    catalog_id: "21".to_owned(),
+                    catalog_id: "21".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC21".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
-                    library_id: Some("i.2".to_owned()),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC21".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    library_id: Some("i.2".parse().unwrap()),
+                    date_added: None,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "12".to_owned(),
+                    catalog_id: "12".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: true,
-                    isrc: "ISRC12".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: Some(ContentRating::Explicit),
+                    isrc: Some("ISRC12".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "22".to_owned(),
+                    catalog_id: "22".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: true,
-                    isrc: "ISRC22".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: Some(ContentRating::Explicit),
+                    isrc: Some("ISRC22".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let expected = vec![
             TrackMatchResult::Match {
                 source: &source.tracks[0],
                 destination: &destination.tracks[0],
+                method: MatchMethod::NameArtist,
+                normalized: false,
+                warnings: vec![MatchWarning::ContentRatingChanged {
+                    source: None,
+                    destination: Some(ContentRating::Explicit),
+                }],
             },
             TrackMatchResult::Match {
                 source: &source.tracks[1],
                 destination: &destination.tracks[1],
+                method: MatchMethod::NameArtist,
+                normalized: false,
+                warnings: vec![MatchWarning::ContentRatingChanged {
+                    source: None,
+                    destination: Some(ContentRating::Explicit),
+                }],
             },
         ];
-        assert_eq!(match_tracks(&source, &destination).unwrap(), expected);
+        assert_eq!(
+            match_tracks(&source, &destination, &MatchOptions::default())
+                .unwrap()
+                .results,
+            expected
+        );
     }
 
     #[test]
     fn test_match_tracks_duplicate_isrc_source() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackWithLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
                     library_id: None,
+                    date_added: None,
                 },
                 TrackWithLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
                     library_id: None,
+                    date_added: None,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "3".to_owned(),
+                catalog_id: "3".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-02".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-02".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
-        assert!(match_tracks(&source, &destination).is_err());
+        assert!(match_tracks(&source, &destination, &MatchOptions::default()).is_err());
     }
 
     #[test]
     fn test_match_tracks_duplicate_isrc_destination() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-02".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-02".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "3".to_owned(),
+                    catalog_id: "3".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-02".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-02".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
-        assert!(match_tracks(&source, &destination).is_err());
+        assert!(match_tracks(&source, &destination, &MatchOptions::default()).is_err());
     }
 
     #[test]
     fn test_match_tracks_duplicate_catalog_id_source() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackWithLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
                     library_id: None,
+                    date_added: None,
                 },
                 TrackWithLibrary {
-                    catalog_id: "1".to_owned(),
+                    catalog_id: "1".parse().unwrap(),
                     name: "Song 2".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2020-01-01".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-01".to_owned()),
+                    duration: None,
                     library_id: None,
+                    date_added: None,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackNoLibrary {
-                catalog_id: "2".to_owned(),
+                catalog_id: "2".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-02".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-02".to_owned()),
+                duration: None,
+                playable: true,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
-        assert!(match_tracks(&source, &destination).is_err());
+        assert!(match_tracks(&source, &destination, &MatchOptions::default()).is_err());
     }
 
     #[test]
     fn test_match_tracks_duplicate_catalog_id_destination() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-02".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-02".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC1".to_owned(),
-                    release_date: "2020-01-02".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC1".to_owned()),
+                    release_date: Some("2020-01-02".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
-        assert!(match_tracks(&source, &destination).is_err());
+        assert!(match_tracks(&source, &destination, &MatchOptions::default()).is_err());
     }
 
     #[test]
     fn test_match_tracks_multiple_same_title_artist_track() {
         let source = Album {
-            catalog_id: "10".to_owned(),
+            catalog_id: "10".parse().unwrap(),
+            name: "Album 1".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
+            tracks: vec![TrackWithLibrary {
+                catalog_id: "1".parse().unwrap(),
+                name: "Song 1".to_owned(),
+                artist_name: "Artist".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
+                library_id: None,
+                date_added: None,
+            }],
+            audio_variants: vec![],
+            apple_digital_master: false,
+        };
+        let destination = Album {
+            catalog_id: "11".parse().unwrap(),
+            name: "Album 1".to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
+            tracks: vec![
+                TrackNoLibrary {
+                    catalog_id: "2".parse().unwrap(),
+                    name: "Song 1".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-02".to_owned()),
+                    duration: None,
+                    playable: true,
+                },
+                TrackNoLibrary {
+                    catalog_id: "3".parse().unwrap(),
+                    name: "Song 1".to_owned(),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC3".to_owned()),
+                    release_date: Some("2020-01-02".to_owned()),
+                    duration: None,
+                    playable: true,
+                },
+            ],
+            audio_variants: vec![],
+            apple_digital_master: false,
+        };
+        assert!(match_tracks(&source, &destination, &MatchOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_match_tracks_multiple_same_title_artist_track_mapped() {
+        let source = Album {
+            catalog_id: "10".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![TrackWithLibrary {
-                catalog_id: "1".to_owned(),
+                catalog_id: "1".parse().unwrap(),
                 name: "Song 1".to_owned(),
                 artist_name: "Artist".to_owned(),
-                is_explicit: false,
-                isrc: "ISRC1".to_owned(),
-                release_date: "2020-01-01".to_owned(),
+                genre_names: vec![],
+                disc_number: 1,
+                track_number: 1,
+                content_rating: None,
+                isrc: Some("ISRC1".to_owned()),
+                release_date: Some("2020-01-01".to_owned()),
+                duration: None,
                 library_id: None,
+                date_added: None,
             }],
+            audio_variants: vec![],
+            apple_digital_master: false,
         };
         let destination = Album {
-            catalog_id: "11".to_owned(),
+            catalog_id: "11".parse().unwrap(),
             name: "Album 1".to_owned(),
             artist_name: "Artist".to_owned(),
-            release_date: "2020-01-01".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
             tracks: vec![
                 TrackNoLibrary {
-                    catalog_id: "2".to_owned(),
+                    catalog_id: "2".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC2".to_owned(),
-                    release_date: "2020-01-02".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 1,
+                    content_rating: None,
+                    isrc: Some("ISRC2".to_owned()),
+                    release_date: Some("2020-01-02".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
                 TrackNoLibrary {
-                    catalog_id: "3".to_owned(),
+                    catalog_id: "3".parse().unwrap(),
                     name: "Song 1".to_owned(),
                     artist_name: "Artist".to_owned(),
-                    is_explicit: false,
-                    isrc: "ISRC3".to_owned(),
-                    release_date: "2020-01-02".to_owned(),
+                    genre_names: vec![],
+                    disc_number: 1,
+                    track_number: 2,
+                    content_rating: None,
+                    isrc: Some("ISRC3".to_owned()),
+                    release_date: Some("2020-01-02".to_owned()),
+                    duration: None,
+                    playable: true,
                 },
             ],
+            audio_variants: vec![],
+            apple_digital_master: false,
+        };
+        let error = match_tracks(&source, &destination, &MatchOptions::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "source track #1 \"Song 1\" matches destination tracks #1 \"Song 1\", #2 \"Song 1\" by name and artist, pick one with `--map SOURCE=DESTINATION`",
+        );
+        let options = MatchOptions {
+            mappings: vec!["1=2".parse().unwrap()],
+            ..MatchOptions::default()
         };
-        assert!(match_tracks(&source, &destination).is_err());
+        let expected = vec![TrackMatchResult::Match {
+            source: &source.tracks[0],
+            destination: &destination.tracks[1],
+            method: MatchMethod::Manual,
+            normalized: false,
+            warnings: vec![],
+        }];
+        assert_eq!(
+            match_tracks(&source, &destination, &options)
+                .unwrap()
+                .results,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_match_track_lists() {
+        struct LocalTrack {
+            name: &'static str,
+            track_number: u8,
+        }
+
+        impl MatchableTrack for LocalTrack {
+            fn name(&self) -> &str {
+                self.name
+            }
+
+            fn artist_name(&self) -> &str {
+                "Artist"
+            }
+
+            fn isrc(&self) -> Option<&str> {
+                None
+            }
+
+            fn duration(&self) -> Option<Duration> {
+                None
+            }
+
+            fn disc_number(&self) -> u8 {
+                1
+            }
+
+            fn track_number(&self) -> u8 {
+                self.track_number
+            }
+
+            fn catalog_id(&self) -> Option<&CatalogSongId> {
+                None
+            }
+
+            fn content_rating(&self) -> Option<&ContentRating> {
+                None
+            }
+        }
+
+        let source = [
+            LocalTrack {
+                name: "Song",
+                track_number: 1,
+            },
+            LocalTrack {
+                name: "Other Song (Pt. 2)",
+                track_number: 2,
+            },
+        ];
+        let destination = [
+            LocalTrack {
+                name: "Other Song (Part 2)",
+                track_number: 1,
+            },
+            LocalTrack {
+                name: "Song",
+                track_number: 2,
+            },
+        ];
+        let report = match_track_lists(&source, &destination, &MatchOptions::default()).unwrap();
+        let pairs: Vec<_> = report
+            .results
+            .iter()
+            .map(|result| match result {
+                TrackMatchResult::Match {
+                    source,
+                    destination,
+                    method,
+                    ..
+                } => (source.track_number, destination.track_number, *method),
+                TrackMatchResult::NoMatch { .. } => panic!("unmatched track"),
+            })
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                (1, 2, MatchMethod::NameArtist),
+                (
+                    2,
+                    1,
+                    MatchMethod::Fuzzy {
+                        similarity_percent: 100
+                    }
+                )
+            ],
+        );
     }
 }
@@ -0,0 +1,1098 @@
+use std::{collections::HashSet, io::Write};
+
+use anyhow::{Context, Result, ensure};
+use futures_util::TryStreamExt;
+
+use crate::{
+    apple_music::{
+        self, AlbumId, CatalogAlbumId, CatalogSongId, LibraryAlbumId, LibrarySongId,
+        custom_types::{self, Album, ContentRating, TrackNoLibrary, TrackWithLibrary},
+    },
+    discovery, history, interrupt, library, matching, output,
+    progress::{Event, Progress},
+    telemetry,
+};
+
+/// The source catalog album with the library IDs of the tracks that are in the library
+pub async fn load_source(
+    client: &apple_music::Client,
+    library_id: &LibraryAlbumId,
+) -> Result<Album<TrackWithLibrary>> {
+    let library_album = client.get_library_album(library_id).await?;
+    ensure!(library_album.library_id()? == library_id.as_str());
+    let catalog_album = client
+        .get_catalog_album(&library_album.catalog_id()?.parse()?)
+        .await?;
+    let album: Album<TrackNoLibrary> = catalog_album.try_into()?;
+    album.with_library_info(&library_album)
+}
+
+pub async fn load_destination(
+    client: &apple_music::Client,
+    catalog_id: &CatalogAlbumId,
+) -> Result<Album<TrackNoLibrary>> {
+    let album: Album<TrackNoLibrary> = client.get_catalog_album(catalog_id).await?.try_into()?;
+    ensure!(album.catalog_id == *catalog_id);
+    Ok(album)
+}
+
+/// An unfinished migration from the source to the destination albums, with the history it's in.
+/// The history is best effort like in [`execute`], so failing to read it is only a warning.
+pub fn find_pending(
+    source: &str,
+    destination_catalog_ids: &[CatalogAlbumId],
+) -> Option<(history::History, history::Pending)> {
+    let destination_catalog_ids: Vec<String> = destination_catalog_ids
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let found = history::History::open().and_then(|history| {
+        let pending = history.pending(source, &destination_catalog_ids)?;
+        Ok(pending.map(|pending| (history, pending)))
+    });
+    found.unwrap_or_else(|error| {
+        eprintln!("Warning: failed to check the history for an unfinished migration: {error:#}");
+        None
+    })
+}
+
+/// Finish a migration that removed the source album but failed to add all of the destination,
+/// adding the songs that still aren't in the library from any of the destination albums
+pub async fn resume(
+    client: &apple_music::Client,
+    mut history: history::History,
+    pending: &history::Pending,
+) -> Result<()> {
+    let destination_catalog_ids: Vec<CatalogAlbumId> = pending
+        .destination_catalog_ids
+        .iter()
+        .map(|catalog_id| catalog_id.parse())
+        .collect::<Result<_>>()?;
+    let mut in_library: HashSet<CatalogSongId> = HashSet::new();
+    for catalog_id in &destination_catalog_ids {
+        if let Some(album) = find_library_album(client, catalog_id).await? {
+            in_library.extend(album.tracks.into_iter().map(|track| track.catalog_id));
+        }
+    }
+    let songs_to_add: Vec<CatalogSongId> = pending
+        .songs_to_add
+        .iter()
+        .map(|catalog_id| catalog_id.parse())
+        .collect::<Result<Vec<CatalogSongId>>>()?
+        .into_iter()
+        .filter(|catalog_id| !in_library.contains(catalog_id))
+        .collect();
+
+    println!(
+        "A previous run removed the source album but didn't finish adding \"{}\", {} of {} songs \
+         are still to be added",
+        pending.destination_name,
+        songs_to_add.len(),
+        pending.songs_to_add.len(),
+    );
+
+    let _deferred = interrupt::defer();
+    if songs_to_add.is_empty() {
+        // Already added, e.g. by hand
+    } else if let [destination_catalog_id] = destination_catalog_ids.as_slice()
+        && pending.whole_album
+        && in_library.is_empty()
+    {
+        client.add_album_to_library(destination_catalog_id).await?;
+    } else {
+        let songs_to_add: Vec<&CatalogSongId> = songs_to_add.iter().collect();
+        add_songs(client, &songs_to_add).await?;
+    }
+    history.finish(pending)?;
+    println!(
+        "Finished migrating to \"{}\", the migration isn't in the history since the previous run \
+         stopped before recording it",
+        pending.destination_name,
+    );
+    Ok(())
+}
+
+/// Whether any of the album's songs are still in the library
+/// A request for each 100 songs, the most the API accepts at once
+async fn add_songs(client: &apple_music::Client, catalog_ids: &[&CatalogSongId]) -> Result<()> {
+    for chunk in catalog_ids.chunks(library::IDS_PER_REQUEST) {
+        client.add_songs_to_library(chunk).await?;
+    }
+    Ok(())
+}
+
+pub async fn in_library(client: &apple_music::Client, catalog_id: &CatalogAlbumId) -> Result<bool> {
+    Ok(find_library_album(client, catalog_id).await?.is_some())
+}
+
+/// Destination albums as one to match against, for a source box set that the catalog has split
+/// into several albums. Each album's discs are numbered after the previous album's, and the
+/// combined album has the first album's catalog ID.
+pub fn combine_destinations(
+    mut albums: Vec<Album<TrackNoLibrary>>,
+) -> Result<Album<TrackNoLibrary>> {
+    ensure!(!albums.is_empty(), "no destination albums");
+    for (i, album) in albums.iter().enumerate() {
+        ensure!(
+            albums[..i]
+                .iter()
+                .all(|other| other.catalog_id != album.catalog_id),
+            "destination album {} is given more than once",
+            album.catalog_id,
+        );
+    }
+    if albums.len() == 1 {
+        return Ok(albums.remove(0));
+    }
+    let mut tracks = Vec::new();
+    let mut disc_offset: u8 = 0;
+    for album in &mut albums {
+        let discs = album
+            .tracks
+            .iter()
+            .map(|t| t.disc_number)
+            .max()
+            .unwrap_or(0);
+        for track in album.tracks.drain(..) {
+            tracks.push(TrackNoLibrary {
+                disc_number: track
+                    .disc_number
+                    .checked_add(disc_offset)
+                    .context("too many discs in the destination albums")?,
+                ..track
+            });
+        }
+        disc_offset = disc_offset
+            .checked_add(discs)
+            .context("too many discs in the destination albums")?;
+    }
+    let first = &albums[0];
+    Ok(Album {
+        catalog_id: first.catalog_id.clone(),
+        name: albums
+            .iter()
+            .map(|album| album.name.as_str())
+            .collect::<Vec<_>>()
+            .join(" + "),
+        artist_name: first.artist_name.clone(),
+        genre_names: first.genre_names.clone(),
+        release_date: first.release_date.clone(),
+        // Each album has its own
+        upc: None,
+        audio_variants: first
+            .audio_variants
+            .iter()
+            .filter(|variant| {
+                albums
+                    .iter()
+                    .all(|album| album.audio_variants.contains(variant))
+            })
+            .cloned()
+            .collect(),
+        apple_digital_master: albums.iter().all(|album| album.apple_digital_master),
+        tracks,
+    })
+}
+
+/// The library album added from the catalog album, if any of its songs are in the library
+pub async fn find_library_album(
+    client: &apple_music::Client,
+    catalog_id: &CatalogAlbumId,
+) -> Result<Option<custom_types::LibraryAlbum>> {
+    client
+        .get_library_album_by_catalog_id(catalog_id)
+        .await?
+        .map(TryInto::try_into)
+        .transpose()
+}
+
+/// The library ID of a library album, or of the library album added from a catalog album
+pub async fn resolve_library_id(
+    client: &apple_music::Client,
+    album_id: &AlbumId,
+) -> Result<LibraryAlbumId> {
+    match album_id {
+        AlbumId::Library(library_id) => Ok(library_id.clone()),
+        AlbumId::Catalog(catalog_id) => Ok(find_library_album(client, catalog_id)
+            .await?
+            .with_context(|| format!("album {catalog_id} isn't in the library"))?
+            .library_id),
+    }
+}
+
+/// Whether the given destination is the album with more songs in the library and so should be the
+/// source instead, for `--detect-direction`
+pub async fn should_swap(
+    client: &apple_music::Client,
+    source_catalog_id: &CatalogAlbumId,
+    destination_catalog_id: &CatalogAlbumId,
+) -> Result<bool> {
+    let mut counts = [0; 2];
+    for (count, catalog_id) in counts
+        .iter_mut()
+        .zip([source_catalog_id, destination_catalog_id])
+    {
+        if let Some(album) = find_library_album(client, catalog_id).await? {
+            *count = album.tracks.len();
+        }
+        println!("Album {catalog_id} has {count} songs in the library");
+    }
+    println!();
+    ensure!(counts != [0, 0], "neither album has songs in the library",);
+    Ok(counts[1] > counts[0])
+}
+
+/// Catalog IDs of the destination tracks that are already in the library, so that a migration to
+/// a partially added album only adds the rest
+pub async fn destination_tracks_in_library(
+    client: &apple_music::Client,
+    destination_album: &Album<TrackNoLibrary>,
+) -> Result<HashSet<CatalogSongId>> {
+    Ok(find_library_album(client, &destination_album.catalog_id)
+        .await?
+        .map(|album| {
+            album
+                .tracks
+                .into_iter()
+                .map(|track| track.catalog_id)
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Which of the recently played albums and heavy rotation the source album is in, for
+/// `--check-rotation`
+pub async fn listening_activity(
+    client: &apple_music::Client,
+    source_album_library_id: &LibraryAlbumId,
+    source_album: &Album<TrackWithLibrary>,
+) -> Result<Vec<&'static str>> {
+    let is_source = |id: &str| *source_album_library_id == id || source_album.catalog_id == id;
+    let mut activity = Vec::new();
+    if client
+        .get_recently_played()
+        .await?
+        .album_ids()
+        .any(is_source)
+    {
+        activity.push("recently played albums");
+    }
+    if client
+        .get_heavy_rotation()
+        .await?
+        .album_ids()
+        .any(is_source)
+    {
+        activity.push("heavy rotation");
+    }
+    Ok(activity)
+}
+
+fn print_album_summary<Track>(label: &str, album: &Album<Track>) {
+    let genres = output::format_genres(&album.genre_names)
+        .map(|genres| format!(", {genres}"))
+        .unwrap_or_default();
+    let upc = output::format_upc(album.upc.as_deref());
+    let badges = output::format_audio_badges(&album.audio_variants, album.apple_digital_master)
+        .map(|badges| format!(", {badges}"))
+        .unwrap_or_default();
+    println!(
+        "{label}: \"{}\" by {} ({}, {} tracks{genres}{upc}{badges})",
+        album.name,
+        album.artist_name,
+        output::format_release_date(album.release_date.as_deref()),
+        album.tracks.len(),
+    );
+}
+
+/// The number of tracks in the library that match and that don't
+fn library_track_counts(report: &matching::MatchReport) -> (usize, usize) {
+    (
+        report.songs_to_add().len(),
+        report.unmatched_in_library().len(),
+    )
+}
+
+/// Print the albums and which tracks in the library match, without making any changes, only the
+/// number of tracks if `summary_only`
+pub fn print_plan(
+    source_album: &Album<TrackWithLibrary>,
+    destination_album: &Album<TrackNoLibrary>,
+    report: &matching::MatchReport,
+    destination_in_library: &HashSet<CatalogSongId>,
+    summary_only: bool,
+    show_ids: bool,
+) {
+    print_album_summary("Source", source_album);
+    print_album_summary("Destination", destination_album);
+    if matching::compare_upcs(source_album, destination_album) == matching::UpcComparison::Same {
+        println!(
+            "Both albums have the same UPC, the destination is likely a reissue of the same release"
+        );
+    }
+    if let Some(earliest) = source_album
+        .tracks
+        .iter()
+        .filter_map(|track| track.date_added.as_deref())
+        .min()
+    {
+        println!(
+            "Warning: migrating resets when the tracks were added to the library (the earliest on \
+             {}), the original dates are kept in the history and `library export` snapshots",
+            output::format_date(earliest),
+        );
+    }
+    println!();
+
+    if summary_only {
+        let (matched, unmatched) = library_track_counts(report);
+        println!(
+            "{matched} of {} library tracks will migrate, {unmatched} unmatched",
+            matched + unmatched,
+        );
+        return;
+    }
+
+    let source_multi_disc = source_album.tracks.iter().any(|t| t.disc_number > 1);
+    let destination_multi_disc = destination_album.tracks.iter().any(|t| t.disc_number > 1);
+    let mut matched = Vec::new();
+    let mut unmatched = Vec::new();
+
+    for result in &report.results {
+        match result {
+            matching::TrackMatchResult::Match {
+                source,
+                destination,
+                method,
+                normalized,
+                warnings,
+            } => {
+                if source.library_id.is_none() {
+                    continue;
+                }
+                let src_num = output::format_position(
+                    source.disc_number,
+                    source.track_number,
+                    source_multi_disc,
+                );
+                let dst_num = output::format_position(
+                    destination.disc_number,
+                    destination.track_number,
+                    destination_multi_disc,
+                );
+                matched.push((
+                    src_num,
+                    source,
+                    dst_num,
+                    destination,
+                    method,
+                    normalized,
+                    warnings,
+                ));
+            }
+            matching::TrackMatchResult::NoMatch { source } => {
+                if source.library_id.is_none() {
+                    continue;
+                }
+                let src_num = output::format_position(
+                    source.disc_number,
+                    source.track_number,
+                    source_multi_disc,
+                );
+                unmatched.push((src_num, *source));
+            }
+        }
+    }
+
+    let width = output::terminal_width();
+    let arrow = output::arrow();
+    if !matched.is_empty() {
+        println!("Matched tracks:");
+        for (src_num, source, dst_num, destination, method, normalized, warnings) in &matched {
+            // Only worth pointing out when the versions differ
+            let (src_rating, dst_rating) = if source.content_rating == destination.content_rating {
+                ("", "")
+            } else {
+                (
+                    output::format_content_rating(source.content_rating.as_ref()),
+                    output::format_content_rating(destination.content_rating.as_ref()),
+                )
+            };
+            // ISRC matches are the norm, so only the others are labelled
+            let method = match (method, normalized) {
+                (matching::MatchMethod::Isrc, _) => String::new(),
+                (method, false) => format!(" (by {method})"),
+                (method, true) => format!(" (by {method} after rewrite rules)"),
+            };
+            let method = if destination_in_library.contains(&destination.catalog_id) {
+                format!("{method} [already in library]")
+            } else {
+                method
+            };
+            let suffix = format!(
+                "{method}{}",
+                output::format_date_added(source.date_added.as_deref()),
+            );
+            if source.name == destination.name && source.artist_name == destination.artist_name {
+                let other =
+                    format!("  #{src_num}{src_rating} {arrow} #{dst_num}{dst_rating} {suffix}");
+                let [name] = output::fit_names([&source.name], other.chars().count(), width);
+                println!("  #{src_num}{src_rating} {arrow} #{dst_num}{dst_rating} {name}{suffix}");
+            } else {
+                let other =
+                    format!("  #{src_num} {src_rating} {arrow} #{dst_num} {dst_rating}{suffix}");
+                let [source_name, destination_name] = output::fit_names(
+                    [&source.name, &destination.name],
+                    other.chars().count(),
+                    width,
+                );
+                println!(
+                    "  #{src_num} {source_name}{src_rating} {arrow} #{dst_num} {destination_name}{dst_rating}{suffix}",
+                );
+            }
+            if show_ids {
+                println!(
+                    "    IDs: {} {arrow} {}",
+                    format_ids(
+                        &source.catalog_id,
+                        source.library_id.as_ref(),
+                        source.isrc.as_deref()
+                    ),
+                    format_ids(&destination.catalog_id, None, destination.isrc.as_deref()),
+                );
+            }
+            for warning in *warnings {
+                println!("    Warning: {warning}");
+            }
+        }
+    }
+
+    if !unmatched.is_empty() {
+        if !matched.is_empty() {
+            println!();
+        }
+        println!("Unmatched tracks (in library, no match in destination):");
+        for (src_num, source) in &unmatched {
+            let src_rating = output::format_content_rating(source.content_rating.as_ref());
+            let added = output::format_date_added(source.date_added.as_deref());
+            let other = format!("  #{src_num} {src_rating}{added}");
+            let [name] = output::fit_names([&source.name], other.chars().count(), width);
+            println!("  #{src_num} {name}{src_rating}{added}");
+            if show_ids {
+                println!(
+                    "    IDs: {}",
+                    format_ids(
+                        &source.catalog_id,
+                        source.library_id.as_ref(),
+                        source.isrc.as_deref()
+                    ),
+                );
+            }
+        }
+    }
+
+    if matched.is_empty() && unmatched.is_empty() {
+        println!("No tracks in the library to migrate.");
+    }
+}
+
+/// `catalog 1, library i.1, ISRC USAAA0000001` for `--show-ids`
+fn format_ids(
+    catalog_id: &CatalogSongId,
+    library_id: Option<&LibrarySongId>,
+    isrc: Option<&str>,
+) -> String {
+    let mut ids = format!("catalog {catalog_id}");
+    if let Some(library_id) = library_id {
+        ids += &format!(", library {library_id}");
+    }
+    ids += &format!(", ISRC {}", isrc.unwrap_or("none"));
+    ids
+}
+
+/// For `--fail-on-unmatched`, which refuses to migrate if any track would be dropped from the
+/// library
+pub fn ensure_all_matched(report: &matching::MatchReport) -> Result<()> {
+    let unmatched = report.unmatched_in_library();
+    ensure!(
+        unmatched.is_empty(),
+        "{} tracks in the library have no match in the destination: {}",
+        unmatched.len(),
+        unmatched
+            .iter()
+            .map(|track| format!("\"{}\"", track.name))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    Ok(())
+}
+
+/// For `--upgrade-to-explicit`, which refuses to migrate unless every track in the library would
+/// end up explicit
+pub fn ensure_all_explicit(report: &matching::MatchReport) -> Result<()> {
+    let remaining: Vec<_> = report
+        .results
+        .iter()
+        .filter_map(|result| match result {
+            matching::TrackMatchResult::Match {
+                source,
+                destination,
+                ..
+            } if source.library_id.is_some() => match &destination.content_rating {
+                Some(ContentRating::Explicit) => None,
+                Some(ContentRating::Clean) => Some((*source, "the match is clean")),
+                Some(ContentRating::Other(_)) => {
+                    Some((*source, "the match has an unknown content rating"))
+                }
+                None => Some((*source, "the match has no content rating")),
+            },
+            matching::TrackMatchResult::NoMatch { source } if source.library_id.is_some() => {
+                Some((*source, "no match in the destination"))
+            }
+            _ => None,
+        })
+        .collect();
+    ensure!(
+        remaining.is_empty(),
+        "{} tracks in the library wouldn't be explicit after migrating: {}",
+        remaining.len(),
+        remaining
+            .iter()
+            .map(|(track, reason)| format!("\"{}\" ({reason})", track.name))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    Ok(())
+}
+
+/// Ask a yes or no question on the terminal, anything but `y` or `yes` is no
+pub async fn confirm(prompt: &str) -> Result<bool> {
+    print!("{prompt} [y/N] ");
+    std::io::stdout().flush()?;
+    // Read off the runtime's thread so that Ctrl-C is still handled while waiting
+    let answer = tokio::task::spawn_blocking(|| {
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer).map(|_| answer)
+    })
+    .await??;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// The matched destination songs that need adding
+fn not_in_library<'a>(
+    matched: &[&'a CatalogSongId],
+    destination_in_library: &HashSet<CatalogSongId>,
+) -> Vec<&'a CatalogSongId> {
+    matched
+        .iter()
+        .copied()
+        .filter(|catalog_id| !destination_in_library.contains(*catalog_id))
+        .collect()
+}
+
+/// What the migration changed in the library as a unified diff, each source track in the library
+/// is removed and replaced by the destination track it matched, if any
+fn print_library_diff(
+    source_album: &Album<TrackWithLibrary>,
+    destination_album: &Album<TrackNoLibrary>,
+    report: &matching::MatchReport,
+) {
+    let color = output::use_color();
+    let source_multi_disc = source_album.tracks.iter().any(|t| t.disc_number > 1);
+    let destination_multi_disc = destination_album.tracks.iter().any(|t| t.disc_number > 1);
+    println!(
+        "{}",
+        output::format_diff_line("---", &source_album.name, color),
+    );
+    println!(
+        "{}",
+        output::format_diff_line("+++", &destination_album.name, color),
+    );
+    for result in &report.results {
+        let (source, destination) = match result {
+            matching::TrackMatchResult::Match {
+                source,
+                destination,
+                ..
+            } => (source, Some(destination)),
+            matching::TrackMatchResult::NoMatch { source } => (source, None),
+        };
+        if source.library_id.is_none() {
+            continue;
+        }
+        let line = format!(
+            "#{} {}",
+            output::format_position(source.disc_number, source.track_number, source_multi_disc),
+            source.name,
+        );
+        println!("{}", output::format_diff_line("-", &line, color));
+        if let Some(destination) = destination {
+            let line = format!(
+                "#{} {}",
+                output::format_position(
+                    destination.disc_number,
+                    destination.track_number,
+                    destination_multi_disc,
+                ),
+                destination.name,
+            );
+            println!("{}", output::format_diff_line("+", &line, color));
+        }
+    }
+}
+
+/// Remove the source album from the library and add the matched destination tracks, then print
+/// what changed, only the number of tracks if `summary_only`. `destination_catalog_ids` are the
+/// albums combined into `destination_album`, with more than one the tracks are added one by one
+/// even if they all matched. With `carry_favorite`, the destination albums are marked as
+/// favorites if the source album was.
+#[allow(clippy::too_many_arguments)]
+pub async fn execute(
+    client: &apple_music::Client,
+    source_album_library_id: &LibraryAlbumId,
+    source_album: &Album<TrackWithLibrary>,
+    destination_album: &Album<TrackNoLibrary>,
+    report: &matching::MatchReport<'_>,
+    destination_in_library: &HashSet<CatalogSongId>,
+    destination_catalog_ids: &[CatalogAlbumId],
+    summary_only: bool,
+    carry_favorite: bool,
+) -> Result<()> {
+    let matched = report.songs_to_add();
+    ensure!(!matched.is_empty(), "no tracks to migrate");
+    // Checked before the source album is removed, which removes its rating too
+    let favorite = carry_favorite
+        && client
+            .is_library_album_favorite(source_album_library_id)
+            .await
+            .unwrap_or_else(|error| {
+                eprintln!(
+                    "Warning: failed to check whether the source album is a favorite: {error:#}"
+                );
+                false
+            });
+    let songs_to_add = not_in_library(&matched, destination_in_library);
+    // Adding the album rather than every one of its songs makes it a proper library album
+    let add_whole_album =
+        destination_catalog_ids.len() == 1 && matched.len() == destination_album.tracks.len();
+    let destination_ids = destination_catalog_ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    let pending = history::Pending {
+        source_library_id: source_album_library_id.to_string(),
+        source_catalog_id: source_album.catalog_id.to_string(),
+        destination_catalog_ids: destination_ids.clone(),
+        destination_name: destination_album.name.clone(),
+        songs_to_add: songs_to_add.iter().map(ToString::to_string).collect(),
+        whole_album: add_whole_album,
+    };
+    let mut history = history::History::open().and_then(|mut history| {
+        history.begin(&pending)?;
+        Ok(history)
+    });
+    if let Err(error) = &history {
+        eprintln!(
+            "Warning: failed to record the migration as started, running it again won't finish \
+             it if adding the destination fails: {error:#}"
+        );
+    }
+
+    // Stopping between removing the source and adding the destination would lose the album
+    let _deferred = interrupt::defer();
+    let remove_span = telemetry::stage("remove source album");
+    if let Err(error) = client
+        .remove_album_from_library(source_album_library_id)
+        .await
+    {
+        if let Ok(history) = &mut history {
+            // Best effort, a leftover is dropped when the source is found still in the library
+            let _ = history.finish(&pending);
+        }
+        return Err(error.context("failed to remove the source album, the library is unchanged"));
+    }
+    remove_span.end();
+
+    let add_span = telemetry::stage("add destination album");
+    let added = if songs_to_add.is_empty() {
+        // The destination album is already in the library
+        Ok(())
+    } else if add_whole_album {
+        client
+            .add_album_to_library(&destination_album.catalog_id)
+            .await
+    } else {
+        add_songs(client, &songs_to_add).await
+    };
+    add_span.result(added).with_context(|| {
+        if history.is_ok() {
+            "the source album was removed but adding the destination failed, run the same command \
+             again to finish the migration"
+                .to_owned()
+        } else {
+            format!(
+                "the source album was removed but adding the destination failed, add \"{}\" ({}) \
+                 to the library manually",
+                destination_album.name,
+                destination_ids.join(", "),
+            )
+        }
+    })?;
+
+    if summary_only {
+        let (matched, unmatched) = library_track_counts(report);
+        println!(
+            "Migrated {matched} of {} library tracks to \"{}\", {unmatched} unmatched",
+            matched + unmatched,
+            destination_album.name,
+        );
+    } else {
+        print_library_diff(source_album, destination_album, report);
+    }
+    if favorite {
+        let mut failed = false;
+        for catalog_id in destination_catalog_ids {
+            if let Err(error) = client.favorite_album(catalog_id).await {
+                eprintln!(
+                    "Warning: failed to mark album {catalog_id} as a favorite like the source \
+                     album: {error:#}",
+                );
+                failed = true;
+            }
+        }
+        if !failed {
+            println!(
+                "Marked \"{}\" as a favorite like the source album",
+                destination_album.name,
+            );
+        }
+    }
+
+    let migration = history::Migration::new(
+        source_album_library_id,
+        source_album,
+        destination_album,
+        destination_catalog_ids,
+        songs_to_add.len(),
+    );
+    match history.and_then(|mut history| {
+        let id = history.record(&migration, &history::track_mappings(report))?;
+        history.finish(&pending)?;
+        Ok(id)
+    }) {
+        Ok(id) => {
+            println!();
+            println!("Recorded as migration #{id} (`history show {id}` for details)");
+        }
+        Err(error) => {
+            eprintln!("Warning: failed to record the migration in the history: {error:#}")
+        }
+    }
+    Ok(())
+}
+
+enum Outcome {
+    /// Or would be in a dry run
+    Migrated,
+    Current,
+    Skipped,
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn migrate_to_newest_edition(
+    client: &apple_music::Client,
+    library_id: &LibraryAlbumId,
+    options: &matching::MatchOptions,
+    dry_run: bool,
+    fail_on_unmatched: bool,
+    summary_only: bool,
+    carry_favorite: bool,
+    progress: &Progress,
+) -> Result<Outcome> {
+    let source_album = load_source(client, library_id).await?;
+    match discovery::find_edition(client, &source_album, options).await? {
+        discovery::Edition::Current => {
+            println!(
+                "\"{}\" is already the most complete edition",
+                source_album.name,
+            );
+            Ok(Outcome::Current)
+        }
+        discovery::Edition::Incomplete { album, missing } => {
+            println!(
+                "Skipping \"{}\": \"{}\" ({}) has more tracks but is missing {}",
+                source_album.name,
+                album.name,
+                album.catalog_id,
+                missing.join(", "),
+            );
+            Ok(Outcome::Skipped)
+        }
+        discovery::Edition::Newer(destination_album) => {
+            let report = matching::match_tracks(&source_album, &destination_album, options)?;
+            let matched = report.songs_to_add();
+            progress.emit(Event::Matched {
+                library_id,
+                destination_catalog_id: &destination_album.catalog_id,
+                matched: matched.len(),
+                unmatched: report.unmatched_in_library().len(),
+            });
+            let destination_in_library =
+                destination_tracks_in_library(client, &destination_album).await?;
+            if dry_run {
+                print_plan(
+                    &source_album,
+                    &destination_album,
+                    &report,
+                    &destination_in_library,
+                    summary_only,
+                    false,
+                );
+            }
+            if fail_on_unmatched {
+                ensure_all_matched(&report)?;
+            }
+            if !dry_run {
+                progress.emit(Event::AddingSongs {
+                    library_id,
+                    count: not_in_library(&matched, &destination_in_library).len(),
+                });
+                execute(
+                    client,
+                    library_id,
+                    &source_album,
+                    &destination_album,
+                    &report,
+                    &destination_in_library,
+                    std::slice::from_ref(&destination_album.catalog_id),
+                    summary_only,
+                    carry_favorite,
+                )
+                .await?;
+            }
+            Ok(Outcome::Migrated)
+        }
+    }
+}
+
+/// Migrate every album by the artist in the library to its most complete catalog edition
+#[allow(clippy::too_many_arguments)]
+pub async fn migrate_artist(
+    client: &apple_music::Client,
+    artist_name: &str,
+    options: &matching::MatchOptions,
+    dry_run: bool,
+    fail_on_unmatched: bool,
+    summary_only: bool,
+    carry_favorite: bool,
+    progress: &Progress,
+) -> Result<()> {
+    let library_ids: Vec<LibraryAlbumId> = client
+        .library_albums()
+        .try_filter(|album| std::future::ready(album.artist_name.eq_ignore_ascii_case(artist_name)))
+        .map_ok(|album| album.library_id)
+        .try_collect()
+        .await?;
+    ensure!(
+        !library_ids.is_empty(),
+        "no albums by {artist_name} in the library",
+    );
+
+    let _deferred = interrupt::defer();
+    let (mut migrated, mut current, mut skipped, mut failed) = (0, 0, 0, 0);
+    let mut not_started: &[LibraryAlbumId] = &[];
+    for (i, library_id) in library_ids.iter().enumerate() {
+        if interrupt::interrupted() {
+            not_started = &library_ids[i..];
+            break;
+        }
+        if i > 0 {
+            println!();
+        }
+        println!("[{}/{}] {library_id}", i + 1, library_ids.len());
+        progress.emit(Event::FetchStarted {
+            library_id,
+            index: i + 1,
+            total: library_ids.len(),
+        });
+        let outcome = migrate_to_newest_edition(
+            client,
+            library_id,
+            options,
+            dry_run,
+            fail_on_unmatched,
+            summary_only,
+            carry_favorite,
+            progress,
+        )
+        .await;
+        let outcome = match outcome {
+            Ok(Outcome::Migrated) => {
+                migrated += 1;
+                "migrated"
+            }
+            Ok(Outcome::Current) => {
+                current += 1;
+                "current"
+            }
+            Ok(Outcome::Skipped) => {
+                skipped += 1;
+                "skipped"
+            }
+            Err(error) => {
+                println!("Failed: {error:#}");
+                progress.emit(Event::Error {
+                    library_id,
+                    message: format!("{error:#}"),
+                });
+                failed += 1;
+                continue;
+            }
+        };
+        progress.emit(Event::PairDone {
+            library_id,
+            outcome,
+            dry_run,
+        });
+    }
+
+    println!();
+    if dry_run {
+        println!(
+            "{migrated} albums would be migrated, {current} already current, {skipped} skipped, {failed} failed",
+        );
+    } else {
+        println!(
+            "Migrated {migrated} albums, {current} already current, {skipped} skipped, {failed} failed",
+        );
+    }
+    if let Some(rate_limits) = client.rate_limits() {
+        println!("Rate limits: {rate_limits}");
+    }
+    if !not_started.is_empty() {
+        println!(
+            "Interrupted, every album above was finished and these weren't started: {}",
+            not_started.join(", "),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn album(catalog_id: &str, name: &str, discs: &[u8]) -> Album<TrackNoLibrary> {
+        Album {
+            catalog_id: catalog_id.parse().unwrap(),
+            name: name.to_owned(),
+            artist_name: "Artist".to_owned(),
+            genre_names: vec![],
+            release_date: Some("2020-01-01".to_owned()),
+            upc: Some(format!("0{catalog_id}")),
+            audio_variants: vec!["lossless".to_owned()],
+            apple_digital_master: false,
+            tracks: discs
+                .iter()
+                .enumerate()
+                .map(|(i, &disc_number)| TrackNoLibrary {
+                    catalog_id: format!("{catalog_id}{i}").parse().unwrap(),
+                    name: format!("Song {i}"),
+                    artist_name: "Artist".to_owned(),
+                    genre_names: vec![],
+                    disc_number,
+                    track_number: i as u8 + 1,
+                    content_rating: None,
+                    isrc: None,
+                    release_date: None,
+                    duration: None,
+                    playable: true,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_ensure_all_explicit() {
+        let mut destination = album("10", "Album", &[1, 1, 1]);
+        destination.tracks[0].content_rating = Some(ContentRating::Explicit);
+        destination.tracks[1].content_rating = Some(ContentRating::Clean);
+        let sources: Vec<_> = destination
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| TrackWithLibrary {
+                catalog_id: format!("2{i}").parse().unwrap(),
+                name: track.name.clone(),
+                artist_name: track.artist_name.clone(),
+                genre_names: vec![],
+                disc_number: track.disc_number,
+                track_number: track.track_number,
+                content_rating: Some(ContentRating::Clean),
+                isrc: None,
+                release_date: None,
+                duration: None,
+                library_id: Some(format!("i.{i}").parse().unwrap()),
+                date_added: None,
+            })
+            .collect();
+        let matched = |i: usize| matching::TrackMatchResult::Match {
+            source: &sources[i],
+            destination: &destination.tracks[i],
+            method: matching::MatchMethod::Isrc,
+            normalized: false,
+            warnings: vec![],
+        };
+
+        let report = matching::MatchReport {
+            results: vec![matched(0)],
+        };
+        assert!(ensure_all_explicit(&report).is_ok());
+
+        let report = matching::MatchReport {
+            results: vec![
+                matched(0),
+                matched(1),
+                matched(2),
+                matching::TrackMatchResult::NoMatch {
+                    source: &sources[0],
+                },
+            ],
+        };
+        assert_eq!(
+            ensure_all_explicit(&report).unwrap_err().to_string(),
+            "3 tracks in the library wouldn't be explicit after migrating: \"Song 1\" (the match \
+             is clean), \"Song 2\" (the match has no content rating), \"Song 0\" (no match in \
+             the destination)",
+        );
+    }
+
+    #[test]
+    fn test_combine_destinations() {
+        let combined = combine_destinations(vec![
+            album("10", "Box Set, Vol. 1", &[1, 2]),
+            album("20", "Box Set, Vol. 2", &[1]),
+        ])
+        .unwrap();
+        assert_eq!(combined.catalog_id, "10");
+        assert_eq!(combined.name, "Box Set, Vol. 1 + Box Set, Vol. 2");
+        assert_eq!(combined.upc, None);
+        assert_eq!(combined.audio_variants, ["lossless"]);
+        assert_eq!(
+            combined
+                .tracks
+                .iter()
+                .map(|track| (track.catalog_id.as_str(), track.disc_number))
+                .collect::<Vec<_>>(),
+            [("100", 1), ("101", 2), ("200", 3)],
+        );
+
+        let single = combine_destinations(vec![album("10", "Album", &[1])]).unwrap();
+        assert_eq!(single.upc.as_deref(), Some("010"));
+        assert!(
+            combine_destinations(vec![album("10", "Album", &[1]), album("10", "Album", &[1])])
+                .is_err()
+        );
+    }
+}
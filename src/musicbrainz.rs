@@ -0,0 +1,161 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tokio::time::Instant;
+
+use crate::{
+    apple_music::{self, CatalogSongId},
+    matching,
+};
+
+const BASE_URL: &str = "https://musicbrainz.org/ws/2";
+
+/// MusicBrainz asks for applications to identify themselves with contact information
+const USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/nihaals/apple-music-album-library-migrator )",
+);
+
+/// MusicBrainz allows an average of one request per second
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Search results below this score (out of 100) are usually different recordings
+const MIN_SCORE: u8 = 90;
+
+/// Maximum number of ISRCs in a single song lookup
+const ISRCS_PER_REQUEST: usize = 25;
+
+#[derive(Deserialize)]
+struct RecordingSearch {
+    recordings: Vec<Recording>,
+}
+
+#[derive(Deserialize)]
+struct Recording {
+    score: u8,
+    #[serde(default)]
+    isrcs: Vec<String>,
+}
+
+pub struct Client {
+    client: reqwest::Client,
+    last_request: Cell<Option<Instant>>,
+}
+
+/// Quote a value as a Lucene phrase
+fn lucene_phrase(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Client {
+    pub fn new() -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            client,
+            last_request: Cell::new(None),
+        })
+    }
+
+    /// ISRCs of recordings with the same title and artist
+    pub async fn recording_isrcs(&self, name: &str, artist_name: &str) -> Result<Vec<String>> {
+        if let Some(last_request) = self.last_request.get() {
+            tokio::time::sleep_until(last_request + REQUEST_INTERVAL).await;
+        }
+        self.last_request.set(Some(Instant::now()));
+
+        let query = format!(
+            "recording:{} AND artist:{}",
+            lucene_phrase(name),
+            lucene_phrase(artist_name),
+        );
+        let url = reqwest::Url::parse_with_params(
+            &format!("{BASE_URL}/recording"),
+            [("query", query.as_str()), ("fmt", "json"), ("limit", "10")],
+        )?;
+        let search: RecordingSearch = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("MusicBrainz search for \"{name}\" failed"))?;
+
+        let mut seen = HashSet::new();
+        Ok(search
+            .recordings
+            .into_iter()
+            .filter(|recording| recording.score >= MIN_SCORE)
+            .flat_map(|recording| recording.isrcs)
+            .filter(|isrc| seen.insert(isrc.clone()))
+            .collect())
+    }
+}
+
+/// For every source track in the library that isn't matched by ISRC, look up the other ISRCs
+/// MusicBrainz has for the recording and which catalog songs in the storefront have them
+pub async fn find_matches(
+    musicbrainz: &Client,
+    client: &apple_music::Client,
+    report: &matching::MatchReport<'_>,
+) -> Result<HashMap<CatalogSongId, HashSet<CatalogSongId>>> {
+    let mut matches = HashMap::new();
+    for result in &report.results {
+        let source = match result {
+            matching::TrackMatchResult::NoMatch { source } => source,
+            matching::TrackMatchResult::Match {
+                source,
+                method: matching::MatchMethod::NameArtist | matching::MatchMethod::Fuzzy { .. },
+                ..
+            } => source,
+            matching::TrackMatchResult::Match { .. } => continue,
+        };
+        if source.library_id.is_none() {
+            continue;
+        }
+
+        let isrcs = musicbrainz
+            .recording_isrcs(&source.name, &source.artist_name)
+            .await?;
+        let isrcs: Vec<&str> = isrcs
+            .iter()
+            .map(String::as_str)
+            .filter(|isrc| Some(*isrc) != source.isrc.as_deref())
+            .collect();
+        let mut catalog_ids = HashSet::new();
+        for chunk in isrcs.chunks(ISRCS_PER_REQUEST) {
+            let songs = client
+                .get_catalog_songs_by_isrc(client.storefront(), chunk)
+                .await?;
+            catalog_ids.extend(songs.ids().map(str::parse).collect::<Result<Vec<_>>>()?);
+        }
+        if !catalog_ids.is_empty() {
+            matches.insert(source.catalog_id.clone(), catalog_ids);
+        }
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lucene_phrase() {
+        assert_eq!(lucene_phrase("Song"), "\"Song\"");
+        assert_eq!(lucene_phrase("12\" \\ Mix"), "\"12\\\" \\\\ Mix\"");
+    }
+}
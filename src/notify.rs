@@ -0,0 +1,68 @@
+//! Desktop notifications for people who switch windows during long runs
+
+use std::{process::Command, time::Duration};
+
+const TITLE: &str = env!("CARGO_PKG_NAME");
+
+/// Quote a value as an AppleScript string
+fn applescript_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    if seconds >= 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+fn message<T>(elapsed: Duration, result: &anyhow::Result<T>) -> String {
+    let elapsed = format_elapsed(elapsed);
+    match result {
+        Ok(_) => format!("Finished after {elapsed}"),
+        Err(error) => format!("Failed after {elapsed}: {error:#}"),
+    }
+}
+
+/// Best effort, a missing notifier only prints a warning
+pub fn finished<T>(elapsed: Duration, result: &anyhow::Result<T>) {
+    let message = message(elapsed, result);
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            applescript_string(&message),
+            applescript_string(TITLE),
+        ));
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.arg(TITLE).arg(&message);
+        command
+    };
+    match command.status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Warning: desktop notification failed ({status})"),
+        Err(error) => eprintln!("Warning: desktop notification failed: {error}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        assert_eq!(
+            message(Duration::from_secs(125), &Ok(())),
+            "Finished after 2m 5s"
+        );
+        assert_eq!(
+            message::<()>(Duration::from_secs(9), &Err(anyhow::anyhow!("no tracks"))),
+            "Failed after 9s: no tracks",
+        );
+        assert_eq!(applescript_string("a \"b\" \\"), "\"a \\\"b\\\" \\\\\"");
+    }
+}
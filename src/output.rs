@@ -0,0 +1,308 @@
+use std::{
+    borrow::Cow,
+    io::IsTerminal,
+    sync::{
+        LazyLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use chrono::TimeDelta;
+
+use crate::apple_music::custom_types::ContentRating;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+/// Set by `--ascii`
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Names are never shortened to fewer characters than this, however narrow the terminal
+const MIN_NAME_WIDTH: usize = 12;
+
+static TERMINAL_WIDTH: LazyLock<Option<usize>> = LazyLock::new(|| {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .or_else(window_columns)
+});
+
+#[cfg(unix)]
+fn window_columns() -> Option<usize> {
+    use std::os::fd::AsRawFd;
+
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: `TIOCGWINSZ` only writes a `winsize` through the pointer
+    let result = unsafe { libc::ioctl(std::io::stdout().as_raw_fd(), libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0).then_some(size.ws_col.into())
+}
+
+#[cfg(not(unix))]
+fn window_columns() -> Option<usize> {
+    None
+}
+
+/// Use ASCII equivalents in place of `→` and `…`, not in names
+pub fn set_ascii(ascii: bool) {
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+/// `→`, or `->` with `--ascii`
+pub fn arrow() -> &'static str {
+    if ASCII.load(Ordering::Relaxed) {
+        "->"
+    } else {
+        "\u{2192}"
+    }
+}
+
+/// `…`, or `...` with `--ascii`
+pub fn ellipsis() -> &'static str {
+    if ASCII.load(Ordering::Relaxed) {
+        "..."
+    } else {
+        "\u{2026}"
+    }
+}
+
+/// The width of the terminal in columns, `None` if stdout isn't a terminal so that piped output
+/// is never shortened
+pub fn terminal_width() -> Option<usize> {
+    *TERMINAL_WIDTH
+}
+
+/// `name` cut to `width` characters ending in [`ellipsis`]
+fn truncate(name: &str, width: usize) -> Cow<'_, str> {
+    if name.chars().count() <= width {
+        return Cow::Borrowed(name);
+    }
+    let ellipsis = ellipsis();
+    let mut truncated: String = name
+        .chars()
+        .take(width.saturating_sub(ellipsis.chars().count()))
+        .collect();
+    truncated.push_str(ellipsis);
+    Cow::Owned(truncated)
+}
+
+/// The names shortened so that a line with `other_width` characters besides them fits in `width`.
+/// The space is shared so that short names are kept whole and only the longest are cut. Widths
+/// are counted in characters, so lines with wide characters such as CJK can still wrap.
+pub fn fit_names<'a, const N: usize>(
+    names: [&'a str; N],
+    other_width: usize,
+    width: Option<usize>,
+) -> [Cow<'a, str>; N] {
+    let Some(width) = width else {
+        return names.map(Cow::Borrowed);
+    };
+    let mut remaining = width.saturating_sub(other_width).max(N * MIN_NAME_WIDTH);
+    let lengths = names.map(|name| name.chars().count());
+    let mut by_length: Vec<usize> = (0..N).collect();
+    by_length.sort_by_key(|&index| lengths[index]);
+    let mut limits = [0; N];
+    for (placed, &index) in by_length.iter().enumerate() {
+        let share = remaining / (N - placed);
+        limits[index] = lengths[index].min(share);
+        remaining -= limits[index];
+    }
+    let mut index = 0;
+    names.map(|name| {
+        index += 1;
+        truncate(name, limits[index - 1])
+    })
+}
+
+/// `2d 3h`, `3h 5m` or `5m`
+pub fn format_duration(duration: TimeDelta) -> String {
+    let days = duration.num_days();
+    let hours = duration.num_hours() % 24;
+    let minutes = duration.num_minutes() % 60;
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// `3` for single-disc albums and `2-3` (disc 2, track 3) for multi-disc albums
+pub fn format_position(disc_number: u8, track_number: u8, multi_disc: bool) -> String {
+    if multi_disc {
+        format!("{disc_number}-{track_number}")
+    } else {
+        track_number.to_string()
+    }
+}
+
+/// ` [E]` or ` [C]` for appending to a track name, ` [?]` for a rating this doesn't know and empty
+/// for tracks without a content rating
+pub fn format_content_rating(content_rating: Option<&ContentRating>) -> &'static str {
+    match content_rating {
+        Some(ContentRating::Explicit) => " [E]",
+        Some(ContentRating::Clean) => " [C]",
+        Some(ContentRating::Other(_)) => " [?]",
+        None => "",
+    }
+}
+
+/// Comma-separated genres without the generic `Music` genre, `None` if there are no others
+pub fn format_genres(genre_names: &[String]) -> Option<String> {
+    let genres: Vec<&str> = genre_names
+        .iter()
+        .map(String::as_str)
+        .filter(|genre| *genre != "Music")
+        .collect();
+    (!genres.is_empty()).then(|| genres.join(", "))
+}
+
+/// The `YYYY-MM-DD` part of an ISO 8601 date and time
+pub fn format_date(date_time: &str) -> &str {
+    date_time.get(..10).unwrap_or(date_time)
+}
+
+/// `TBA` for albums that haven't had their release date announced
+pub fn format_release_date(release_date: Option<&str>) -> &str {
+    release_date.unwrap_or("TBA")
+}
+
+/// ` [in library since 2020-02-03]` for appending to a source track, empty without a date
+pub fn format_date_added(date_added: Option<&str>) -> String {
+    date_added
+        .map(|date_added| format!(" [in library since {}]", format_date(date_added)))
+        .unwrap_or_default()
+}
+
+/// The lossless, hi-res and Dolby Atmos badges from an album's audio variants and the Apple
+/// Digital Master badge, comma-separated, `None` if it has none of them
+pub fn format_audio_badges(
+    audio_variants: &[String],
+    apple_digital_master: bool,
+) -> Option<String> {
+    let mut badges: Vec<&str> = [
+        ("lossless", "Lossless"),
+        ("hi-res-lossless", "Hi-Res Lossless"),
+        ("dolby-atmos", "Dolby Atmos"),
+    ]
+    .into_iter()
+    .filter(|(variant, _)| audio_variants.iter().any(|v| v == variant))
+    .map(|(_, badge)| badge)
+    .collect();
+    if apple_digital_master {
+        badges.push("Apple Digital Master");
+    }
+    (!badges.is_empty()).then(|| badges.join(", "))
+}
+
+/// `, UPC 012345678905` for appending to an album summary, empty if there is no UPC
+pub fn format_upc(upc: Option<&str>) -> String {
+    upc.map(|upc| format!(", UPC {upc}")).unwrap_or_default()
+}
+
+/// Whether stdout is a terminal and `NO_COLOR` isn't set
+pub fn use_color() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+/// A line of a unified diff, `-` and `---` lines in red and `+` and `+++` lines in green if `color`
+pub fn format_diff_line(prefix: &str, line: &str, color: bool) -> String {
+    let color = match prefix.chars().next() {
+        Some('-') if color => RED,
+        Some('+') if color => GREEN,
+        _ => return format!("{prefix} {line}"),
+    };
+    format!("{color}{prefix} {line}{RESET}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_genres() {
+        assert_eq!(
+            format_genres(&["Pop".to_owned(), "Music".to_owned(), "Rock".to_owned()]),
+            Some("Pop, Rock".to_owned()),
+        );
+        assert_eq!(format_genres(&["Music".to_owned()]), None);
+        assert_eq!(format_genres(&[]), None);
+    }
+
+    #[test]
+    fn test_format_audio_badges() {
+        assert_eq!(
+            format_audio_badges(
+                &[
+                    "dolby-atmos".to_owned(),
+                    "lossy-stereo".to_owned(),
+                    "lossless".to_owned(),
+                ],
+                false
+            ),
+            Some("Lossless, Dolby Atmos".to_owned()),
+        );
+        assert_eq!(
+            format_audio_badges(&["lossy-stereo".to_owned()], false),
+            None
+        );
+        assert_eq!(
+            format_audio_badges(&[], true),
+            Some("Apple Digital Master".to_owned()),
+        );
+    }
+
+    #[test]
+    fn test_format_date_added() {
+        assert_eq!(
+            format_date_added(Some("2020-02-03T10:20:30Z")),
+            " [in library since 2020-02-03]"
+        );
+        assert_eq!(
+            format_date_added(Some("2020-02-03")),
+            " [in library since 2020-02-03]"
+        );
+        assert_eq!(format_date_added(None), "");
+    }
+
+    #[test]
+    fn test_fit_names() {
+        let long = "Symphony No. 9 in D Minor, Op. 125: IV. Presto";
+        assert_eq!(fit_names([long], 10, None), [long]);
+        assert_eq!(fit_names([long], 10, Some(80)), [long]);
+        assert_eq!(
+            fit_names([long], 10, Some(40)),
+            ["Symphony No. 9 in D Minor, Op\u{2026}"],
+        );
+        assert_eq!(
+            fit_names(["Intro", long], 10, Some(40)),
+            [
+                "Intro".into(),
+                Cow::from("Symphony No. 9 in D Mino\u{2026}")
+            ],
+        );
+        assert_eq!(
+            fit_names([long, long], 80, Some(40)),
+            ["Symphony No\u{2026}", "Symphony No\u{2026}"],
+        );
+    }
+
+    #[test]
+    fn test_format_diff_line() {
+        assert_eq!(format_diff_line("-", "#1 Song", false), "- #1 Song");
+        assert_eq!(
+            format_diff_line("+", "#1 Song", true),
+            "\x1b[32m+ #1 Song\x1b[0m"
+        );
+        assert_eq!(format_diff_line(" ", "#1 Song", true), "  #1 Song");
+    }
+}
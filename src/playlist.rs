@@ -0,0 +1,191 @@
+//! Finding playlist entries that need repairing after albums were migrated or removed from the
+//! catalog
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use futures_util::TryStreamExt;
+
+use crate::{
+    apple_music::{self, CatalogSongId},
+    history, library,
+};
+
+struct Entry {
+    name: String,
+    artist_name: String,
+    /// `None` for uploaded songs, which have nothing to check
+    catalog_id: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Issue<'a> {
+    /// The song is from a migrated source album, so the playlist still has the old edition
+    Migrated {
+        destination: Option<&'a (String, String)>,
+    },
+    /// The catalog song is no longer in the storefront
+    Unavailable,
+}
+
+impl std::fmt::Display for Issue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::Migrated {
+                destination: Some((catalog_id, name)),
+            } => write!(
+                f,
+                "its album was migrated, replace it with \"{name}\" ({catalog_id})"
+            ),
+            Issue::Migrated { destination: None } => write!(
+                f,
+                "its album was migrated but it had no match on the new edition"
+            ),
+            Issue::Unavailable => write!(f, "no longer available"),
+        }
+    }
+}
+
+/// The entries with issues and their positions in the playlist
+fn find_issues<'a>(
+    entries: &[Entry],
+    available: &HashSet<String>,
+    migrated: &'a HashMap<String, Option<(String, String)>>,
+) -> Vec<(usize, Issue<'a>)> {
+    entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let catalog_id = entry.catalog_id.as_ref()?;
+            let issue = if let Some(destination) = migrated.get(catalog_id) {
+                Issue::Migrated {
+                    destination: destination.as_ref(),
+                }
+            } else if !available.contains(catalog_id) {
+                Issue::Unavailable
+            } else {
+                return None;
+            };
+            Some((i, issue))
+        })
+        .collect()
+}
+
+/// Print the playlists with songs that were migrated or are no longer available
+pub async fn audit(client: &apple_music::Client) -> Result<()> {
+    let migrated = history::History::open()?.migrated_tracks()?;
+
+    let mut playlists = Vec::new();
+    let playlist_summaries: Vec<_> = client.library_playlists().try_collect().await?;
+    for playlist in &playlist_summaries {
+        let entries: Vec<Entry> = client
+            .library_playlist_tracks(playlist.id())
+            .map_ok(|track| Entry {
+                name: track.name().to_owned(),
+                artist_name: track.artist_name().to_owned(),
+                catalog_id: track.catalog_id().map(str::to_owned),
+            })
+            .try_collect()
+            .await?;
+        playlists.push((playlist.name(), entries));
+    }
+
+    let catalog_ids: Vec<CatalogSongId> = playlists
+        .iter()
+        .flat_map(|(_, entries)| {
+            entries
+                .iter()
+                .filter_map(|entry| entry.catalog_id.as_deref())
+        })
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(str::parse)
+        .collect::<Result<_>>()?;
+    let catalog_ids: Vec<&CatalogSongId> = catalog_ids.iter().collect();
+    let mut available = HashSet::new();
+    for chunk in catalog_ids.chunks(library::IDS_PER_REQUEST) {
+        available.extend(
+            client
+                .get_catalog_songs(chunk)
+                .await?
+                .ids()
+                .map(str::to_owned),
+        );
+    }
+
+    let mut needing_repair = 0;
+    for (name, entries) in &playlists {
+        let issues = find_issues(entries, &available, &migrated);
+        if issues.is_empty() {
+            continue;
+        }
+        if needing_repair > 0 {
+            println!();
+        }
+        needing_repair += 1;
+        println!(
+            "\"{name}\" ({} of {} songs need repairing)",
+            issues.len(),
+            entries.len(),
+        );
+        for (i, issue) in &issues {
+            let entry = &entries[*i];
+            println!(
+                "  #{} \"{}\" by {}: {issue}",
+                i + 1,
+                entry.name,
+                entry.artist_name,
+            );
+        }
+    }
+
+    if needing_repair == 0 {
+        println!("None of the {} playlists need repairing", playlists.len());
+    } else {
+        println!();
+        println!(
+            "{needing_repair} of {} playlists need repairing",
+            playlists.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_issues() {
+        let entry = |catalog_id: Option<&str>| Entry {
+            name: "Song".to_owned(),
+            artist_name: "Artist".to_owned(),
+            catalog_id: catalog_id.map(str::to_owned),
+        };
+        let entries = [
+            entry(Some("1")),
+            entry(Some("2")),
+            entry(None),
+            entry(Some("3")),
+            entry(Some("4")),
+        ];
+        let available = HashSet::from(["1".to_owned(), "3".to_owned()]);
+        let migrated = HashMap::from([
+            ("3".to_owned(), Some(("30".to_owned(), "Song".to_owned()))),
+            ("4".to_owned(), None),
+        ]);
+        assert_eq!(
+            find_issues(&entries, &available, &migrated),
+            [
+                (1, Issue::Unavailable),
+                (
+                    3,
+                    Issue::Migrated {
+                        destination: migrated["3"].as_ref(),
+                    },
+                ),
+                (4, Issue::Migrated { destination: None }),
+            ],
+        );
+    }
+}
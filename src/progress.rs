@@ -0,0 +1,98 @@
+//! Machine-readable progress for scripts and GUI wrappers
+
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    FetchStarted {
+        library_id: &'a str,
+        /// From 1
+        index: usize,
+        total: usize,
+    },
+    Matched {
+        library_id: &'a str,
+        destination_catalog_id: &'a str,
+        /// Source tracks in the library with a match
+        matched: usize,
+        /// Source tracks in the library without a match
+        unmatched: usize,
+    },
+    AddingSongs {
+        library_id: &'a str,
+        count: usize,
+    },
+    PairDone {
+        library_id: &'a str,
+        /// `migrated`, `current` or `skipped`
+        outcome: &'a str,
+        dry_run: bool,
+    },
+    Error {
+        library_id: &'a str,
+        message: String,
+    },
+}
+
+/// Writes one JSON object per line for each [`Event`], or nothing if disabled
+pub struct Progress {
+    writer: Option<RefCell<Box<dyn Write>>>,
+}
+
+impl Progress {
+    pub fn disabled() -> Self {
+        Self { writer: None }
+    }
+
+    /// `-` is stderr, anything else is a file or named pipe
+    pub fn open(path: &Path) -> Result<Self> {
+        let writer: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stderr())
+        } else {
+            Box::new(
+                File::create(path).with_context(|| format!("failed to open {}", path.display()))?,
+            )
+        };
+        Ok(Self {
+            writer: Some(RefCell::new(writer)),
+        })
+    }
+
+    /// Failing to write doesn't stop a migration, the reader may have gone away
+    pub fn emit(&self, event: Event) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        let mut writer = writer.borrow_mut();
+        let _ = serde_json::to_writer(&mut *writer, &event)
+            .map_err(io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"))
+            .and_then(|()| writer.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        assert_eq!(
+            serde_json::to_string(&Event::AddingSongs {
+                library_id: "l.A",
+                count: 3,
+            })
+            .unwrap(),
+            r#"{"event":"adding_songs","library_id":"l.A","count":3}"#,
+        );
+    }
+}
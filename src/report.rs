@@ -0,0 +1,454 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail, ensure};
+use serde::Serialize;
+
+use crate::{
+    apple_music::custom_types::{Album, ContentRating, TrackNoLibrary, TrackWithLibrary},
+    matching::{self, TrackPosition},
+    output, yaml,
+};
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// Whether the report is a plan and no changes were made
+    pub dry_run: bool,
+    pub source: ReportAlbum,
+    pub destination: ReportAlbum,
+    /// Only the source tracks that are in the library
+    pub matched: Vec<ReportMatch>,
+    pub unmatched: Vec<ReportTrack>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ReportAlbum {
+    pub catalog_id: String,
+    pub name: String,
+    pub artist_name: String,
+    /// YYYY-MM-DD, `None` if it hasn't been announced
+    pub release_date: Option<String>,
+    pub upc: Option<String>,
+    /// e.g. `lossless`, `hi-res-lossless` or `dolby-atmos`
+    pub audio_variants: Vec<String>,
+    pub apple_digital_master: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ReportTrack {
+    /// `3`, or `2-3` for disc 2 track 3
+    pub position: String,
+    pub catalog_id: String,
+    pub name: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ReportMatch {
+    pub source: ReportTrack,
+    pub destination: ReportTrack,
+    pub method: String,
+    pub normalized: bool,
+    pub warnings: Vec<String>,
+    pub reason: MatchReason,
+    /// Universal link to the destination track on other streaming services
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub song_link: Option<String>,
+}
+
+/// Why two tracks were matched, for tools that audit matches rather than show them
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct MatchReason {
+    /// `isrc`, `musicbrainz`, `name-artist`, `fuzzy` or `manual`
+    pub matcher: &'static str,
+    /// The values the matcher compared, after any normalization, empty for manual mappings
+    pub compared: Vec<ComparedValue>,
+    /// Title similarity from 0 to 100, `None` for manual mappings
+    pub score: Option<u8>,
+    pub warnings: Vec<ReportWarning>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ComparedValue {
+    /// `isrc`, `title` or `artist`
+    pub field: &'static str,
+    pub source: Option<String>,
+    pub destination: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum ReportWarning {
+    /// `clean`, `explicit`, a rating this doesn't know as the API gives it, or `None` when
+    /// unrated
+    ContentRatingChanged {
+        source: Option<String>,
+        destination: Option<String>,
+    },
+    DurationChanged {
+        source_millis: u64,
+        destination_millis: u64,
+    },
+}
+
+impl From<&matching::MatchWarning> for ReportWarning {
+    fn from(warning: &matching::MatchWarning) -> Self {
+        let content_rating = |content_rating: &Option<ContentRating>| {
+            content_rating
+                .as_ref()
+                .map(|content_rating| content_rating.as_str().to_owned())
+        };
+        let millis = |duration: std::time::Duration| duration.as_millis() as u64;
+        match warning {
+            matching::MatchWarning::ContentRatingChanged {
+                source,
+                destination,
+            } => Self::ContentRatingChanged {
+                source: content_rating(source),
+                destination: content_rating(destination),
+            },
+            matching::MatchWarning::DurationChanged {
+                source,
+                destination,
+            } => Self::DurationChanged {
+                source_millis: millis(*source),
+                destination_millis: millis(*destination),
+            },
+        }
+    }
+}
+
+fn match_reason(
+    options: &matching::MatchOptions,
+    source: &TrackWithLibrary,
+    destination: &TrackNoLibrary,
+    method: matching::MatchMethod,
+    warnings: &[matching::MatchWarning],
+) -> MatchReason {
+    let isrcs = || {
+        vec![ComparedValue {
+            field: "isrc",
+            source: source.isrc.clone(),
+            destination: destination.isrc.clone(),
+        }]
+    };
+    let names = || {
+        let (source_name, source_artist) =
+            matching::normalized_names(options, &source.name, &source.artist_name);
+        let (destination_name, destination_artist) =
+            matching::normalized_names(options, &destination.name, &destination.artist_name);
+        vec![
+            ComparedValue {
+                field: "title",
+                source: Some(source_name.into_owned()),
+                destination: Some(destination_name.into_owned()),
+            },
+            ComparedValue {
+                field: "artist",
+                source: Some(source_artist.into_owned()),
+                destination: Some(destination_artist.into_owned()),
+            },
+        ]
+    };
+    let (matcher, compared, score) = match method {
+        matching::MatchMethod::Isrc => ("isrc", isrcs(), Some(100)),
+        matching::MatchMethod::MusicBrainz => ("musicbrainz", isrcs(), Some(100)),
+        matching::MatchMethod::NameArtist => ("name-artist", names(), Some(100)),
+        matching::MatchMethod::Fuzzy { similarity_percent } => {
+            ("fuzzy", names(), Some(similarity_percent))
+        }
+        matching::MatchMethod::Manual => ("manual", vec![], None),
+    };
+    MatchReason {
+        matcher,
+        compared,
+        score,
+        warnings: warnings.iter().map(ReportWarning::from).collect(),
+    }
+}
+
+impl<Track> From<&Album<Track>> for ReportAlbum {
+    fn from(album: &Album<Track>) -> Self {
+        Self {
+            catalog_id: album.catalog_id.to_string(),
+            name: album.name.clone(),
+            artist_name: album.artist_name.clone(),
+            release_date: album.release_date.clone(),
+            upc: album.upc.clone(),
+            audio_variants: album.audio_variants.clone(),
+            apple_digital_master: album.apple_digital_master,
+        }
+    }
+}
+
+fn report_track(catalog_id: &str, name: &str, disc_number: u8, track_number: u8) -> ReportTrack {
+    ReportTrack {
+        position: TrackPosition {
+            disc_number,
+            track_number,
+        }
+        .to_string(),
+        catalog_id: catalog_id.to_owned(),
+        name: name.to_owned(),
+    }
+}
+
+/// song.link resolves Apple Music song IDs to the same recording on other services
+fn song_link(catalog_id: &str) -> String {
+    format!("https://song.link/i/{catalog_id}")
+}
+
+pub fn build(
+    source_album: &Album<TrackWithLibrary>,
+    destination_album: &Album<TrackNoLibrary>,
+    report: &matching::MatchReport,
+    options: &matching::MatchOptions,
+    dry_run: bool,
+    song_links: bool,
+) -> Report {
+    let mut matched = Vec::new();
+    let mut unmatched = Vec::new();
+    for result in &report.results {
+        match result {
+            matching::TrackMatchResult::Match {
+                source,
+                destination,
+                method,
+                normalized,
+                warnings,
+            } => {
+                if source.library_id.is_none() {
+                    continue;
+                }
+                matched.push(ReportMatch {
+                    source: report_track(
+                        &source.catalog_id,
+                        &source.name,
+                        source.disc_number,
+                        source.track_number,
+                    ),
+                    destination: report_track(
+                        &destination.catalog_id,
+                        &destination.name,
+                        destination.disc_number,
+                        destination.track_number,
+                    ),
+                    method: method.to_string(),
+                    normalized: *normalized,
+                    warnings: warnings.iter().map(ToString::to_string).collect(),
+                    reason: match_reason(options, source, destination, *method, warnings),
+                    song_link: song_links.then(|| song_link(&destination.catalog_id)),
+                });
+            }
+            matching::TrackMatchResult::NoMatch { source } => {
+                if source.library_id.is_none() {
+                    continue;
+                }
+                unmatched.push(report_track(
+                    &source.catalog_id,
+                    &source.name,
+                    source.disc_number,
+                    source.track_number,
+                ));
+            }
+        }
+    }
+    Report {
+        dry_run,
+        source: source_album.into(),
+        destination: destination_album.into(),
+        matched,
+        unmatched,
+    }
+}
+
+/// Escape a value for a Markdown table cell
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+fn markdown_album(label: &str, album: &ReportAlbum) -> String {
+    let upc = album
+        .upc
+        .as_deref()
+        .map(|upc| format!(", UPC {upc}"))
+        .unwrap_or_default();
+    let badges = output::format_audio_badges(&album.audio_variants, album.apple_digital_master)
+        .map(|badges| format!(", {badges}"))
+        .unwrap_or_default();
+    format!(
+        "- {label}: \"{}\" by {} ({}, catalog ID {}{upc}{badges})\n",
+        album.name,
+        album.artist_name,
+        output::format_release_date(album.release_date.as_deref()),
+        album.catalog_id,
+    )
+}
+
+fn to_markdown(report: &Report) -> String {
+    let mut markdown = format!(
+        "# {} \u{2192} {}\n\n",
+        report.source.name, report.destination.name,
+    );
+    markdown += &markdown_album("Source", &report.source);
+    markdown += &markdown_album("Destination", &report.destination);
+    if report.dry_run {
+        markdown += "- Dry run, no changes were made\n";
+    }
+
+    if !report.matched.is_empty() {
+        let song_links = report.matched.iter().any(|m| m.song_link.is_some());
+        markdown += "\n## Matched tracks\n\n| Source | Destination | Method | Warnings |";
+        markdown += if song_links {
+            " song.link |\n| --- | --- | --- | --- | --- |\n"
+        } else {
+            "\n| --- | --- | --- | --- |\n"
+        };
+        for track_match in &report.matched {
+            let method = if track_match.normalized {
+                format!("{} after rewrite rules", track_match.method)
+            } else {
+                track_match.method.clone()
+            };
+            markdown += &format!(
+                "| {}. {} | {}. {} | {} | {} |",
+                track_match.source.position,
+                markdown_cell(&track_match.source.name),
+                track_match.destination.position,
+                markdown_cell(&track_match.destination.name),
+                markdown_cell(&method),
+                markdown_cell(&track_match.warnings.join("; ")),
+            );
+            if song_links {
+                match &track_match.song_link {
+                    Some(link) => markdown += &format!(" [Listen]({link}) |\n"),
+                    None => markdown += " |\n",
+                }
+            } else {
+                markdown += "\n";
+            }
+        }
+    }
+
+    if !report.unmatched.is_empty() {
+        markdown += "\n## Unmatched tracks\n\n";
+        for track in &report.unmatched {
+            markdown += &format!("- {}. {}\n", track.position, track.name);
+        }
+    }
+    markdown
+}
+
+fn extension(path: &Path) -> Option<&str> {
+    path.extension().and_then(|extension| extension.to_str())
+}
+
+/// Checked up front so that a migration isn't made without its report
+pub fn parse_path(value: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(value);
+    ensure!(
+        matches!(extension(&path), Some("json" | "yaml" | "yml" | "md")),
+        "report file must end with `.json`, `.yaml` or `.md`",
+    );
+    Ok(path)
+}
+
+/// Write the report as JSON, YAML or Markdown depending on the file extension
+pub fn write(report: &Report, path: &Path) -> Result<()> {
+    let contents = match extension(path) {
+        Some("json") => serde_json::to_string_pretty(report)? + "\n",
+        Some("yaml" | "yml") => yaml::to_string(report)?,
+        Some("md") => to_markdown(report),
+        _ => bail!("report file must end with `.json`, `.yaml` or `.md`"),
+    };
+    fs::write(path, contents)
+        .with_context(|| format!("failed to write report to {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_warning() {
+        let warning = ReportWarning::from(&matching::MatchWarning::ContentRatingChanged {
+            source: Some(ContentRating::Other("restricted".to_owned())),
+            destination: None,
+        });
+        assert_eq!(
+            serde_json::to_value(&warning).unwrap(),
+            serde_json::json!({
+                "kind": "content-rating-changed",
+                "source": "restricted",
+                "destination": null,
+            }),
+        );
+        let warning = ReportWarning::from(&matching::MatchWarning::DurationChanged {
+            source: std::time::Duration::from_millis(200_500),
+            destination: std::time::Duration::from_secs(230),
+        });
+        assert_eq!(
+            serde_json::to_value(&warning).unwrap(),
+            serde_json::json!({
+                "kind": "duration-changed",
+                "source_millis": 200_500,
+                "destination_millis": 230_000,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let album = |catalog_id: &str, name: &str| ReportAlbum {
+            catalog_id: catalog_id.to_owned(),
+            name: name.to_owned(),
+            artist_name: "Artist".to_owned(),
+            release_date: Some("2020-01-01".to_owned()),
+            upc: None,
+            audio_variants: vec![],
+            apple_digital_master: false,
+        };
+        let report = Report {
+            dry_run: true,
+            source: album("1", "Album"),
+            destination: ReportAlbum {
+                audio_variants: vec!["lossless".to_owned(), "dolby-atmos".to_owned()],
+                ..album("2", "Album (Deluxe)")
+            },
+            matched: vec![ReportMatch {
+                source: report_track("10", "A|B", 1, 1),
+                destination: report_track("20", "A|B", 1, 2),
+                method: "ISRC".to_owned(),
+                normalized: false,
+                warnings: vec![],
+                reason: MatchReason {
+                    matcher: "isrc",
+                    compared: vec![],
+                    score: Some(100),
+                    warnings: vec![],
+                },
+                song_link: Some(song_link("20")),
+            }],
+            unmatched: vec![report_track("11", "Interlude", 1, 3)],
+        };
+        assert_eq!(
+            to_markdown(&report),
+            "# Album \u{2192} Album (Deluxe)\n\
+             \n\
+             - Source: \"Album\" by Artist (2020-01-01, catalog ID 1)\n\
+             - Destination: \"Album (Deluxe)\" by Artist (2020-01-01, catalog ID 2, Lossless, Dolby Atmos)\n\
+             - Dry run, no changes were made\n\
+             \n\
+             ## Matched tracks\n\
+             \n\
+             | Source | Destination | Method | Warnings | song.link |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | 1. A\\|B | 2. A\\|B | ISRC |  | [Listen](https://song.link/i/20) |\n\
+             \n\
+             ## Unmatched tracks\n\
+             \n\
+             - 3. Interlude\n",
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Request and stage metrics for `--stats`, always counted since it's cheap and only printed when
+//! asked for
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+#[derive(Default)]
+struct Stats {
+    /// By method and path, with IDs and storefronts replaced so requests for different albums are
+    /// counted together
+    requests: BTreeMap<String, u32>,
+    retries: u32,
+    cache_hits: u32,
+    bytes_received: usize,
+    /// In the order they first ran, with how many times they ran
+    stages: Vec<(String, u32, Duration)>,
+}
+
+thread_local! {
+    static STATS: RefCell<Stats> = RefCell::default();
+}
+
+/// `GET /v1/catalog/{storefront}/albums/{id}` for `GET /v1/catalog/us/albums/123?extend=...`
+fn endpoint(method: &reqwest::Method, path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+    let mut segments = Vec::new();
+    let mut previous = "";
+    for segment in path.split('/') {
+        let is_id = !segment.is_empty()
+            && (segment.chars().all(|c| c.is_ascii_digit())
+                || ["l.", "i.", "p."]
+                    .iter()
+                    .any(|prefix| segment.starts_with(prefix)));
+        segments.push(if is_id {
+            "{id}"
+        } else if previous == "catalog" {
+            "{storefront}"
+        } else {
+            segment
+        });
+        previous = segment;
+    }
+    format!("{method} {}", segments.join("/"))
+}
+
+/// A request that was sent, once for each attempt
+pub fn record_request(method: &reqwest::Method, path: &str, bytes_received: usize) {
+    STATS.with_borrow_mut(|stats| {
+        *stats.requests.entry(endpoint(method, path)).or_default() += 1;
+        stats.bytes_received += bytes_received;
+    });
+}
+
+pub fn record_retry() {
+    STATS.with_borrow_mut(|stats| stats.retries += 1);
+}
+
+pub fn record_cache_hit() {
+    STATS.with_borrow_mut(|stats| stats.cache_hits += 1);
+}
+
+pub fn record_stage(name: &str, elapsed: Duration) {
+    STATS.with_borrow_mut(|stats| {
+        match stats.stages.iter_mut().find(|(stage, _, _)| stage == name) {
+            Some((_, count, total)) => {
+                *count += 1;
+                *total += elapsed;
+            }
+            None => stats.stages.push((name.to_owned(), 1, elapsed)),
+        }
+    });
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1_000 => format!("{bytes} B"),
+        1_000..1_000_000 => format!("{:.1} kB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
+
+/// Printed to stderr, so it doesn't end up in output that's piped elsewhere
+pub fn print(started: Instant) {
+    STATS.with_borrow(|stats| {
+        eprintln!();
+        eprintln!("Requests:");
+        for (endpoint, count) in &stats.requests {
+            eprintln!("  {count:>5}  {endpoint}");
+        }
+        eprintln!(
+            "  {} requests, {} retries, {} cache hits, {} received",
+            stats.requests.values().sum::<u32>(),
+            stats.retries,
+            stats.cache_hits,
+            format_bytes(stats.bytes_received as u64),
+        );
+        if !stats.stages.is_empty() {
+            eprintln!("Stages:");
+            for (stage, count, total) in &stats.stages {
+                let count = if *count > 1 {
+                    format!(" ({count} times)")
+                } else {
+                    String::new()
+                };
+                eprintln!("  {:>8.2}s  {stage}{count}", total.as_secs_f64());
+            }
+        }
+        eprintln!("Total: {:.2}s", started.elapsed().as_secs_f64());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint() {
+        assert_eq!(
+            endpoint(
+                &reqwest::Method::GET,
+                "/v1/catalog/us/albums/123?extend=audioVariants"
+            ),
+            "GET /v1/catalog/{storefront}/albums/{id}",
+        );
+        assert_eq!(
+            endpoint(&reqwest::Method::DELETE, "/v1/me/library/albums/l.ABC"),
+            "DELETE /v1/me/library/albums/{id}",
+        );
+        assert_eq!(
+            endpoint(&reqwest::Method::POST, "/v1/me/library?ids[songs]=1,2"),
+            "POST /v1/me/library",
+        );
+        assert_eq!(format_bytes(1_500), "1.5 kB");
+    }
+}
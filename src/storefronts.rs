@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, ensure};
+
+use crate::{
+    apple_music::{self, CatalogAlbumId, custom_types},
+    output,
+};
+
+/// Maximum number of ISRCs in a single song lookup
+const ISRCS_PER_REQUEST: usize = 25;
+
+struct Availability {
+    storefront: String,
+    /// `None` if the album isn't available under the same catalog ID
+    album_catalog_id: Option<CatalogAlbumId>,
+    /// Catalog IDs of the songs with each ISRC, a recording can be on several albums
+    song_ids_by_isrc: HashMap<String, Vec<String>>,
+}
+
+async fn check(
+    client: &apple_music::Client,
+    album: &custom_types::Album<custom_types::TrackNoLibrary>,
+    storefront: &str,
+) -> Result<Availability> {
+    let album_catalog_id = match client
+        .get_catalog_album_in(storefront, &album.catalog_id)
+        .await
+    {
+        Ok(_) => Some(album.catalog_id.clone()),
+        Err(error) if apple_music::ApiError::is_not_found(&error) => None,
+        Err(error) => return Err(error),
+    };
+
+    let isrcs: Vec<&str> = album
+        .tracks
+        .iter()
+        .filter_map(|t| t.isrc.as_deref())
+        .collect();
+    let mut song_ids_by_isrc: HashMap<String, Vec<String>> = HashMap::new();
+    for chunk in isrcs.chunks(ISRCS_PER_REQUEST) {
+        let songs = client.get_catalog_songs_by_isrc(storefront, chunk).await?;
+        for (isrc, catalog_id) in songs.ids_by_isrc() {
+            song_ids_by_isrc
+                .entry(isrc.to_owned())
+                .or_default()
+                .push(catalog_id.to_owned());
+        }
+    }
+
+    Ok(Availability {
+        storefront: storefront.to_owned(),
+        album_catalog_id,
+        song_ids_by_isrc,
+    })
+}
+
+/// Print whether an album and each of its tracks (by ISRC) is available in each storefront
+pub async fn compare(
+    client: &apple_music::Client,
+    catalog_id: &CatalogAlbumId,
+    storefronts: &[String],
+) -> Result<()> {
+    for storefront in storefronts {
+        ensure!(
+            apple_music::validate_storefront(storefront),
+            "invalid storefront: {storefront}",
+        );
+    }
+
+    let album: custom_types::Album<custom_types::TrackNoLibrary> =
+        client.get_catalog_album(catalog_id).await?.try_into()?;
+    ensure!(album.catalog_id == *catalog_id);
+    println!(
+        "\"{}\" by {} ({} tracks in `{}`)",
+        album.name,
+        album.artist_name,
+        album.tracks.len(),
+        client.storefront(),
+    );
+
+    let multi_disc = album.tracks.iter().any(|t| t.disc_number > 1);
+    for storefront in storefronts {
+        let availability = check(client, &album, storefront).await?;
+        let available_tracks = album
+            .tracks
+            .iter()
+            .filter(|t| {
+                t.isrc
+                    .as_ref()
+                    .is_some_and(|isrc| availability.song_ids_by_isrc.contains_key(isrc))
+            })
+            .count();
+
+        println!();
+        match &availability.album_catalog_id {
+            Some(album_catalog_id) => println!(
+                "{}: album available as {album_catalog_id}, {available_tracks} of {} tracks available",
+                availability.storefront,
+                album.tracks.len(),
+            ),
+            None => println!(
+                "{}: album not available, {available_tracks} of {} tracks available",
+                availability.storefront,
+                album.tracks.len(),
+            ),
+        }
+        for track in &album.tracks {
+            let position =
+                output::format_position(track.disc_number, track.track_number, multi_disc);
+            let status = match &track.isrc {
+                None => "no ISRC to look up".to_owned(),
+                Some(isrc) => match availability.song_ids_by_isrc.get(isrc) {
+                    Some(catalog_ids) => catalog_ids.join(", "),
+                    None => "not available".to_owned(),
+                },
+            };
+            println!("  #{position} {}: {status}", track.name);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,530 @@
+//! Minimal readers for the tags of FLAC, MP3 (ID3v2.3 and 2.4) and MP4 (AAC and ALAC) files,
+//! only reading the fields used for matching
+
+use std::{
+    fs::File,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail, ensure};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Tags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub isrc: Option<String>,
+    pub disc_number: Option<u8>,
+    pub track_number: Option<u8>,
+    pub duration: Option<Duration>,
+}
+
+/// `None` for files that aren't in a supported format, judging by the extension
+pub fn read(path: &Path) -> Result<Option<Tags>> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    if !matches!(extension.as_deref(), Some("flac" | "mp3" | "m4a" | "mp4")) {
+        return Ok(None);
+    }
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    match extension.as_deref() {
+        Some("flac") => read_flac(&mut file),
+        Some("mp3") => read_id3(&mut file),
+        _ => read_mp4(&mut file),
+    }
+    .map(Some)
+    .with_context(|| format!("failed to read the tags of {}", path.display()))
+}
+
+/// `3` or `3/12`
+fn parse_number(value: &str) -> Option<u8> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// Grows as it reads rather than allocating `length` up front, so a corrupt size can't allocate
+/// more than the file holds
+fn read_vec(reader: &mut impl Read, length: usize) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader
+        .by_ref()
+        .take(u64::try_from(length)?)
+        .read_to_end(&mut data)?;
+    ensure!(data.len() == length, "unexpected end of file");
+    Ok(data)
+}
+
+/// Split off the first `length` bytes
+fn take<'a>(data: &mut &'a [u8], length: usize) -> Result<&'a [u8]> {
+    ensure!(data.len() >= length, "unexpected end of data");
+    let (taken, rest) = data.split_at(length);
+    *data = rest;
+    Ok(taken)
+}
+
+fn take_u32_le(data: &mut &[u8]) -> Result<u32> {
+    Ok(u32::from_le_bytes(take(data, 4)?.try_into()?))
+}
+
+fn be_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+fn read_flac(reader: &mut (impl Read + Seek)) -> Result<Tags> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    ensure!(&magic == b"fLaC", "not a FLAC file");
+    let mut tags = Tags::default();
+    loop {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        let length = be_u32(&[0, header[1], header[2], header[3]]) as usize;
+        match header[0] & 0x7F {
+            // STREAMINFO
+            0 => {
+                let block = read_vec(reader, length)?;
+                ensure!(block.len() >= 18, "STREAMINFO block is too short");
+                let sample_rate = (u64::from(block[10]) << 12)
+                    | (u64::from(block[11]) << 4)
+                    | (u64::from(block[12]) >> 4);
+                let samples =
+                    (u64::from(block[13] & 0x0F) << 32) | u64::from(be_u32(&block[14..18]));
+                if sample_rate > 0 && samples > 0 {
+                    tags.duration = Some(Duration::from_millis(samples * 1000 / sample_rate));
+                }
+            }
+            // VORBIS_COMMENT
+            4 => read_vorbis_comments(&read_vec(reader, length)?, &mut tags)?,
+            _ => {
+                reader.seek(SeekFrom::Current(length as i64))?;
+            }
+        }
+        if header[0] & 0x80 != 0 {
+            return Ok(tags);
+        }
+    }
+}
+
+fn read_vorbis_comments(mut block: &[u8], tags: &mut Tags) -> Result<()> {
+    let vendor_length = take_u32_le(&mut block)? as usize;
+    take(&mut block, vendor_length)?;
+    for _ in 0..take_u32_le(&mut block)? {
+        let length = take_u32_le(&mut block)? as usize;
+        let comment = String::from_utf8_lossy(take(&mut block, length)?);
+        let Some((key, value)) = comment.split_once('=') else {
+            continue;
+        };
+        // Only the first of repeated fields is used
+        let value = value.to_owned();
+        match key.to_ascii_uppercase().as_str() {
+            "TITLE" if tags.title.is_none() => tags.title = Some(value),
+            "ARTIST" if tags.artist.is_none() => tags.artist = Some(value),
+            "ISRC" if tags.isrc.is_none() => tags.isrc = Some(value),
+            "DISCNUMBER" if tags.disc_number.is_none() => {
+                tags.disc_number = parse_number(&value);
+            }
+            "TRACKNUMBER" if tags.track_number.is_none() => {
+                tags.track_number = parse_number(&value);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 7 bits a byte, so that the size can't contain a frame sync
+fn syncsafe(data: &[u8]) -> usize {
+    data.iter()
+        .fold(0, |size, byte| (size << 7) | usize::from(byte & 0x7F))
+}
+
+/// Undo unsynchronisation, which inserts a 0 after every 0xFF so that the tag can't contain a
+/// frame sync
+fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut resynchronised = Vec::with_capacity(data.len());
+    for (i, &byte) in data.iter().enumerate() {
+        if !(byte == 0 && i > 0 && data[i - 1] == 0xFF) {
+            resynchronised.push(byte);
+        }
+    }
+    resynchronised
+}
+
+fn decode_id3_text(frame: &[u8]) -> Result<Option<String>> {
+    let Some((&encoding, text)) = frame.split_first() else {
+        return Ok(None);
+    };
+    let text = match encoding {
+        0 => text.iter().map(|&byte| char::from(byte)).collect(),
+        1 | 2 => {
+            let (big_endian, text) = match text {
+                [0xFF, 0xFE, rest @ ..] => (false, rest),
+                [0xFE, 0xFF, rest @ ..] => (true, rest),
+                _ => (encoding == 2, text),
+            };
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| {
+                    let pair = [pair[0], pair[1]];
+                    if big_endian {
+                        u16::from_be_bytes(pair)
+                    } else {
+                        u16::from_le_bytes(pair)
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => bail!("unknown ID3 text encoding {encoding}"),
+    };
+    // ID3v2.4 separates multiple values with NUL, only the first is used
+    let text = text.split('\0').next().unwrap_or_default();
+    Ok((!text.is_empty()).then(|| text.to_owned()))
+}
+
+fn read_id3(reader: &mut impl Read) -> Result<Tags> {
+    let mut header = [0; 10];
+    reader.read_exact(&mut header)?;
+    ensure!(&header[..3] == b"ID3", "no ID3v2 tag");
+    let version = header[3];
+    ensure!(matches!(version, 3 | 4), "unsupported ID3v2.{version} tag");
+    let unsynchronised = header[5] & 0x80 != 0;
+    let mut tag = read_vec(reader, syncsafe(&header[6..10]))?;
+    // ID3v2.3 unsynchronises the whole tag and 2.4 each frame
+    if unsynchronised && version == 3 {
+        tag = resynchronise(&tag);
+    }
+    let mut data = tag.as_slice();
+    if header[5] & 0x40 != 0 {
+        // The extended header's size includes itself in 2.4 but not 2.3
+        let size = take(&mut data, 4)?;
+        let size = match version {
+            4 => syncsafe(size).saturating_sub(4),
+            _ => be_u32(size) as usize,
+        };
+        take(&mut data, size)?;
+    }
+
+    let mut tags = Tags::default();
+    // The frames can be followed by padding
+    while data.len() >= 10 && data[0] != 0 {
+        let frame_header = take(&mut data, 10)?;
+        let size = match version {
+            4 => syncsafe(&frame_header[4..8]),
+            _ => be_u32(&frame_header[4..8]) as usize,
+        };
+        let mut frame = take(&mut data, size)?;
+        let flags = frame_header[9];
+        let (compressed_or_encrypted, grouped) = match version {
+            4 => (flags & 0x0C != 0, flags & 0x40 != 0),
+            _ => (flags & 0xC0 != 0, flags & 0x20 != 0),
+        };
+        if compressed_or_encrypted {
+            continue;
+        }
+        // The group ID, and in 2.4 the data length before unsynchronising
+        if grouped {
+            take(&mut frame, 1)?;
+        }
+        let resynchronised;
+        if version == 4 {
+            if flags & 0x01 != 0 {
+                take(&mut frame, 4)?;
+            }
+            if unsynchronised || flags & 0x02 != 0 {
+                resynchronised = resynchronise(frame);
+                frame = &resynchronised;
+            }
+        }
+        match &frame_header[..4] {
+            b"TIT2" => tags.title = decode_id3_text(frame)?,
+            b"TPE1" => tags.artist = decode_id3_text(frame)?,
+            b"TSRC" => tags.isrc = decode_id3_text(frame)?,
+            b"TPOS" => tags.disc_number = decode_id3_text(frame)?.as_deref().and_then(parse_number),
+            b"TRCK" => {
+                tags.track_number = decode_id3_text(frame)?.as_deref().and_then(parse_number);
+            }
+            // In milliseconds
+            b"TLEN" => {
+                tags.duration = decode_id3_text(frame)?
+                    .and_then(|length| length.trim().parse().ok())
+                    .map(Duration::from_millis);
+            }
+            _ => {}
+        }
+    }
+    Ok(tags)
+}
+
+/// The type and body of each atom
+fn atoms(mut data: &[u8]) -> Result<Vec<(&[u8], &[u8])>> {
+    let mut atoms = Vec::new();
+    while data.len() >= 8 {
+        let header = take(&mut data, 8)?;
+        let (mut size, mut header_size) = (be_u32(header) as usize, 8);
+        if size == 1 {
+            size = usize::try_from(u64::from_be_bytes(take(&mut data, 8)?.try_into()?))?;
+            header_size = 16;
+        } else if size == 0 {
+            // Until the end
+            size = data.len() + header_size;
+        }
+        ensure!(size >= header_size, "invalid atom size");
+        atoms.push((&header[4..], take(&mut data, size - header_size)?));
+    }
+    Ok(atoms)
+}
+
+fn child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Result<Option<&'a [u8]>> {
+    Ok(atoms(data)?
+        .into_iter()
+        .find(|(atom_kind, _)| atom_kind == kind)
+        .map(|(_, body)| body))
+}
+
+fn read_mp4(reader: &mut (impl Read + Seek)) -> Result<Tags> {
+    // The metadata is in the moov atom, which can come after the audio at the top level
+    loop {
+        let mut header = [0; 8];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => bail!("no moov atom"),
+            Err(error) => return Err(error.into()),
+        }
+        let (mut size, mut header_size) = (u64::from(be_u32(&header)), 8);
+        if size == 1 {
+            let mut large_size = [0; 8];
+            reader.read_exact(&mut large_size)?;
+            size = u64::from_be_bytes(large_size);
+            header_size = 16;
+        }
+        if &header[4..] == b"moov" {
+            let moov = if size == 0 {
+                let mut moov = Vec::new();
+                reader.read_to_end(&mut moov)?;
+                moov
+            } else {
+                ensure!(size >= header_size, "invalid atom size");
+                read_vec(reader, usize::try_from(size - header_size)?)?
+            };
+            return read_moov(&moov);
+        }
+        // A size of 0 means the atom continues until the end
+        ensure!(size >= header_size, "no moov atom");
+        reader.seek(SeekFrom::Current(i64::try_from(size - header_size)?))?;
+    }
+}
+
+fn read_moov(moov: &[u8]) -> Result<Tags> {
+    let mut tags = Tags::default();
+    if let Some(mvhd) = child(moov, b"mvhd")? {
+        let (timescale, duration) = match mvhd.first() {
+            Some(0) if mvhd.len() >= 20 => {
+                (be_u32(&mvhd[12..16]), u64::from(be_u32(&mvhd[16..20])))
+            }
+            Some(1) if mvhd.len() >= 32 => (
+                be_u32(&mvhd[20..24]),
+                u64::from_be_bytes(mvhd[24..32].try_into()?),
+            ),
+            _ => (0, 0),
+        };
+        if timescale > 0 {
+            // Whole seconds first, since a version 1 duration can be too large to scale up
+            let timescale = u64::from(timescale);
+            tags.duration = Some(
+                Duration::from_secs(duration / timescale)
+                    + Duration::from_nanos(duration % timescale * 1_000_000_000 / timescale),
+            );
+        }
+    }
+
+    let Some(meta) = child(moov, b"udta")?
+        .map(|udta| child(udta, b"meta"))
+        .transpose()?
+        .flatten()
+    else {
+        return Ok(tags);
+    };
+    // A full atom, with a version and flags before its children
+    let Some(ilst) = child(meta.get(4..).unwrap_or_default(), b"ilst")? else {
+        return Ok(tags);
+    };
+    for (kind, item) in atoms(ilst)? {
+        // After the type and locale of the data
+        let Some(data) = child(item, b"data")?.and_then(|data| data.get(8..)) else {
+            continue;
+        };
+        let text = || Some(String::from_utf8_lossy(data).into_owned());
+        // The position as a 16-bit number after 2 reserved bytes, followed by the total
+        let number = || {
+            data.get(2..4)
+                .and_then(|number| u8::try_from(u16::from_be_bytes([number[0], number[1]])).ok())
+                .filter(|number| *number > 0)
+        };
+        match kind {
+            b"\xA9nam" => tags.title = text(),
+            b"\xA9ART" => tags.artist = text(),
+            b"disk" => tags.disc_number = number(),
+            b"trkn" => tags.track_number = number(),
+            // Freeform, where the ISRC is stored
+            b"----" if child(item, b"name")?.and_then(|name| name.get(4..)) == Some(b"ISRC") => {
+                tags.isrc = text();
+            }
+            _ => {}
+        }
+    }
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_read_flac() {
+        let mut flac = b"fLaC".to_vec();
+        // STREAMINFO with a sample rate of 44100 Hz and 441000 samples
+        flac.extend([0, 0, 0, 34]);
+        let mut streaminfo = [0; 34];
+        streaminfo[10..13].copy_from_slice(&[0x0A, 0xC4, 0x40]);
+        streaminfo[14..18].copy_from_slice(&441_000u32.to_be_bytes());
+        flac.extend(streaminfo);
+        let mut comments = Vec::new();
+        comments.extend(3u32.to_le_bytes());
+        comments.extend(b"abc");
+        let fields = [
+            "title=Song",
+            "ARTIST=Artist",
+            "TRACKNUMBER=3/12",
+            "ISRC=ISRC1",
+        ];
+        comments.extend((fields.len() as u32).to_le_bytes());
+        for field in fields {
+            comments.extend((field.len() as u32).to_le_bytes());
+            comments.extend(field.as_bytes());
+        }
+        flac.extend([0x84, 0, 0, comments.len() as u8]);
+        flac.extend(comments);
+        assert_eq!(
+            read_flac(&mut Cursor::new(flac)).unwrap(),
+            Tags {
+                title: Some("Song".to_owned()),
+                artist: Some("Artist".to_owned()),
+                isrc: Some("ISRC1".to_owned()),
+                disc_number: None,
+                track_number: Some(3),
+                duration: Some(Duration::from_secs(10)),
+            },
+        );
+    }
+
+    #[test]
+    fn test_read_id3() {
+        let frame = |id: &[u8], text: &[u8]| {
+            let mut frame = id.to_vec();
+            frame.extend((text.len() as u32).to_be_bytes());
+            frame.extend([0, 0]);
+            frame.extend(text);
+            frame
+        };
+        let mut frames = frame(b"TIT2", b"\x03Song");
+        frames.extend(frame(b"TPE1", b"\x01\xFF\xFEA\0r\0t\0"));
+        frames.extend(frame(b"TRCK", b"\x002/9"));
+        frames.extend([0; 16]);
+        let mut mp3 = b"ID3\x03\x00\x00".to_vec();
+        mp3.extend([0, 0, 0, frames.len() as u8]);
+        mp3.extend(frames);
+        assert_eq!(
+            read_id3(&mut Cursor::new(mp3)).unwrap(),
+            Tags {
+                title: Some("Song".to_owned()),
+                artist: Some("Art".to_owned()),
+                track_number: Some(2),
+                ..Tags::default()
+            },
+        );
+
+        // Unsynchronised, where a 0 is inserted after the 0xFF of the byte order mark
+        let mut frames = Vec::new();
+        for byte in [frame(b"TIT2", b"\x01\xFF\xFES\0o\0n\0g\0"), vec![0; 4]].concat() {
+            frames.push(byte);
+            if byte == 0xFF {
+                frames.push(0);
+            }
+        }
+        let mut mp3 = b"ID3\x03\x00\x80".to_vec();
+        mp3.extend([0, 0, 0, frames.len() as u8]);
+        mp3.extend(frames);
+        assert_eq!(
+            read_id3(&mut Cursor::new(mp3)).unwrap().title.as_deref(),
+            Some("Song"),
+        );
+
+        // A tag size far larger than the file
+        assert!(read_id3(&mut Cursor::new(b"ID3\x04\x00\x00\x7F\x7F\x7F\x7F")).is_err());
+    }
+
+    #[test]
+    fn test_read_mp4() {
+        let atom = |kind: &[u8], body: &[u8]| {
+            let mut atom = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+            atom.extend(kind);
+            atom.extend(body);
+            atom
+        };
+        let data = |value: &[u8]| atom(b"data", &[&[0; 8], value].concat());
+        let mut mvhd = vec![0; 20];
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&5500u32.to_be_bytes());
+        let ilst = [
+            atom(b"\xA9nam", &data(b"Song")),
+            atom(b"trkn", &data(&[0, 0, 0, 4, 0, 10])),
+            atom(
+                b"----",
+                &[
+                    atom(b"mean", b"\0\0\0\0com.apple.iTunes"),
+                    atom(b"name", b"\0\0\0\0ISRC"),
+                    data(b"ISRC1"),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        let meta = atom(
+            b"meta",
+            &[&[0; 4], atom(b"ilst", &ilst).as_slice()].concat(),
+        );
+        let moov = atom(
+            b"moov",
+            &[atom(b"mvhd", &mvhd), atom(b"udta", &meta)].concat(),
+        );
+        let mp4 = [atom(b"ftyp", b"M4A "), atom(b"mdat", &[0; 16]), moov].concat();
+        assert_eq!(
+            read_mp4(&mut Cursor::new(mp4)).unwrap(),
+            Tags {
+                title: Some("Song".to_owned()),
+                isrc: Some("ISRC1".to_owned()),
+                track_number: Some(4),
+                duration: Some(Duration::from_millis(5500)),
+                ..Tags::default()
+            },
+        );
+
+        // A version 1 header with the largest duration
+        let mut mvhd = vec![0; 32];
+        mvhd[0] = 1;
+        mvhd[20..24].copy_from_slice(&1u32.to_be_bytes());
+        mvhd[24..32].copy_from_slice(&u64::MAX.to_be_bytes());
+        let mp4 = [atom(b"ftyp", b"M4A "), atom(b"moov", &atom(b"mvhd", &mvhd))].concat();
+        assert_eq!(
+            read_mp4(&mut Cursor::new(mp4)).unwrap().duration,
+            Some(Duration::from_secs(u64::MAX)),
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Traces of API calls and migration stages, exported as OTLP/HTTP JSON when the run finishes so
+//! job runners can see where long batch runs spend their time. Only recorded with the `otel`
+//! feature, otherwise only the time spent in stages is kept for `--stats`.
+//!
+//! Exporting is configured with the standard `OTEL_EXPORTER_OTLP_ENDPOINT` (or
+//! `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) and `OTEL_EXPORTER_OTLP_HEADERS` variables, nothing is
+//! sent if neither endpoint is set.
+
+use std::time::Instant;
+#[cfg(feature = "otel")]
+use std::{
+    cell::RefCell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::SystemTime,
+};
+
+use crate::stats;
+
+/// Ends when dropped. Its parent is the innermost span that was open when it started.
+pub struct Span {
+    /// The name and start of a stage
+    stage: Option<(String, Instant)>,
+    #[cfg(feature = "otel")]
+    recording: Recording,
+}
+
+#[cfg(feature = "otel")]
+struct Recording {
+    id: u64,
+    parent_id: Option<u64>,
+    name: String,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, String)>,
+    error: Option<String>,
+}
+
+#[cfg(feature = "otel")]
+#[derive(Default)]
+struct State {
+    open: Vec<u64>,
+    finished: Vec<Recording>,
+}
+
+#[cfg(feature = "otel")]
+thread_local! {
+    static STATE: RefCell<State> = RefCell::default();
+}
+
+#[cfg(feature = "otel")]
+fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.finish()
+}
+
+pub fn span(name: impl Into<String>) -> Span {
+    #[cfg(feature = "otel")]
+    {
+        let id = random_u64();
+        let parent_id = STATE.with_borrow_mut(|state| {
+            let parent_id = state.open.last().copied();
+            state.open.push(id);
+            parent_id
+        });
+        let start = SystemTime::now();
+        Span {
+            stage: None,
+            recording: Recording {
+                id,
+                parent_id,
+                name: name.into(),
+                start,
+                end: start,
+                attributes: Vec::new(),
+                error: None,
+            },
+        }
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = name.into();
+        Span { stage: None }
+    }
+}
+
+/// A span that's also included in the stage times for `--stats`
+pub fn stage(name: &str) -> Span {
+    let mut span = span(name);
+    span.stage = Some((name.to_owned(), Instant::now()));
+    span
+}
+
+impl Span {
+    pub fn attribute(&mut self, key: &'static str, value: impl ToString) {
+        #[cfg(feature = "otel")]
+        self.recording.attributes.push((key, value.to_string()));
+        #[cfg(not(feature = "otel"))]
+        let _ = (key, value.to_string());
+    }
+
+    /// Mark the span as failed
+    pub fn error(&mut self, error: &anyhow::Error) {
+        #[cfg(feature = "otel")]
+        {
+            self.recording.error = Some(format!("{error:#}"));
+        }
+        #[cfg(not(feature = "otel"))]
+        let _ = error;
+    }
+
+    /// End the span before the end of its scope
+    pub fn end(self) {}
+
+    /// Record the outcome of the stage the span covers
+    pub fn result<T>(mut self, result: anyhow::Result<T>) -> anyhow::Result<T> {
+        if let Err(error) = &result {
+            self.error(error);
+        }
+        result
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some((name, started)) = &self.stage {
+            stats::record_stage(name, started.elapsed());
+        }
+        #[cfg(feature = "otel")]
+        self.finish();
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Span {
+    fn finish(&mut self) {
+        let recording = Recording {
+            id: self.recording.id,
+            parent_id: self.recording.parent_id,
+            name: std::mem::take(&mut self.recording.name),
+            start: self.recording.start,
+            end: SystemTime::now(),
+            attributes: std::mem::take(&mut self.recording.attributes),
+            error: self.recording.error.take(),
+        };
+        STATE.with_borrow_mut(|state| {
+            // Not always the last one, concurrent requests can end in any order
+            state.open.retain(|id| *id != recording.id);
+            state.finished.push(recording);
+        });
+    }
+}
+
+#[cfg(feature = "otel")]
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// The finished spans as an OTLP `ExportTraceServiceRequest`
+#[cfg(feature = "otel")]
+fn export_request(trace_id: u128, spans: &[Recording]) -> serde_json::Value {
+    use serde_json::json;
+
+    let spans: Vec<serde_json::Value> = spans
+        .iter()
+        .map(|span| {
+            let attributes: Vec<serde_json::Value> = span
+                .attributes
+                .iter()
+                .map(|(key, value)| json!({"key": key, "value": {"stringValue": value}}))
+                .collect();
+            json!({
+                "traceId": format!("{trace_id:032x}"),
+                "spanId": format!("{:016x}", span.id),
+                "parentSpanId": span.parent_id.map(|id| format!("{id:016x}")).unwrap_or_default(),
+                "name": span.name,
+                // Internal
+                "kind": 1,
+                "startTimeUnixNano": unix_nanos(span.start),
+                "endTimeUnixNano": unix_nanos(span.end),
+                "attributes": attributes,
+                "status": match &span.error {
+                    Some(message) => json!({"code": 2, "message": message}),
+                    None => json!({"code": 0}),
+                },
+            })
+        })
+        .collect();
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [{
+                    "key": "service.name",
+                    "value": {"stringValue": env!("CARGO_PKG_NAME")},
+                }],
+            },
+            "scopeSpans": [{
+                "scope": {"name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION")},
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+/// Send the finished spans to the configured collector, best effort since it shouldn't fail the
+/// run
+pub async fn export() {
+    #[cfg(feature = "otel")]
+    {
+        let endpoint = match std::env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT") {
+            Ok(endpoint) => endpoint,
+            Err(_) => match std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+                Ok(endpoint) => format!("{}/v1/traces", endpoint.trim_end_matches('/')),
+                Err(_) => return,
+            },
+        };
+        let spans = STATE.with_borrow_mut(|state| std::mem::take(&mut state.finished));
+        if spans.is_empty() {
+            return;
+        }
+        let mut request = reqwest::Client::new().post(&endpoint).json(&export_request(
+            (u128::from(random_u64()) << 64) | u128::from(random_u64()),
+            &spans,
+        ));
+        for header in std::env::var("OTEL_EXPORTER_OTLP_HEADERS")
+            .unwrap_or_default()
+            .split(',')
+        {
+            if let Some((name, value)) = header.split_once('=') {
+                request = request.header(name.trim(), value.trim());
+            }
+        }
+        match request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(_) => {}
+            Err(error) => eprintln!("Warning: failed to export traces to {endpoint}: {error}"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_parents() {
+        {
+            let mut outer = span("outer");
+            outer.attribute("album", 1);
+            let _inner = span("inner");
+        }
+        let finished = STATE.with_borrow_mut(|state| std::mem::take(&mut state.finished));
+        let [inner, outer] = finished.as_slice() else {
+            panic!("expected two spans");
+        };
+        assert_eq!(inner.parent_id, Some(outer.id));
+        assert_eq!(outer.parent_id, None);
+        let request = export_request(1, &finished);
+        let spans = &request["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(spans[1]["attributes"][0]["value"]["stringValue"], "1");
+        assert_eq!(spans[0]["traceId"], format!("{:032x}", 1));
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{apple_music::token, output};
+
+fn format_timestamp(timestamp: i64) -> String {
+    match DateTime::from_timestamp(timestamp, 0) {
+        Some(date_time) => date_time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => format!("{timestamp} (out of range)"),
+    }
+}
+
+fn describe_expiry(exp: i64, now: DateTime<Utc>) -> String {
+    let remaining = TimeDelta::seconds(exp - now.timestamp());
+    if remaining > TimeDelta::zero() {
+        format!("expires in {}", output::format_duration(remaining))
+    } else {
+        format!("expired {} ago", output::format_duration(-remaining))
+    }
+}
+
+pub fn inspect(developer_token: &str, user_token: Option<&str>) -> Result<()> {
+    let now = Utc::now();
+    let decoded =
+        token::decode_developer_token(developer_token).context("invalid developer token")?;
+
+    println!("Developer token");
+    println!("  Header: {}", decoded.header);
+    println!("  Claims: {}", decoded.claims);
+    println!();
+    println!("  alg: {}", decoded.alg().unwrap_or("(missing)"));
+    println!("  kid: {}", decoded.kid().unwrap_or("(missing)"));
+    println!("  iss: {}", decoded.iss().unwrap_or("(missing)"));
+    match decoded.iat() {
+        Some(iat) => println!("  iat: {}", format_timestamp(iat)),
+        None => println!("  iat: (missing)"),
+    }
+    match decoded.exp() {
+        Some(exp) => println!(
+            "  exp: {} ({})",
+            format_timestamp(exp),
+            describe_expiry(exp, now),
+        ),
+        None => println!("  exp: (missing)"),
+    }
+
+    if let Some(user_token) = user_token {
+        println!();
+        println!("Media User Token");
+        println!("  Length: {} characters", user_token.len());
+        // The user token is opaque, so all that can be checked is that it looks like base64
+        match STANDARD.decode(user_token) {
+            Ok(bytes) => println!("  Decodes as base64 ({} bytes)", bytes.len()),
+            Err(_) => println!("  Does not decode as base64, it may be truncated or mangled"),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_expiry() {
+        let now = DateTime::from_timestamp(1_000_000, 0).unwrap();
+        assert_eq!(
+            describe_expiry(1_000_000 + 2 * 86400 + 3 * 3600, now),
+            "expires in 2d 3h",
+        );
+        assert_eq!(
+            describe_expiry(1_000_000 - 90 * 60, now),
+            "expired 1h 30m ago",
+        );
+        assert_eq!(describe_expiry(1_000_000 + 59, now), "expires in 0m");
+    }
+}
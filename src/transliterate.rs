@@ -0,0 +1,131 @@
+//! Romanization of Hangul and kana, so native-script and romanized titles of the same track can
+//! be compared. Kanji and hanzi are left as they are since they can't be romanized without a
+//! dictionary.
+
+const HANGUL_SYLLABLES: std::ops::RangeInclusive<u32> = 0xAC00..=0xD7A3;
+
+/// Revised Romanization without the sound change rules, which romanized titles rarely follow
+/// consistently anyway
+const HANGUL_INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p",
+    "h",
+];
+const HANGUL_MEDIALS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+const HANGUL_FINALS: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+
+/// Hepburn romanization of the hiragana from `ぁ` to `ゖ`, katakana are mapped onto these
+const KANA: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o", "ka", "ga", "ki", "gi", "ku", "gu", "ke",
+    "ge", "ko", "go", "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo", "ta", "da",
+    "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do", "na", "ni", "nu", "ne", "no", "ha", "ba",
+    "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo", "po", "ma", "mi", "mu",
+    "me", "mo", "ya", "ya", "yu", "yu", "yo", "yo", "ra", "ri", "ru", "re", "ro", "wa", "wa", "i",
+    "e", "o", "n", "vu", "ka", "ke",
+];
+const HIRAGANA_START: u32 = 0x3041;
+const KATAKANA_START: u32 = 0x30A1;
+const SMALL_TSU: u32 = 0x3063;
+/// `ゃ`, `ゅ` and `ょ`
+const SMALL_Y: [(u32, char); 3] = [(0x3083, 'a'), (0x3085, 'u'), (0x3087, 'o')];
+
+fn hiragana(c: char) -> Option<u32> {
+    let c = c as u32;
+    let offset = if (HIRAGANA_START..HIRAGANA_START + KANA.len() as u32).contains(&c) {
+        c - HIRAGANA_START
+    } else if (KATAKANA_START..KATAKANA_START + KANA.len() as u32).contains(&c) {
+        c - KATAKANA_START
+    } else {
+        return None;
+    };
+    Some(HIRAGANA_START + offset)
+}
+
+fn kana_romaji(hiragana: u32) -> &'static str {
+    KANA[(hiragana - HIRAGANA_START) as usize]
+}
+
+/// Whether the text has any Hangul or kana that [`romanize`] would change
+pub fn has_romanizable(text: &str) -> bool {
+    text.chars()
+        .any(|c| HANGUL_SYLLABLES.contains(&(c as u32)) || hiragana(c).is_some() || c == 'ー')
+}
+
+/// Hangul and kana replaced by their romanization, everything else is kept
+pub fn romanize(text: &str) -> String {
+    let mut romanized = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    // Doubles the next consonant
+    let mut geminate = false;
+    while let Some(c) = chars.next() {
+        let code = c as u32;
+        if HANGUL_SYLLABLES.contains(&code) {
+            let index = (code - HANGUL_SYLLABLES.start()) as usize;
+            romanized.push_str(HANGUL_INITIALS[index / (21 * 28)]);
+            romanized.push_str(HANGUL_MEDIALS[index % (21 * 28) / 28]);
+            romanized.push_str(HANGUL_FINALS[index % 28]);
+        } else if let Some(kana) = hiragana(c) {
+            if kana == SMALL_TSU {
+                geminate = true;
+                continue;
+            }
+            let mut romaji = kana_romaji(kana).to_owned();
+            if let Some(&(_, vowel)) = chars
+                .peek()
+                .and_then(|&next| hiragana(next))
+                .and_then(|next| SMALL_Y.iter().find(|(small, _)| *small == next))
+                && romaji.len() > 1
+                && romaji.ends_with('i')
+            {
+                chars.next();
+                romaji.pop();
+                // `shi`, `chi` and `ji` don't take a `y`, as in `sha`
+                if !matches!(romaji.as_str(), "sh" | "ch" | "j") {
+                    romaji.push('y');
+                }
+                romaji.push(vowel);
+            }
+            if std::mem::take(&mut geminate) {
+                romanized.push(if romaji.starts_with("ch") {
+                    't'
+                } else {
+                    romaji.chars().next().unwrap_or_default()
+                });
+            }
+            romanized.push_str(&romaji);
+        } else if c == 'ー' {
+            // Lengthens the previous vowel
+            if let Some(vowel) = romanized.chars().last().filter(|c| "aeiou".contains(*c)) {
+                romanized.push(vowel);
+            }
+        } else {
+            romanized.push(c);
+        }
+    }
+    romanized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_romanize() {
+        assert_eq!(romanize("사랑해"), "saranghae");
+        assert_eq!(romanize("봄날 (Spring Day)"), "bomnal (Spring Day)");
+        assert_eq!(romanize("こいのよかん"), "koinoyokan");
+        assert_eq!(romanize("きょうしつ"), "kyoushitsu");
+        assert_eq!(romanize("ちゃんと"), "chanto");
+        assert_eq!(romanize("ずっと"), "zutto");
+        assert_eq!(romanize("マッチ"), "matchi");
+        assert_eq!(romanize("ラーメン"), "raamen");
+        assert_eq!(romanize("東京"), "東京");
+        assert!(has_romanizable("夜に駆ける"));
+        assert!(!has_romanizable("Song 1"));
+    }
+}
@@ -0,0 +1,356 @@
+//! YAML for reports and batches. Values are written in block style by way of their JSON form,
+//! with strings double-quoted since a JSON string is also a valid YAML one, and read back the same
+//! way.
+
+use anyhow::{Context, Result, bail};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+pub fn to_string<T: Serialize>(value: &T) -> Result<String> {
+    let value = serde_json::to_value(value)?;
+    let mut yaml = String::new();
+    match inline(&value) {
+        Some(scalar) => {
+            yaml.push_str(&scalar);
+            yaml.push('\n');
+        }
+        None => write_block(&mut yaml, &value, 0),
+    }
+    Ok(yaml)
+}
+
+/// Scalars and empty collections, which are written on the same line as their key
+fn inline(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some("null".to_owned()),
+        Value::Bool(value) => Some(value.to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::String(value) => Some(Value::String(value.clone()).to_string()),
+        Value::Array(items) if items.is_empty() => Some("[]".to_owned()),
+        Value::Object(map) if map.is_empty() => Some("{}".to_owned()),
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+fn key(key: &str) -> String {
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if plain {
+        key.to_owned()
+    } else {
+        Value::String(key.to_owned()).to_string()
+    }
+}
+
+/// A non-empty collection starting on a new line
+fn write_block(yaml: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map {
+                yaml.push_str(&" ".repeat(indent));
+                write_entry(yaml, name, value, indent);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                yaml.push_str(&" ".repeat(indent));
+                yaml.push('-');
+                write_item(yaml, item, indent);
+            }
+        }
+        _ => unreachable!("scalars are written inline"),
+    }
+}
+
+fn write_entry(yaml: &mut String, name: &str, value: &Value, indent: usize) {
+    yaml.push_str(&key(name));
+    yaml.push(':');
+    match inline(value) {
+        Some(scalar) => {
+            yaml.push(' ');
+            yaml.push_str(&scalar);
+            yaml.push('\n');
+        }
+        None => {
+            yaml.push('\n');
+            write_block(yaml, value, indent + 2);
+        }
+    }
+}
+
+/// After the `-`, a mapping's first entry goes on the same line and the rest are lined up with it
+fn write_item(yaml: &mut String, item: &Value, indent: usize) {
+    if let Some(scalar) = inline(item) {
+        yaml.push(' ');
+        yaml.push_str(&scalar);
+        yaml.push('\n');
+        return;
+    }
+    match item {
+        Value::Object(map) => {
+            for (i, (name, value)) in map.iter().enumerate() {
+                yaml.push_str(&" ".repeat(if i == 0 { 1 } else { indent + 2 }));
+                write_entry(yaml, name, value, indent + 2);
+            }
+        }
+        _ => {
+            yaml.push('\n');
+            write_block(yaml, item, indent + 2);
+        }
+    }
+}
+
+/// Reads the block style YAML that [`to_string`] writes, with plain scalars and comments too so
+/// that hand-edited files still work. Flow collections other than `[]` and `{}`, multi-line
+/// scalars, anchors and tags aren't supported.
+pub fn from_str<T: DeserializeOwned>(yaml: &str) -> Result<T> {
+    let mut lines: Vec<Line> = yaml
+        .lines()
+        .enumerate()
+        .filter_map(|(i, text)| {
+            let content = text.trim_start_matches(' ');
+            (!content.is_empty() && !content.starts_with('#') && content != "---").then(|| Line {
+                number: i + 1,
+                indent: text.len() - content.len(),
+                content: content.trim_end().to_owned(),
+            })
+        })
+        .collect();
+    let value = match lines.first() {
+        None => Value::Null,
+        Some(first) if lines.len() == 1 && !is_block_start(&first.content) => {
+            scalar(&first.content).with_context(|| format!("line {}", first.number))?
+        }
+        Some(first) => {
+            let indent = first.indent;
+            let mut position = 0;
+            let value = block(&mut lines, &mut position, indent)?;
+            if let Some(line) = lines.get(position) {
+                bail!("line {}: unexpected indentation", line.number);
+            }
+            value
+        }
+    };
+    Ok(serde_json::from_value(value)?)
+}
+
+struct Line {
+    /// 1-based, for errors
+    number: usize,
+    indent: usize,
+    content: String,
+}
+
+fn is_block_start(content: &str) -> bool {
+    content == "-" || content.starts_with("- ") || split_entry(content).is_some()
+}
+
+/// A JSON value at the start of `text` and the rest of it, for quoted strings and flow scalars
+fn json_prefix(text: &str) -> Option<(Value, &str)> {
+    let mut values = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+    let value = values.next()?.ok()?;
+    Some((value, &text[values.byte_offset()..]))
+}
+
+/// `key: value` into the key and the possibly empty value
+fn split_entry(content: &str) -> Option<(String, &str)> {
+    if content.starts_with('"') {
+        let (Value::String(key), rest) = json_prefix(content)? else {
+            return None;
+        };
+        let value = rest.strip_prefix(':')?;
+        (value.is_empty() || value.starts_with(' ')).then(|| (key, value.trim_start()))
+    } else {
+        let (key, value) = content
+            .split_once(": ")
+            .or_else(|| content.strip_suffix(':').map(|key| (key, "")))?;
+        (!key.is_empty() && !key.starts_with(['-', '[', '{'])).then(|| (key.to_owned(), value))
+    }
+}
+
+fn scalar(text: &str) -> Result<Value> {
+    // Comments after a plain scalar, quoted ones are handled by their JSON parsing
+    let text = match text.find(" #") {
+        Some(i) if !text.starts_with('"') => text[..i].trim_end(),
+        _ => text,
+    };
+    Ok(match text {
+        "" | "null" | "~" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "[]" => Value::Array(Vec::new()),
+        "{}" => Value::Object(serde_json::Map::new()),
+        _ if text.starts_with('"') => match json_prefix(text) {
+            Some((value @ Value::String(_), rest))
+                if rest.is_empty() || rest.trim_start().starts_with('#') =>
+            {
+                value
+            }
+            _ => bail!("invalid quoted string {text}"),
+        },
+        _ if text.starts_with(['[', '{', '\'', '&', '*', '!', '|', '>']) => {
+            bail!("unsupported YAML syntax `{text}`")
+        }
+        _ => match serde_json::from_str::<serde_json::Number>(text) {
+            Ok(number) => Value::Number(number),
+            Err(_) => Value::String(text.to_owned()),
+        },
+    })
+}
+
+/// The mapping or sequence whose lines start at `indent`
+fn block(lines: &mut [Line], position: &mut usize, indent: usize) -> Result<Value> {
+    let is_sequence = {
+        let content = &lines[*position].content;
+        content == "-" || content.starts_with("- ")
+    };
+    if is_sequence {
+        let mut items = Vec::new();
+        while let Some(line) = lines.get_mut(*position)
+            && line.indent == indent
+            && (line.content == "-" || line.content.starts_with("- "))
+        {
+            let rest = line.content[1..].trim_start().to_owned();
+            if rest.is_empty() {
+                *position += 1;
+                items.push(nested(lines, position, indent, true)?);
+            } else if is_block_start(&rest) {
+                // Lined up with the entries after it, as if the `-` were indentation
+                line.indent += line.content.len() - rest.len();
+                line.content = rest;
+                let item_indent = line.indent;
+                items.push(block(lines, position, item_indent)?);
+            } else {
+                let number = line.number;
+                *position += 1;
+                items.push(scalar(&rest).with_context(|| format!("line {number}"))?);
+            }
+        }
+        return Ok(Value::Array(items));
+    }
+
+    let mut map = serde_json::Map::new();
+    while let Some(line) = lines.get(*position)
+        && line.indent == indent
+    {
+        let number = line.number;
+        let (key, value) = split_entry(&line.content)
+            .with_context(|| format!("line {number}: expected `key: value`"))?;
+        let value = value.to_owned();
+        *position += 1;
+        let value = if value.is_empty() {
+            nested(lines, position, indent, false)?
+        } else {
+            scalar(&value).with_context(|| format!("line {number}"))?
+        };
+        if map.insert(key.clone(), value).is_some() {
+            bail!("line {number}: duplicate key `{key}`");
+        }
+    }
+    Ok(Value::Object(map))
+}
+
+/// The value of an entry or item with nothing after it on its line, `null` unless the next line
+/// is indented further, or is a sequence lined up with a mapping's key
+fn nested(
+    lines: &mut [Line],
+    position: &mut usize,
+    indent: usize,
+    in_sequence: bool,
+) -> Result<Value> {
+    match lines.get(*position) {
+        Some(next)
+            if next.indent > indent
+                || (!in_sequence
+                    && next.indent == indent
+                    && (next.content == "-" || next.content.starts_with("- "))) =>
+        {
+            let indent = next.indent;
+            block(lines, position, indent)
+        }
+        _ => Ok(Value::Null),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_string() {
+        let value = serde_json::json!({
+            "dry_run": true,
+            "source": { "name": "Album: \"Deluxe\"", "upc": null },
+            "matched": [
+                { "position": "1", "warnings": ["a", "b"] },
+                { "position": "2", "warnings": [] },
+            ],
+            "skipped": [],
+            "nested": [[1, 2]],
+            "a key": 1,
+        });
+        assert_eq!(
+            to_string(&value).unwrap(),
+            "\"a key\": 1\n\
+             dry_run: true\n\
+             matched:\n\
+             \x20 - position: \"1\"\n\
+             \x20   warnings:\n\
+             \x20     - \"a\"\n\
+             \x20     - \"b\"\n\
+             \x20 - position: \"2\"\n\
+             \x20   warnings: []\n\
+             nested:\n\
+             \x20 -\n\
+             \x20   - 1\n\
+             \x20   - 2\n\
+             skipped: []\n\
+             source:\n\
+             \x20 name: \"Album: \\\"Deluxe\\\"\"\n\
+             \x20 upc: null\n",
+        );
+        assert_eq!(to_string(&"text").unwrap(), "\"text\"\n");
+    }
+
+    #[test]
+    fn test_from_str() {
+        let value = serde_json::json!({
+            "dry_run": true,
+            "source": { "name": "Album: \"Deluxe\" #1", "upc": null },
+            "matched": [
+                { "position": "1", "warnings": ["a", "b"] },
+                { "position": "2", "warnings": [] },
+            ],
+            "nested": [[1, 2], {}],
+            "a key": -1.5,
+        });
+        assert_eq!(
+            from_str::<Value>(&to_string(&value).unwrap()).unwrap(),
+            value
+        );
+        assert_eq!(from_str::<String>("\"text\"\n").unwrap(), "text");
+
+        // As edited by hand
+        assert_eq!(
+            from_str::<Value>(
+                "# Reviewed\n\
+                 version: 1\n\
+                 pairs:\n\
+                 - name: Album  # the deluxe one\n\
+                 \x20 id: \"2\"\n\
+                 skipped:\n",
+            )
+            .unwrap(),
+            serde_json::json!({
+                "version": 1,
+                "pairs": [{ "name": "Album", "id": "2" }],
+                "skipped": null,
+            }),
+        );
+        assert!(from_str::<Value>("a: [1, 2]\n").is_err());
+        assert!(from_str::<Value>("a: 1\n  b: 2\n").is_err());
+        assert!(from_str::<Value>("a: 1\na: 2\n").is_err());
+    }
+}